@@ -1,7 +1,24 @@
 use crate::alert::{self, Alert};
-use crate::event::{AppEvent, ConnectionEvent, Event, EventHandler, PingEvent};
+use crate::audit::{AuditEntry, AuditKind, AuditLog, AuditOutcome};
+use crate::clock::{Clock, SystemClock};
+use crate::config_editor::ConfigEditor;
+use crate::event::{
+    AppEvent, ConnectionEvent, Event, EventHandler, NotificationReplyEvent, NotificationsEvent,
+    PingEvent, RuleReply, SubscribeEvent,
+};
+use crate::event_log::EventLog;
+use crate::firewall_view::{self, FirewallDraft, FirewallRow, FirewallView};
+use crate::history::{self, HistoryJournal, Record, RecordKind};
+use crate::history_view::{HISTORY_QUERY_LIMIT, HistoryView};
+use crate::keymap::{KeyAction, Keymap};
+use crate::node::{Node, NodeRegistry};
+use crate::notification::{
+    DaemonCommand, NotificationRequest, NotificationTracker, PendingNotification, RuleOperation,
+};
+use crate::opensnitch_json::OpenSnitchDaemonConfig;
 use crate::opensnitch_proto::pb;
-use crate::server::OpenSnitchUIServer;
+use crate::policy::Policy;
+use crate::server::{BindAddress, OpenSnitchUIServer};
 use crossterm::event::MouseEvent;
 use ratatui::layout::{Position, Rect};
 use ratatui::{
@@ -11,13 +28,17 @@ use ratatui::{
 
 use crate::constants;
 use crate::operator_util::{self, PresetCombination};
+use crate::passwd;
+use crate::rule_editor::RuleEditor;
+use crate::rules_view::{self, RulesView};
+use crate::stats_view::StatsView;
+use crate::theme::Theme;
+use crate::ui;
 
-use std::collections::VecDeque;
-use std::net::SocketAddr;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
 use std::str::FromStr;
-use std::sync::Arc;
-use tokio::sync::{Mutex, mpsc};
-use tonic::Status;
+use tokio::sync::mpsc;
 
 /// Application.
 #[derive(Debug)]
@@ -28,14 +49,10 @@ pub struct App {
     events: EventHandler,
     /// Server
     server: OpenSnitchUIServer,
-    /// Channel sender to generate notifications for a daemon towards.
-    /// The sender handle gets replaced to the latest client connection.
-    /// Race protection enabled by the mutex.
-    notification_sender: Arc<Mutex<mpsc::Sender<Result<pb::Notification, Status>>>>,
-    /// Rule sender.
-    rule_sender: mpsc::Sender<pb::Rule>,
-    /// gRPC server IP and port to bind to.
-    bind_address: SocketAddr,
+    /// Rule sender, replies are routed to the `AskRule` request matching their ID.
+    rule_sender: mpsc::Sender<RuleReply>,
+    /// gRPC server address to bind to.
+    bind_address: BindAddress,
     /// The duration up to which app waits for user to make a disposition
     /// (allow/deny) on a trapped connection.
     connection_disposition_timeout: std::time::Duration,
@@ -45,6 +62,18 @@ pub struct App {
     tui_state: TuiState,
     /// Shared **mutable** state between app driver and TUI rendering.
     tui_mut_state: TuiMutState,
+    /// Time source for expiry and staleness checks.
+    clock: Box<dyn Clock>,
+    /// Journal of connections, decisions, alerts and events, if enabled.
+    history: Option<HistoryJournal>,
+    /// Audit log of dispositions and rule operations, if enabled.
+    audit_log: Option<AuditLog>,
+    /// Whether trapped connections are answered from `policy` without a terminal.
+    headless: bool,
+    /// Policy answering matching trapped connections before they're shown.
+    policy: Option<Policy>,
+    /// How long alerts are shown for.
+    alert_retention: std::time::Duration,
 }
 
 /// Primary "screens" in TUI.
@@ -52,6 +81,13 @@ pub struct App {
 pub enum TuiScreen {
     Main,
     Help,
+    Rules,
+    ConnectionDetails,
+    Stats,
+    EventLog,
+    History,
+    Firewall,
+    DaemonConfig,
 }
 
 /// Shared state between TUI and app driver.
@@ -59,79 +95,79 @@ pub enum TuiScreen {
 pub struct TuiState {
     /// Currently rendered scren.
     pub current_screen: TuiScreen,
-    /// Rx Pings from daemon - internal only.
-    rx_pings: u64,
-    /// Peer (`OpenSnitch` daemon) address.
-    pub peer: Option<std::net::SocketAddr>,
-    /// Latest stats to present to UI.
-    pub current_stats: Option<pb::Statistics>,
+    /// Connected `OpenSnitch` daemons and their latest stats.
+    pub nodes: NodeRegistry,
     /// Vector of alerts
     pub current_alerts: VecDeque<alert::Alert>,
     /// Alert list head in UI.
     pub alert_list_render_offset: usize,
-    /// Info on the current connection awaiting a rule determination.
-    pub current_connection: Option<ConnectionEvent>,
+    /// Queue of connections awaiting a rule determination, oldest first.
+    pub pending_connections: VecDeque<ConnectionEvent>,
+    /// Index into `pending_connections` of the connection currently selected by user.
+    pub selected_connection: usize,
     /// Default action to be sent to connected daemons.
     pub default_action: constants::DefaultAction,
     /// Temporary rule lifetime.
     pub temp_rule_lifetime: constants::Duration,
-    /// UI footer `Controls` list.
-    pub controls: Vec<Controls>,
+    /// Actions shown as clickable controls in the UI footer.
+    pub controls: Vec<KeyAction>,
+    /// Main screen key bindings.
+    pub keymap: Keymap,
+    /// Colors used for rendering.
+    pub theme: Theme,
+    /// Rules screen sorting, filtering and selection.
+    pub rules_view: RulesView,
+    /// Notifications sent to daemons awaiting a reply.
+    pub notifications: NotificationTracker,
+    /// Rule editor overlay on the main screen, if open.
+    pub rule_editor: Option<RuleEditor>,
+    /// Stats screen focus and sorting.
+    pub stats_view: StatsView,
+    /// User names by uid, from the local passwd file.
+    pub users: HashMap<u32, String>,
+    /// Connection events reported by nodes, and the event log screen's state.
+    pub event_log: EventLog,
+    /// History screen filtering and the journal records it shows.
+    pub history_view: HistoryView,
+    /// Firewall screen selection and status.
+    pub firewall_view: FirewallView,
+    /// Command awaiting confirmation on the main screen, with the key of the node it's for.
+    pub confirm_command: Option<(String, DaemonCommand)>,
+    /// Daemon config screen form, if opened for a node.
+    pub config_editor: Option<ConfigEditor>,
 }
 
 /// Shared mutable state between both TUI and app driver.
 #[derive(Debug, Default)]
 pub struct TuiMutState {
-    /// Connection panel area as determined by ratatui lib.
+    /// Pending connection list area as determined by ratatui lib.
     pub connection_area: Rect,
+    /// Index of the first pending connection rendered in the list area.
+    pub connection_list_offset: usize,
     /// Controls footer area as determined by ratatui lib.
     pub controls_area: Rect,
+    /// Line scrolled to on the connection details screen, clamped when rendering.
+    pub details_scroll: usize,
 }
 
-/// Enum of controls and any associated data at the footer of TUI.
-#[derive(Clone, Debug, Copy)]
-pub enum Controls {
-    Quit,
-    AllowTemp(constants::Duration),
-    DenyTemp(constants::Duration),
-    AllowForever,
-    DenyForever,
-    Help,
-}
-
-impl Controls {
-    /// Get keybinding string.
-    #[must_use]
-    pub fn get_keybind_str(&self) -> &str {
-        // Add in whitespace margins here for easy math later.
-        match self {
-            Self::Quit => " Ctrl+C ",
-            Self::AllowTemp(_) => " A ",
-            Self::DenyTemp(_) => " D ",
-            Self::AllowForever => " J ",
-            Self::DenyForever => " L ",
-            Self::Help => " H ",
-        }
-    }
-
-    /// Get the "control"/description.
-    #[must_use]
-    pub fn get_control_str(&self) -> String {
-        // Add in whitespace margins here for easy math later.
-        match self {
-            Self::Quit => String::from(" Quit "),
-            Self::AllowTemp(duration) => format!(" Allow {} ", duration.get_str()),
-            Self::DenyTemp(duration) => format!(" Deny {} ", duration.get_str()),
-            Self::AllowForever => String::from(" Allow Forever "),
-            Self::DenyForever => String::from(" Deny Forever "),
-            Self::Help => String::from(" Help "),
-        }
-    }
-
-    /// Return the sum of keybind strlen and control strlen.
+impl TuiState {
+    /// Key hint and label of a footer control.
+    /// Both have whitespace margins, for easy math later.
     #[must_use]
-    pub fn get_button_width(&self) -> usize {
-        self.get_keybind_str().len() + self.get_control_str().len()
+    pub fn control_strs(&self, action: KeyAction) -> (String, String) {
+        let label = match action {
+            KeyAction::Quit => String::from("Quit"),
+            KeyAction::AllowTemp => format!("Allow {}", self.temp_rule_lifetime.get_str()),
+            KeyAction::DenyTemp => format!("Deny {}", self.temp_rule_lifetime.get_str()),
+            KeyAction::AllowForever => String::from("Allow Forever"),
+            KeyAction::DenyForever => String::from("Deny Forever"),
+            KeyAction::Help => String::from("Help"),
+            action => action.description().to_string(),
+        };
+        (
+            format!(" {} ", self.keymap.key(action)),
+            format!(" {label} "),
+        )
     }
 }
 
@@ -141,23 +177,15 @@ impl App {
     /// Returns an error for invalid input arg.
     #[allow(clippy::missing_panics_doc)]
     pub fn new(
-        bind_string: &String,
+        bind_string: &str,
         default_action_in: &String,
         temp_rule_lifetime: &String,
         connection_disposition_timeout_in: &u64,
         rule_presets: &str,
+        socket_mode: Option<&str>,
+        socket_owner: Option<&str>,
     ) -> Result<Self, String> {
-        if bind_string.starts_with("unix") {
-            return Err(String::from("Unix domain sockets not supported"));
-        }
-        let maybe_bind_addr = bind_string.parse::<SocketAddr>();
-        if maybe_bind_addr.is_err() {
-            return Err(format!(
-                "Error parsing bind address '{}' : {}",
-                bind_string,
-                maybe_bind_addr.unwrap_err()
-            ));
-        }
+        let bind_address = BindAddress::new(bind_string, socket_mode, socket_owner)?;
 
         let maybe_default_action = constants::DefaultAction::new(default_action_in);
         if maybe_default_action.is_err() {
@@ -190,65 +218,109 @@ impl App {
         let events_handler = EventHandler::new();
         let server = OpenSnitchUIServer::default();
 
-        // Hold a dummy sender channel until a client actually connects to server and swaps in a usable
-        // sender handle.
-        let (dummy_notification_sender, _) = mpsc::channel(1);
+        // Hold a dummy sender channel until the server is spawned and takes the receiver.
         let (dummy_rule_sender, _) = mpsc::channel(1);
 
         let controls = vec![
-            Controls::Quit,
-            Controls::AllowTemp(maybe_temp_rule_lifetime.clone().unwrap()),
-            Controls::DenyTemp(maybe_temp_rule_lifetime.clone().unwrap()),
-            Controls::AllowForever,
-            Controls::DenyForever,
-            Controls::Help,
+            KeyAction::Quit,
+            KeyAction::AllowTemp,
+            KeyAction::DenyTemp,
+            KeyAction::AllowForever,
+            KeyAction::DenyForever,
+            KeyAction::Help,
         ];
 
         Ok(Self {
             running: true,
             events: events_handler,
             server,
-            notification_sender: Arc::new(Mutex::new(dummy_notification_sender)),
             rule_sender: dummy_rule_sender,
-            bind_address: maybe_bind_addr.unwrap(),
+            bind_address,
             connection_disposition_timeout,
             preset_combo,
             tui_state: TuiState {
                 current_screen: TuiScreen::Main,
-                rx_pings: 0,
-                peer: None,
-                current_stats: None,
+                nodes: NodeRegistry::default(),
                 current_alerts: VecDeque::new(),
                 alert_list_render_offset: 0,
-                current_connection: None,
+                pending_connections: VecDeque::new(),
+                selected_connection: 0,
                 default_action: maybe_default_action.unwrap(),
                 temp_rule_lifetime: maybe_temp_rule_lifetime.unwrap(),
                 controls,
+                keymap: Keymap::default(),
+                theme: Theme::default(),
+                rules_view: RulesView::default(),
+                notifications: NotificationTracker::default(),
+                rule_editor: None,
+                stats_view: StatsView::default(),
+                users: passwd::load_users(std::path::Path::new(passwd::PASSWD_PATH)),
+                event_log: EventLog::default(),
+                history_view: HistoryView::default(),
+                firewall_view: FirewallView::default(),
+                confirm_command: None,
+                config_editor: None,
             },
-            tui_mut_state: TuiMutState {
-                connection_area: Rect::default(),
-                controls_area: Rect::default(),
-            },
+            tui_mut_state: TuiMutState::default(),
+            clock: Box::new(SystemClock),
+            history: None,
+            audit_log: None,
+            headless: false,
+            policy: None,
+            alert_retention: std::time::Duration::from_secs(
+                crate::config::DEFAULT_ALERT_RETENTION_SECS,
+            ),
         })
     }
 
-    /// Run the application's main loop.
-    /// # Errors
-    /// Doesn't nominally return any errors at runtime.
-    /// # Panics
-    /// Largely upon runtime invariant violation, could be fixed in future versions.
-    pub async fn run(mut self, mut terminal: DefaultTerminal) -> color_eyre::Result<()> {
-        // Rule receiver gets borrowed by the server
-        let (rule_sender, rule_receiver) = mpsc::channel(1);
+    /// Use `keymap` for the main screen's key bindings.
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.tui_state.keymap = keymap;
+    }
+
+    /// Render with `theme` instead of the default one.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.tui_state.theme = theme;
+    }
+
+    /// Show alerts for `retention` instead of the default.
+    pub fn set_alert_retention(&mut self, retention: std::time::Duration) {
+        self.alert_retention = retention;
+    }
+
+    /// Answer trapped connections matching `policy` without asking the user.
+    pub fn set_policy(&mut self, policy: Policy) {
+        self.policy = Some(policy);
+    }
+
+    /// Answer every trapped connection without asking the user, falling through to the default
+    /// action when the policy doesn't match. Use with [`App::run_headless`].
+    pub fn enable_headless(&mut self) {
+        self.headless = true;
+    }
+
+    /// Spawn the gRPC server, which takes the receiving end of the rule channel.
+    fn spawn_server(&mut self) -> color_eyre::Result<()> {
+        let (rule_sender, rule_receiver) = mpsc::channel(128);
         self.rule_sender = rule_sender;
         self.server.spawn_and_run(
-            self.bind_address,
+            &self.bind_address,
             self.events.sender.clone(),
-            &self.notification_sender,
             rule_receiver,
             self.tui_state.default_action,
             self.connection_disposition_timeout,
-        );
+        )?;
+        Ok(())
+    }
+
+    /// Run the application's main loop.
+    /// # Errors
+    /// Doesn't nominally return any errors at runtime.
+    /// # Panics
+    /// Largely upon runtime invariant violation, could be fixed in future versions.
+    pub async fn run(mut self, mut terminal: DefaultTerminal) -> color_eyre::Result<()> {
+        self.events.spawn(true);
+        self.spawn_server()?;
         // Only need a draw if:
         // * This is the first cycle (see default value below)
         // * Tick resulted in a meaningful state update
@@ -277,15 +349,7 @@ impl App {
                 },
                 Event::App(app_event) => {
                     draw_needed = true;
-                    match *app_event {
-                        AppEvent::Update(stats) => self.update_stats(stats),
-                        AppEvent::Alert(alert) => {
-                            self.tui_state.current_alerts.push_back(alert.clone());
-                        }
-                        AppEvent::AskRule(evt) => self.update_connection(evt),
-                        AppEvent::TestNotify => self.test_notify().await,
-                        AppEvent::Quit => self.quit(),
-                    }
+                    self.handle_app_event(*app_event);
                 }
             }
             if draw_needed {
@@ -299,66 +363,594 @@ impl App {
                 draw_needed = false;
             }
         }
+        if let BindAddress::Unix(path, _) = &self.bind_address {
+            let _ = std::fs::remove_file(path);
+        }
+        Ok(())
+    }
+
+    /// Run the application without a terminal until interrupted or terminated,
+    /// answering trapped connections from the policy.
+    /// # Errors
+    /// If the server can't be spawned.
+    pub async fn run_headless(mut self) -> color_eyre::Result<()> {
+        self.events.spawn(false);
+        self.spawn_server()?;
+        let sender = self.events.sender.clone();
+        tokio::spawn(async move {
+            if let Ok(mut sigterm) =
+                tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = sigterm.recv() => {}
+                }
+            } else {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+            let _ = sender.send(Event::App(Box::new(AppEvent::Quit)));
+        });
+        while self.running {
+            match self.events.next().await? {
+                Event::Tick => {
+                    self.tick();
+                }
+                Event::Crossterm(_) => {}
+                Event::App(app_event) => self.handle_app_event(*app_event),
+            }
+        }
+        if let BindAddress::Unix(path, _) = &self.bind_address {
+            let _ = std::fs::remove_file(path);
+        }
         Ok(())
     }
 
+    /// Handles events from the gRPC server and key handlers.
+    fn handle_app_event(&mut self, app_event: AppEvent) {
+        match app_event {
+            AppEvent::Update(stats) => self.update_stats(stats),
+            AppEvent::Alert(alert) => self.push_alert(alert),
+            AppEvent::AskRule(evt) => self.update_connection(evt),
+            AppEvent::Subscribe(evt) => self.update_node_config(evt),
+            AppEvent::NotificationsOpened(evt) => self.open_notifications(evt),
+            AppEvent::NotificationsClosed(node, stream) => {
+                self.close_notifications(&node, stream);
+            }
+            AppEvent::NotificationReply(evt) => self.handle_notification_reply(&evt),
+            AppEvent::Quit => self.quit(),
+        }
+    }
+
     /// Handles key events and updates the state of [`App`].
     /// # Errors
     /// Not really...
     pub fn handle_key_events(&mut self, key_event: KeyEvent) -> color_eyre::Result<()> {
         // TODO: might be possible to use some trait magic to avoid repetitive screen matching?
         match self.tui_state.current_screen {
-            TuiScreen::Main => match key_event.code {
+            TuiScreen::Main if self.tui_state.rule_editor.is_some() => {
+                self.handle_editor_key_event(key_event);
+            }
+            TuiScreen::Main if self.tui_state.confirm_command.is_some() => {
+                if let Some((key, command)) = self.tui_state.confirm_command.take()
+                    && matches!(key_event.code, KeyCode::Char('y' | 'Y'))
+                {
+                    self.request_daemon_command(&key, command);
+                }
+            }
+            TuiScreen::Main => {
+                if let Some(action) = self.tui_state.keymap.action(key_event) {
+                    self.handle_key_action(action);
+                }
+            }
+            TuiScreen::Help => match key_event.code {
                 KeyCode::Char('c' | 'C') if key_event.modifiers == KeyModifiers::CONTROL => {
                     self.events.send(AppEvent::Quit);
                 }
-                KeyCode::Char('t' | 'T') => self.events.send(AppEvent::TestNotify),
-                KeyCode::Char('a' | 'A') => {
-                    self.make_and_send_rule(
-                        constants::Action::Allow,
-                        self.tui_state.temp_rule_lifetime,
-                    );
+                KeyCode::Esc => {
+                    self.set_tui_screen(TuiScreen::Main);
                 }
-                KeyCode::Char('d' | 'D') => {
-                    self.make_and_send_rule(
-                        constants::Action::Deny,
-                        self.tui_state.temp_rule_lifetime,
-                    );
+                _ => {}
+            },
+            TuiScreen::Rules => self.handle_rules_key_event(key_event),
+            TuiScreen::ConnectionDetails => self.handle_details_key_event(key_event),
+            TuiScreen::Stats => self.handle_stats_key_event(key_event),
+            TuiScreen::EventLog => self.handle_event_log_key_event(key_event),
+            TuiScreen::History => self.handle_history_key_event(key_event),
+            TuiScreen::Firewall => self.handle_firewall_key_event(key_event),
+            TuiScreen::DaemonConfig => self.handle_config_key_event(key_event),
+        }
+        Ok(())
+    }
+
+    /// Performs a main screen action, whether bound to a key or clicked in the footer.
+    fn handle_key_action(&mut self, action: KeyAction) {
+        match action {
+            KeyAction::Quit => self.events.send(AppEvent::Quit),
+            KeyAction::AllowTemp => {
+                self.make_and_send_rule(
+                    constants::Action::Allow,
+                    self.tui_state.temp_rule_lifetime,
+                );
+            }
+            KeyAction::DenyTemp => {
+                self.make_and_send_rule(constants::Action::Deny, self.tui_state.temp_rule_lifetime);
+            }
+            KeyAction::AllowForever => {
+                self.make_and_send_rule(constants::Action::Allow, constants::Duration::Always);
+            }
+            KeyAction::DenyForever => {
+                self.make_and_send_rule(constants::Action::Deny, constants::Duration::Always);
+            }
+            KeyAction::Help => {
+                self.set_tui_screen(TuiScreen::Help);
+            }
+            KeyAction::EditRule => self.open_rule_editor(),
+            KeyAction::Inspect => {
+                if self.selected_connection().is_some() {
+                    self.tui_mut_state.details_scroll = 0;
+                    self.set_tui_screen(TuiScreen::ConnectionDetails);
                 }
-                KeyCode::Char('j' | 'J') => {
-                    self.make_and_send_rule(constants::Action::Allow, constants::Duration::Always);
+            }
+            KeyAction::NextNode => self.tui_state.nodes.select_next(),
+            KeyAction::Stats => {
+                self.set_tui_screen(TuiScreen::Stats);
+            }
+            KeyAction::Rules => {
+                self.set_tui_screen(TuiScreen::Rules);
+            }
+            KeyAction::EventLog => {
+                self.set_tui_screen(TuiScreen::EventLog);
+            }
+            KeyAction::History => {
+                self.load_history();
+                self.set_tui_screen(TuiScreen::History);
+            }
+            KeyAction::Firewall => {
+                self.tui_state
+                    .firewall_view
+                    .clamp_selection(&self.tui_state.nodes);
+                self.set_tui_screen(TuiScreen::Firewall);
+            }
+            KeyAction::ToggleInterception => {
+                self.confirm_daemon_command(|node| {
+                    if node.interception_paused {
+                        DaemonCommand::EnableInterception
+                    } else {
+                        DaemonCommand::DisableInterception
+                    }
+                });
+            }
+            KeyAction::ToggleFirewall => {
+                self.confirm_daemon_command(|node| {
+                    if node
+                        .config
+                        .as_ref()
+                        .is_some_and(|config| config.is_firewall_running)
+                    {
+                        DaemonCommand::DisableFirewall
+                    } else {
+                        DaemonCommand::EnableFirewall
+                    }
+                });
+            }
+            KeyAction::StopDaemon => {
+                self.confirm_daemon_command(|_| DaemonCommand::Stop);
+            }
+            KeyAction::DaemonConfig => self.open_config_editor(),
+            KeyAction::PrevConnection => {
+                self.tui_state.selected_connection =
+                    self.tui_state.selected_connection.saturating_sub(1);
+            }
+            KeyAction::NextConnection => {
+                self.tui_state.selected_connection = std::cmp::min(
+                    self.tui_state.selected_connection.saturating_add(1),
+                    self.tui_state.pending_connections.len().saturating_sub(1),
+                );
+            }
+            KeyAction::AlertsUp => {
+                self.tui_state.alert_list_render_offset =
+                    self.tui_state.alert_list_render_offset.saturating_sub(1);
+            }
+            KeyAction::AlertsDown => {
+                self.tui_state.alert_list_render_offset = std::cmp::min(
+                    self.tui_state.alert_list_render_offset.saturating_add(1),
+                    self.tui_state.current_alerts.len().saturating_sub(1),
+                );
+            }
+        }
+    }
+
+    /// Handles key events on the rules screen.
+    fn handle_rules_key_event(&mut self, key_event: KeyEvent) {
+        if key_event.code == KeyCode::Char('c') && key_event.modifiers == KeyModifiers::CONTROL {
+            self.events.send(AppEvent::Quit);
+            return;
+        }
+        let view = &mut self.tui_state.rules_view;
+        if view.editing_filter {
+            match key_event.code {
+                KeyCode::Char(c) => view.filter.push(c),
+                KeyCode::Backspace => {
+                    view.filter.pop();
                 }
-                KeyCode::Char('l' | 'L') => {
-                    self.make_and_send_rule(constants::Action::Deny, constants::Duration::Always);
+                KeyCode::Enter | KeyCode::Esc => view.editing_filter = false,
+                _ => {}
+            }
+            view.selected = 0;
+            return;
+        }
+        if view.confirm_delete {
+            view.confirm_delete = false;
+            if let KeyCode::Char('y' | 'Y') = key_event.code {
+                self.request_rule_operation(RuleOperation::Delete, |_| {});
+            } else {
+                view.status = Some(String::from("Delete cancelled"));
+            }
+            return;
+        }
+        match key_event.code {
+            KeyCode::Esc => {
+                self.set_tui_screen(TuiScreen::Main);
+            }
+            KeyCode::Char('/') => view.editing_filter = true,
+            KeyCode::Char('s' | 'S') => view.sort_key = view.sort_key.next(),
+            KeyCode::Char('n' | 'N') => {
+                self.tui_state.nodes.select_next();
+                self.tui_state.rules_view.selected = 0;
+            }
+            KeyCode::Char('e' | 'E') => {
+                self.request_rule_operation(RuleOperation::Enable, |rule| rule.enabled = true);
+            }
+            KeyCode::Char('d' | 'D') => {
+                self.request_rule_operation(RuleOperation::Disable, |rule| rule.enabled = false);
+            }
+            KeyCode::Char('a' | 'A') => {
+                self.request_rule_operation(RuleOperation::Change, |rule| {
+                    rule.action = rules_view::next_rule_action(&rule.action)
+                        .get_str()
+                        .to_string();
+                });
+            }
+            KeyCode::Char('p' | 'P') => {
+                self.request_rule_operation(RuleOperation::Change, |rule| {
+                    rule.precedence = !rule.precedence;
+                });
+            }
+            KeyCode::Char('x' | 'X') | KeyCode::Delete => {
+                view.confirm_delete = view
+                    .selected_rule(self.tui_state.nodes.selected_rules())
+                    .is_some();
+            }
+            KeyCode::Up => view.selected = view.selected.saturating_sub(1),
+            KeyCode::Down => {
+                view.selected = view.selected.saturating_add(1);
+                self.tui_state
+                    .rules_view
+                    .clamp_selection(self.tui_state.nodes.selected_rules());
+            }
+            _ => {}
+        }
+    }
+
+    /// Handles key events on the connection details screen.
+    fn handle_details_key_event(&mut self, key_event: KeyEvent) {
+        let scroll = &mut self.tui_mut_state.details_scroll;
+        match key_event.code {
+            KeyCode::Char('c' | 'C') if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.events.send(AppEvent::Quit);
+            }
+            KeyCode::Esc => {
+                self.set_tui_screen(TuiScreen::Main);
+            }
+            KeyCode::Up => *scroll = scroll.saturating_sub(1),
+            KeyCode::Down => *scroll = scroll.saturating_add(1),
+            KeyCode::PageUp => *scroll = scroll.saturating_sub(10),
+            KeyCode::PageDown => *scroll = scroll.saturating_add(10),
+            KeyCode::Home => *scroll = 0,
+            KeyCode::Left => {
+                self.tui_state.selected_connection =
+                    self.tui_state.selected_connection.saturating_sub(1);
+                *scroll = 0;
+            }
+            KeyCode::Right if !self.tui_state.pending_connections.is_empty() => {
+                self.tui_state.selected_connection = std::cmp::min(
+                    self.tui_state.selected_connection.saturating_add(1),
+                    self.tui_state.pending_connections.len() - 1,
+                );
+                *scroll = 0;
+            }
+            _ => {}
+        }
+    }
+
+    /// Handles key events on the stats screen.
+    fn handle_stats_key_event(&mut self, key_event: KeyEvent) {
+        let view = &mut self.tui_state.stats_view;
+        match key_event.code {
+            KeyCode::Char('c' | 'C') if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.events.send(AppEvent::Quit);
+            }
+            KeyCode::Esc => {
+                self.set_tui_screen(TuiScreen::Main);
+            }
+            KeyCode::Tab | KeyCode::Right => view.focus_next(),
+            KeyCode::BackTab | KeyCode::Left => view.focus_prev(),
+            KeyCode::Char('s' | 'S') => view.cycle_sort(),
+            KeyCode::Char('w' | 'W') => view.window = view.window.next(),
+            KeyCode::Char('n' | 'N') => self.tui_state.nodes.select_next(),
+            _ => {}
+        }
+    }
+
+    /// Handles key events on the event log screen.
+    fn handle_event_log_key_event(&mut self, key_event: KeyEvent) {
+        if key_event.code == KeyCode::Char('c') && key_event.modifiers == KeyModifiers::CONTROL {
+            self.events.send(AppEvent::Quit);
+            return;
+        }
+        let log = &mut self.tui_state.event_log;
+        if log.editing_filter {
+            match key_event.code {
+                KeyCode::Char(c) => log.filter.push(c),
+                KeyCode::Backspace => {
+                    log.filter.pop();
                 }
-                KeyCode::Char('h' | 'H') => {
-                    self.set_tui_screen(TuiScreen::Help);
+                KeyCode::Enter | KeyCode::Esc => log.editing_filter = false,
+                _ => {}
+            }
+            log.selected = 0;
+            return;
+        }
+        match key_event.code {
+            KeyCode::Esc => {
+                self.set_tui_screen(TuiScreen::Main);
+            }
+            KeyCode::Char('/') => log.editing_filter = true,
+            KeyCode::Char('p' | 'P' | ' ') => {
+                log.toggle_pause();
+                log.clamp_selection();
+            }
+            KeyCode::Up => log.selected = log.selected.saturating_sub(1),
+            KeyCode::Down => {
+                log.selected = log.selected.saturating_add(1);
+                log.clamp_selection();
+            }
+            KeyCode::PageUp => log.selected = log.selected.saturating_sub(10),
+            KeyCode::PageDown => {
+                log.selected = log.selected.saturating_add(10);
+                log.clamp_selection();
+            }
+            KeyCode::Home => log.selected = 0,
+            _ => {}
+        }
+    }
+
+    /// Handles key events on the history screen.
+    fn handle_history_key_event(&mut self, key_event: KeyEvent) {
+        if key_event.code == KeyCode::Char('c') && key_event.modifiers == KeyModifiers::CONTROL {
+            self.events.send(AppEvent::Quit);
+            return;
+        }
+        let view = &mut self.tui_state.history_view;
+        if view.editing_filter {
+            match key_event.code {
+                KeyCode::Char(c) => view.filter.push(c),
+                KeyCode::Backspace => {
+                    view.filter.pop();
                 }
-                KeyCode::Up => {
-                    self.tui_state.alert_list_render_offset =
-                        self.tui_state.alert_list_render_offset.saturating_sub(1);
+                KeyCode::Enter | KeyCode::Esc => view.editing_filter = false,
+                _ => {}
+            }
+            view.selected = 0;
+            return;
+        }
+        match key_event.code {
+            KeyCode::Esc => {
+                self.set_tui_screen(TuiScreen::Main);
+            }
+            KeyCode::Char('/') => view.editing_filter = true,
+            KeyCode::Char('k' | 'K') => view.cycle_kind(),
+            KeyCode::Char('r' | 'R') => self.load_history(),
+            KeyCode::Up => view.selected = view.selected.saturating_sub(1),
+            KeyCode::Down => {
+                view.selected = view.selected.saturating_add(1);
+                view.clamp_selection();
+            }
+            KeyCode::PageUp => view.selected = view.selected.saturating_sub(10),
+            KeyCode::PageDown => {
+                view.selected = view.selected.saturating_add(10);
+                view.clamp_selection();
+            }
+            KeyCode::Home => view.selected = 0,
+            _ => {}
+        }
+    }
+
+    /// Handles key events on the firewall screen.
+    fn handle_firewall_key_event(&mut self, key_event: KeyEvent) {
+        if key_event.code == KeyCode::Char('c') && key_event.modifiers == KeyModifiers::CONTROL {
+            self.events.send(AppEvent::Quit);
+            return;
+        }
+        let view = &mut self.tui_state.firewall_view;
+        if let Some(spec) = &mut view.new_rule {
+            view.status = None;
+            match key_event.code {
+                KeyCode::Char(c) => spec.push(c),
+                KeyCode::Backspace => {
+                    spec.pop();
                 }
-                KeyCode::Down => {
-                    if !self.tui_state.current_alerts.is_empty() {
-                        self.tui_state.alert_list_render_offset = std::cmp::min(
-                            self.tui_state.alert_list_render_offset.saturating_add(1),
-                            self.tui_state.current_alerts.len() - 1,
-                        );
+                KeyCode::Tab => view.next_template(),
+                KeyCode::Enter => self.add_firewall_rule(),
+                KeyCode::Esc => view.new_rule = None,
+                _ => {}
+            }
+            return;
+        }
+        if let Some(key) = view.confirm_reload.take() {
+            if matches!(key_event.code, KeyCode::Char('y' | 'Y')) {
+                self.request_firewall_reload(&key);
+            }
+            return;
+        }
+        if view.confirm_send {
+            view.confirm_send = false;
+            if matches!(key_event.code, KeyCode::Char('y' | 'Y')) {
+                self.send_firewall_draft();
+            }
+            return;
+        }
+        let shift = key_event.modifiers.contains(KeyModifiers::SHIFT);
+        match key_event.code {
+            KeyCode::Esc => {
+                self.set_tui_screen(TuiScreen::Main);
+            }
+            KeyCode::Char('n' | 'N') => {
+                self.tui_state.nodes.select_next();
+                self.tui_state.firewall_view.selected = 0;
+            }
+            KeyCode::Char('r' | 'R') => self.confirm_firewall_reload(),
+            KeyCode::Char(' ') => {
+                self.edit_firewall(|firewall, row| {
+                    firewall_view::toggle_rule(firewall, row).then_some(row)
+                });
+            }
+            KeyCode::Char('x' | 'X') | KeyCode::Delete => {
+                self.edit_firewall(|firewall, row| {
+                    firewall_view::remove_rule(firewall, row).then_some(row)
+                });
+            }
+            KeyCode::Up | KeyCode::Down if shift => {
+                let down = key_event.code == KeyCode::Down;
+                self.edit_firewall(|firewall, row| firewall_view::move_rule(firewall, row, down));
+            }
+            KeyCode::Char('a' | 'A') if view.shown(&self.tui_state.nodes).is_some() => {
+                view.status = None;
+                view.next_template();
+            }
+            KeyCode::Char('s' | 'S') => {
+                view.confirm_send = self
+                    .tui_state
+                    .nodes
+                    .selected_key()
+                    .is_some_and(|key| view.draft_for(key).is_some());
+            }
+            KeyCode::Char('u' | 'U') if view.draft.is_some() => {
+                view.draft = None;
+                view.status = Some(String::from("Discarded changes"));
+                view.clamp_selection(&self.tui_state.nodes);
+            }
+            KeyCode::Up => view.selected = view.selected.saturating_sub(1),
+            KeyCode::PageUp => view.selected = view.selected.saturating_sub(10),
+            KeyCode::Home => view.selected = 0,
+            KeyCode::Down | KeyCode::PageDown => {
+                let step = if key_event.code == KeyCode::Down {
+                    1
+                } else {
+                    10
+                };
+                view.selected = view.selected.saturating_add(step);
+                view.clamp_selection(&self.tui_state.nodes);
+            }
+            _ => {}
+        }
+    }
+
+    /// Handles key events on the daemon config screen.
+    fn handle_config_key_event(&mut self, key_event: KeyEvent) {
+        if key_event.code == KeyCode::Char('c') && key_event.modifiers == KeyModifiers::CONTROL {
+            self.events.send(AppEvent::Quit);
+            return;
+        }
+        let Some(editor) = self.tui_state.config_editor.as_mut() else {
+            self.set_tui_screen(TuiScreen::Main);
+            return;
+        };
+        if editor.text.is_some() {
+            editor.status = None;
+            match key_event.code {
+                KeyCode::Char(c) => editor.push_char(c),
+                KeyCode::Backspace => editor.pop_char(),
+                KeyCode::Enter => {
+                    if let Err(err) = editor.commit_text() {
+                        editor.status = Some(err);
                     }
                 }
+                KeyCode::Esc => editor.cancel_text(),
                 _ => {}
-            },
-            TuiScreen::Help => match key_event.code {
-                KeyCode::Char('c' | 'C') if key_event.modifiers == KeyModifiers::CONTROL => {
-                    self.events.send(AppEvent::Quit);
-                }
-                KeyCode::Esc => {
-                    self.set_tui_screen(TuiScreen::Main);
-                }
+            }
+            return;
+        }
+        if editor.confirm_send || editor.confirm_discard {
+            let confirmed = matches!(key_event.code, KeyCode::Char('y' | 'Y'));
+            let discard = editor.confirm_discard;
+            editor.confirm_send = false;
+            editor.confirm_discard = false;
+            match (confirmed, discard) {
+                (true, false) => self.send_daemon_config(),
+                (true, true) => self.open_next_config_editor(),
+                _ => {}
+            }
+            return;
+        }
+        match key_event.code {
+            KeyCode::Esc => {
+                self.set_tui_screen(TuiScreen::Main);
+            }
+            KeyCode::Up => editor.focus_prev(),
+            KeyCode::Down => editor.focus_next(),
+            KeyCode::Char(' ') | KeyCode::Enter => {
+                editor.status = None;
+                editor.toggle();
+            }
+            KeyCode::Char('u' | 'U') if editor.is_modified() => {
+                editor.config = editor.original.clone();
+                editor.status = Some(String::from("Discarded changes"));
+            }
+            KeyCode::Char('s' | 'S') if editor.is_modified() => editor.confirm_send = true,
+            KeyCode::Char('s' | 'S') => editor.status = Some(String::from("No changes to send")),
+            KeyCode::Char('n' | 'N') if editor.is_modified() => editor.confirm_discard = true,
+            KeyCode::Char('n' | 'N') => self.open_next_config_editor(),
+            _ => {}
+        }
+    }
+
+    /// Handles key events while the rule editor is open.
+    fn handle_editor_key_event(&mut self, key_event: KeyEvent) {
+        if key_event.code == KeyCode::Char('c') && key_event.modifiers == KeyModifiers::CONTROL {
+            self.events.send(AppEvent::Quit);
+            return;
+        }
+        let Some(editor) = self.tui_state.rule_editor.as_mut() else {
+            return;
+        };
+        if editor.editing_text {
+            match key_event.code {
+                KeyCode::Char(c) => editor.push_char(c),
+                KeyCode::Backspace => editor.pop_char(),
+                KeyCode::Enter | KeyCode::Esc => editor.stop_editing(),
                 _ => {}
+            }
+            return;
+        }
+        match key_event.code {
+            KeyCode::Esc => self.tui_state.rule_editor = None,
+            KeyCode::Up => editor.focus_prev(),
+            KeyCode::Down => editor.focus_next(),
+            KeyCode::Char(' ') | KeyCode::Enter | KeyCode::Left | KeyCode::Right => {
+                editor.toggle();
+            }
+            KeyCode::Char('s' | 'S') => match editor.build_rule() {
+                Ok(rule) => {
+                    let id = editor.connection_id;
+                    self.tui_state.rule_editor = None;
+                    self.send_rule(id, rule);
+                    self.remove_connection(id);
+                }
+                Err(err) => editor.status = Some(err),
             },
+            _ => {}
         }
-        Ok(())
     }
 
     /// Handles mouse events and updates the state of [`App`].
@@ -367,60 +959,54 @@ impl App {
     pub fn handle_mouse_events(&mut self, mouse_event: MouseEvent) -> color_eyre::Result<bool> {
         // TODO: might be possible to use some trait magic to avoid repetitive screen matching?
         match self.tui_state.current_screen {
+            TuiScreen::Main if self.tui_state.rule_editor.is_some() => Ok(false),
             TuiScreen::Main => {
+                let click = Position::new(mouse_event.column, mouse_event.row);
+                if self.tui_mut_state.connection_area.contains(click) {
+                    // Pending connection list is rendered one item per row from the top of its area.
+                    let row = usize::from(mouse_event.row - self.tui_mut_state.connection_area.y)
+                        + self.tui_mut_state.connection_list_offset;
+                    if row < self.tui_state.pending_connections.len() {
+                        self.tui_state.selected_connection = row;
+                        return Ok(true);
+                    }
+                    return Ok(false);
+                }
+
                 let mut clicked_control = None;
-                if self
-                    .tui_mut_state
-                    .controls_area
-                    .contains(Position::new(mouse_event.column, mouse_event.row))
-                {
+                if self.tui_mut_state.controls_area.contains(click) {
                     // Controls footer faux button group is left aligned in TUI, so use a simple
                     // accumulator pattern to determine which control overlaps with click coords.
                     let mut column_accumulator: usize = 0;
                     for control in &self.tui_state.controls {
+                        let (key, label) = self.tui_state.control_strs(*control);
                         let lb = column_accumulator;
-                        column_accumulator += control.get_button_width();
+                        column_accumulator += key.len() + label.len();
                         let rb = column_accumulator;
                         if usize::from(mouse_event.column) >= lb
                             && usize::from(mouse_event.column) < rb
                         {
-                            clicked_control = Some(control);
+                            clicked_control = Some(*control);
                             break;
                         }
                     }
                 }
 
                 if let Some(control) = clicked_control {
-                    match control {
-                        Controls::Quit => self.events.send(AppEvent::Quit),
-                        Controls::AllowTemp(duration) => {
-                            self.make_and_send_rule(constants::Action::Allow, *duration);
-                        }
-                        Controls::DenyTemp(duration) => {
-                            self.make_and_send_rule(constants::Action::Deny, *duration);
-                        }
-                        Controls::AllowForever => {
-                            self.make_and_send_rule(
-                                constants::Action::Allow,
-                                constants::Duration::Always,
-                            );
-                        }
-                        Controls::DenyForever => {
-                            self.make_and_send_rule(
-                                constants::Action::Deny,
-                                constants::Duration::Always,
-                            );
-                        }
-                        Controls::Help => {
-                            self.set_tui_screen(TuiScreen::Help);
-                        }
-                    }
+                    self.handle_key_action(control);
                     Ok(true)
                 } else {
                     Ok(false)
                 }
             }
-            TuiScreen::Help => Ok(false),
+            TuiScreen::Help
+            | TuiScreen::Rules
+            | TuiScreen::ConnectionDetails
+            | TuiScreen::Stats
+            | TuiScreen::EventLog
+            | TuiScreen::History
+            | TuiScreen::Firewall
+            | TuiScreen::DaemonConfig => Ok(false),
         }
     }
 
@@ -435,13 +1021,65 @@ impl App {
     /// Returns whether meaningful change occured, which should trigger a re-render of terminal.
     pub fn tick(&mut self) -> bool {
         let mut did_work = false;
-        let now = std::time::SystemTime::now();
-        if let Some(conn) = &self.tui_state.current_connection
-            && now >= conn.expiry_ts
+        let now = self.clock.now();
+        // The daemon's gRPC calls should time out and take some default action
+        // in the absence of a Rule created by us.
+        let (pending, expired): (VecDeque<ConnectionEvent>, VecDeque<ConnectionEvent>) = self
+            .tui_state
+            .pending_connections
+            .drain(..)
+            .partition(|conn| now < conn.expiry_ts);
+        self.tui_state.pending_connections = pending;
+        if !expired.is_empty() {
+            self.clamp_selected_connection();
+            did_work = true;
+        }
+        for evt in expired {
+            self.record_timeout(now, &evt);
+        }
+        if let Some(editor) = &self.tui_state.rule_editor
+            && !self
+                .tui_state
+                .pending_connections
+                .iter()
+                .any(|conn| conn.id == editor.connection_id)
         {
-            // The daemon's gRPC call should time out and take some default action
-            // in the absence of a Rule created by us.
-            self.clear_connection();
+            self.tui_state.rule_editor = None;
+            self.push_alert(Alert::create_simple(
+                now,
+                "Connection expired before the edited rule was sent",
+            ));
+            did_work = true;
+        }
+
+        // Flag nodes that stopped pinging.
+        let mut newly_disconnected = Vec::new();
+        for (key, node) in self.tui_state.nodes.iter_mut() {
+            if !node.disconnected && node.missed_pings(now) {
+                node.disconnected = true;
+                newly_disconnected.push(node.display_name(key));
+            }
+        }
+        for name in newly_disconnected {
+            self.push_alert(Alert {
+                timestamp: now,
+                priority: alert::Priority::High,
+                r#type: alert::Type::Warning,
+                what: alert::What::Generic,
+                msg: format!(
+                    "No ping from {name} in {} intervals, marking it disconnected",
+                    crate::node::MISSED_PINGS_THRESHOLD
+                ),
+            });
+            did_work = true;
+        }
+
+        // Give up on notifications the daemon never replied to.
+        for pending in self.tui_state.notifications.expire(now) {
+            self.audit_notification(&pending, AuditOutcome::NoReply, "");
+            let msg = format!("No reply: {}", pending.describe());
+            self.push_rule_alert(alert::Type::Warning, &msg);
+            self.set_notification_status(&pending.request, msg);
             did_work = true;
         }
 
@@ -449,19 +1087,18 @@ impl App {
         match self.tui_state.current_alerts.front() {
             None => {}
             Some(alert) => {
-                if let Ok(age) = now.duration_since(alert.timestamp) {
-                    // Max alert duration is 60s, could be adjustable if needed
-                    if age.as_secs() >= 60 {
-                        // Pop this off but also correct the render offset in case it's set to back of list.
-                        if self.tui_state.alert_list_render_offset
-                            == (self.tui_state.current_alerts.len() - 1)
-                        {
-                            self.tui_state.alert_list_render_offset =
-                                self.tui_state.alert_list_render_offset.saturating_sub(1);
-                        }
-                        self.tui_state.current_alerts.pop_front();
-                        did_work = true;
+                if let Ok(age) = now.duration_since(alert.timestamp)
+                    && age >= self.alert_retention
+                {
+                    // Pop this off but also correct the render offset in case it's set to back of list.
+                    if self.tui_state.alert_list_render_offset
+                        == (self.tui_state.current_alerts.len() - 1)
+                    {
+                        self.tui_state.alert_list_render_offset =
+                            self.tui_state.alert_list_render_offset.saturating_sub(1);
                     }
+                    self.tui_state.current_alerts.pop_front();
+                    did_work = true;
                 }
             }
         }
@@ -469,136 +1106,968 @@ impl App {
         did_work
     }
 
+    /// Audit and journal a connection left to the daemon's default action.
+    fn record_timeout(&mut self, now: std::time::SystemTime, evt: &ConnectionEvent) {
+        let default_action = self.tui_state.default_action.get_str().to_string();
+        let mut audit_entry = AuditEntry::new(
+            now,
+            &evt.node,
+            AuditKind::Timeout,
+            Some(&evt.connection),
+            None,
+            AuditOutcome::DefaultAction,
+        );
+        audit_entry.action.clone_from(&default_action);
+        self.append_audit(audit_entry);
+        let summary = format!(
+            "Unanswered, daemon applies default action {default_action}: {}",
+            ui::format_connection_summary(&evt.connection)
+        );
+        self.log_headless(&evt.node, &summary);
+        self.append_history(&Record::new(
+            now,
+            RecordKind::Timeout,
+            &evt.node,
+            summary,
+            serde_json::json!({
+                "connection_id": evt.id,
+                "default_action": default_action,
+                "connection": evt.connection,
+            }),
+        ));
+    }
+
     /// Set running to false to quit the application.
     pub fn quit(&mut self) {
         self.running = false;
     }
 
     /// Update peer stats from incoming Ping payload.
-    /// TODO: Set a staleness threshold so we can clearly flag a disconnected peer.
-    /// For now, this can be simulated locally via:
+    /// Staleness can be simulated locally via:
     /// iptables -A INPUT -p tcp --dport 50051 -j DROP
     /// iptables -D INPUT -p tcp --dport 50051 -j DROP
-    pub fn update_stats(&mut self, ping_event: PingEvent) {
-        self.tui_state.rx_pings = self.tui_state.rx_pings.saturating_add(1);
-        self.tui_state.peer = ping_event.peer;
-        self.tui_state.current_stats = Some(ping_event.stats);
-    }
-
-    /// Server to daemon notifications under development.
-    pub async fn test_notify(&mut self) {
-        let sender = self.notification_sender.lock().await;
-        let _ = sender
-            .send(Ok(pb::Notification {
-                id: 123,
-                client_name: String::default(),
-                server_name: String::default(),
-                r#type: 14, // This is a task stop notification.
-                data: String::from("Test notification triggers an error"),
-                rules: Vec::default(),
-                sys_firewall: None,
-            }))
-            .await;
-    }
-
-    /// Update connection holder with latest inbound event.
-    pub fn update_connection(&mut self, evt: ConnectionEvent) {
-        self.tui_state.current_connection = Some(evt);
+    pub fn update_stats(&mut self, mut ping_event: PingEvent) {
+        let now = self.clock.now();
+        // Events are only kept in the log, the daemon resends its recent ones on every ping.
+        let events = std::mem::take(&mut ping_event.stats.events);
+        let added = self.tui_state.event_log.record(&ping_event.node, events);
+        if self.history.is_some() {
+            let records: Vec<Record> = self
+                .tui_state
+                .event_log
+                .newest(added)
+                .map(|entry| {
+                    Record::new(
+                        now,
+                        RecordKind::Event,
+                        &entry.node,
+                        format!(
+                            "{} by {}: {}",
+                            entry.action(),
+                            entry.rule_name(),
+                            entry
+                                .event
+                                .connection
+                                .as_ref()
+                                .map(ui::format_connection_summary)
+                                .unwrap_or_default()
+                        ),
+                        serde_json::to_value(&entry.event).unwrap_or_default(),
+                    )
+                })
+                .collect();
+            for record in records {
+                self.append_history(&record);
+            }
+        }
+        let node = self.tui_state.nodes.get_or_insert(&ping_event.node);
+        node.rx_pings = node.rx_pings.saturating_add(1);
+        node.peer = ping_event.peer;
+        node.history.push(now, &ping_event.stats);
+        node.prev_stats = node.stats.replace(ping_event.stats);
+        node.last_ping = Some(now);
+        if node.disconnected {
+            node.disconnected = false;
+            let msg = format!("{} is pinging again", node.display_name(&ping_event.node));
+            self.push_alert(Alert::create_simple(now, &msg));
+        }
     }
 
-    /// Clear connection holder.
-    pub fn clear_connection(&mut self) {
-        self.tui_state.current_connection = None;
+    /// Keep the sender of a node's notifications stream, replacing that of an older stream.
+    pub fn open_notifications(&mut self, evt: NotificationsEvent) {
+        let node = self.tui_state.nodes.get_or_insert(&evt.node);
+        node.notification_sender = Some(evt.sender);
+        node.notification_stream = evt.stream;
     }
 
-    /// Generate a rule for the current connection being handled by this server.
-    /// Returns `none` if there is no current connection.
-    /// * `is_allow`: Whether the rule for this connection should allow or deny the flow.
-    fn make_rule(
-        &self,
-        action: constants::Action,
-        duration: constants::Duration,
-    ) -> Option<pb::Rule> {
-        // Noop if there's no connection trapped.
-        let conn: &pb::Connection = &self.tui_state.current_connection.as_ref()?.connection;
-
-        // Fill in all the inputs to generator with best effort.
-        let inputs = operator_util::GeneratedOperatorsInputs {
-            user_id: Some(conn.user_id),
-            ppath: Some(conn.process_path.clone()),
-            dst_ip: Some(conn.dst_ip.clone()),
-            dst_port: Some(conn.dst_port),
-            protocol: Some(conn.protocol.clone()),
-            hostname: if conn.dst_host.is_empty() {
-                None
-            } else {
-                Some(conn.dst_host.clone())
-            },
-        };
+    /// Forget a node's notifications stream and peer once the stream closes.
+    /// Streams replaced by a newer one of the same node are ignored.
+    pub fn close_notifications(&mut self, key: &str, stream: u64) {
+        if let Some(node) = self.tui_state.nodes.get_mut(key)
+            && node.notification_stream == stream
+        {
+            node.notification_sender = None;
+            node.peer = None;
+            node.disconnected = true;
+            for pending in self.tui_state.notifications.resolve_stops(key) {
+                self.audit_notification(&pending, AuditOutcome::Applied, "");
+                let msg = format!("Done: {}", pending.describe());
+                self.push_rule_alert(alert::Type::Info, &msg);
+            }
+        }
+    }
+
+    /// Record the configuration a node shared on subscribing.
+    pub fn update_node_config(&mut self, subscribe_event: SubscribeEvent) {
+        // Pings may come before the subscription, keyed by the host alone.
+        if let Some(peer) = subscribe_event
+            .node
+            .strip_prefix(subscribe_event.config.name.as_str())
+            .and_then(|peer| peer.strip_prefix('@'))
+        {
+            self.tui_state.nodes.rename(peer, &subscribe_event.node);
+        }
+        let node = self.tui_state.nodes.get_or_insert(&subscribe_event.node);
+        node.peer = subscribe_event.peer;
+        node.config = Some(subscribe_event.config);
+        self.tui_state
+            .firewall_view
+            .clamp_selection(&self.tui_state.nodes);
+    }
+
+    /// Ask the selected node to apply an operation to the selected rule.
+    /// `edit` turns the selected rule into the one sent along with the notification.
+    fn request_rule_operation(
+        &mut self,
+        operation: RuleOperation,
+        edit: impl FnOnce(&mut pb::Rule),
+    ) {
+        let Some((key, node)) = self.tui_state.nodes.selected() else {
+            self.tui_state.rules_view.status = Some(String::from("No node selected"));
+            return;
+        };
+        let Some(mut rule) = self
+            .tui_state
+            .rules_view
+            .selected_rule(self.tui_state.nodes.selected_rules())
+            .cloned()
+        else {
+            self.tui_state.rules_view.status = Some(String::from("No rule selected"));
+            return;
+        };
+        let Some(sender) = node.notification_sender.clone() else {
+            let msg = format!("Node {key} has no open notifications stream");
+            self.push_rule_alert(alert::Type::Warning, &msg);
+            self.tui_state.rules_view.status = Some(msg);
+            return;
+        };
+        let key = key.to_string();
+        edit(&mut rule);
+        let name = rule.name.clone();
+        let mut audit_entry = AuditEntry::new(
+            self.clock.now(),
+            &key,
+            AuditKind::RuleOperation,
+            None,
+            Some(&rule),
+            AuditOutcome::Requested,
+        );
+        audit_entry.operation = operation.get_str().to_string();
+        match self.tui_state.notifications.send(
+            &key,
+            &sender,
+            NotificationRequest::Rule(operation, rule),
+            self.clock.now(),
+        ) {
+            Ok(_) => {
+                self.append_audit(audit_entry);
+                self.tui_state.rules_view.status = Some(format!(
+                    "Sent: {} rule {name} on {key}",
+                    operation.get_str()
+                ));
+            }
+            Err(err) => {
+                self.push_rule_alert(alert::Type::Error, &err);
+                self.tui_state.rules_view.status = Some(err);
+            }
+        }
+    }
+
+    /// Apply an edit to the selected row of the selected node's firewall, as a draft.
+    /// `edit` returns the row to select afterwards, or `None` if there was nothing to edit.
+    fn edit_firewall(
+        &mut self,
+        edit: impl FnOnce(&mut pb::SysFirewall, FirewallRow) -> Option<FirewallRow>,
+    ) {
+        let view = &mut self.tui_state.firewall_view;
+        let (Some(key), Some(shown)) = (
+            self.tui_state.nodes.selected_key(),
+            view.shown(&self.tui_state.nodes),
+        ) else {
+            view.status = Some(String::from("No system firewall to edit"));
+            return;
+        };
+        let mut firewall = shown.clone();
+        let Some(row) = view
+            .selected_row(&firewall)
+            .and_then(|row| edit(&mut firewall, row))
+        else {
+            view.status = Some(String::from("Nothing to edit at the selected row"));
+            return;
+        };
+        view.select_row(&firewall, row);
+        // Edits undone by hand leave nothing to send.
+        view.draft =
+            (self.tui_state.nodes.selected_firewall() != Some(&firewall)).then(|| FirewallDraft {
+                node: key.to_string(),
+                firewall,
+            });
+    }
+
+    /// Add the rule being typed in after the selected row, closing the prompt unless the
+    /// rule is invalid.
+    fn add_firewall_rule(&mut self) {
+        let view = &mut self.tui_state.firewall_view;
+        let Some(spec) = view.new_rule.as_deref() else {
+            return;
+        };
+        match firewall_view::parse_rule(spec) {
+            Ok(rule) => {
+                view.new_rule = None;
+                self.edit_firewall(|firewall, row| firewall_view::insert_rule(firewall, row, rule));
+            }
+            Err(err) => view.status = Some(err),
+        }
+    }
+
+    /// Ask a node to apply and save its system firewall configuration again,
+    /// e.g. after its rules were flushed behind the daemon's back.
+    fn request_firewall_reload(&mut self, key: &str) {
+        let Some(firewall) = self
+            .tui_state
+            .nodes
+            .get(key)
+            .and_then(|node| node.config.as_ref())
+            .and_then(|config| config.system_firewall.clone())
+        else {
+            self.tui_state.firewall_view.status =
+                Some(format!("Node {key} reported no system firewall"));
+            return;
+        };
+        let request = NotificationRequest::ReloadFirewall {
+            firewall,
+            rollback: None,
+        };
+        let msg = match self.send_firewall_request(key, request) {
+            Ok(()) => format!("Sent: reload firewall on {key}"),
+            Err(err) => err,
+        };
+        self.tui_state.firewall_view.status = Some(msg);
+    }
+
+    /// Ask to confirm reapplying the firewall the selected node reported.
+    fn confirm_firewall_reload(&mut self) {
+        let view = &mut self.tui_state.firewall_view;
+        match self.tui_state.nodes.selected() {
+            None => view.status = Some(String::from("No node selected")),
+            Some((key, node))
+                if node
+                    .config
+                    .as_ref()
+                    .is_none_or(|config| config.system_firewall.is_none()) =>
+            {
+                view.status = Some(format!("Node {key} reported no system firewall"));
+            }
+            Some((key, _)) => view.confirm_reload = Some(key.to_string()),
+        }
+    }
+
+    /// Send the draft of the selected node's firewall, to be rolled back to the firewall
+    /// the node reported if it fails to apply it.
+    /// The draft is kept until the node confirms it.
+    fn send_firewall_draft(&mut self) {
+        let nodes = &self.tui_state.nodes;
+        let (Some(key), Some(previous)) = (nodes.selected_key(), nodes.selected_firewall()) else {
+            return;
+        };
+        let Some(firewall) = self.tui_state.firewall_view.draft_for(key).cloned() else {
+            return;
+        };
+        let key = key.to_string();
+        let request = NotificationRequest::ReloadFirewall {
+            firewall,
+            rollback: Some(previous.clone()),
+        };
+        let msg = match self.send_firewall_request(&key, request) {
+            Ok(()) => format!("Sent: change firewall on {key}"),
+            Err(err) => err,
+        };
+        self.tui_state.firewall_view.status = Some(msg);
+    }
+
+    /// Send a firewall request to a node and audit it, alerting on failure.
+    /// # Errors
+    /// If the node has no open notifications stream, or it's backed up.
+    fn send_firewall_request(
+        &mut self,
+        key: &str,
+        request: NotificationRequest,
+    ) -> Result<(), String> {
+        let Some(sender) = self
+            .tui_state
+            .nodes
+            .get(key)
+            .and_then(|node| node.notification_sender.clone())
+        else {
+            let msg = format!("Node {key} has no open notifications stream");
+            self.push_rule_alert(alert::Type::Warning, &msg);
+            return Err(msg);
+        };
+        let mut audit_entry = AuditEntry::new(
+            self.clock.now(),
+            key,
+            AuditKind::FirewallOperation,
+            None,
+            None,
+            AuditOutcome::Requested,
+        );
+        audit_entry.operation = request.get_str().to_string();
+        if let Err(err) = self
+            .tui_state
+            .notifications
+            .send(key, &sender, request, self.clock.now())
+        {
+            self.push_rule_alert(alert::Type::Error, &err);
+            return Err(err);
+        }
+        self.append_audit(audit_entry);
+        Ok(())
+    }
+
+    /// Open the config screen for the selected node's daemon, warning if its config
+    /// can't be parsed.
+    fn open_config_editor(&mut self) {
+        let Some((key, node)) = self.tui_state.nodes.selected() else {
+            return;
+        };
+        let json = node
+            .config
+            .as_ref()
+            .map(|config| config.config.as_str())
+            .unwrap_or_default();
+        match OpenSnitchDaemonConfig::parse(json) {
+            Ok(config) => {
+                self.tui_state.config_editor = Some(ConfigEditor::new(key, config));
+                self.set_tui_screen(TuiScreen::DaemonConfig);
+            }
+            Err(err) => {
+                let msg = format!("Node {key}: {err}");
+                self.push_rule_alert(alert::Type::Warning, &msg);
+            }
+        }
+    }
+
+    /// Select the next node and open its daemon config, dropping any edits.
+    fn open_next_config_editor(&mut self) {
+        self.tui_state.nodes.select_next();
+        self.open_config_editor();
+    }
+
+    /// Send the edited daemon config to its node, keeping the edits until confirmed.
+    fn send_daemon_config(&mut self) {
+        let Some(editor) = self.tui_state.config_editor.as_ref() else {
+            return;
+        };
+        if !editor.is_modified() {
+            self.set_config_status(String::from("No changes to send"));
+            return;
+        }
+        let key = editor.node.clone();
+        let json = match editor.config.to_json() {
+            Ok(json) => json,
+            Err(err) => {
+                self.set_config_status(err);
+                return;
+            }
+        };
+        let Some(sender) = self
+            .tui_state
+            .nodes
+            .get(&key)
+            .and_then(|node| node.notification_sender.clone())
+        else {
+            self.set_config_status(format!("Node {key} has no open notifications stream"));
+            return;
+        };
+        let mut audit_entry = AuditEntry::new(
+            self.clock.now(),
+            &key,
+            AuditKind::ConfigChange,
+            None,
+            None,
+            AuditOutcome::Requested,
+        );
+        let request = NotificationRequest::ChangeConfig(json);
+        audit_entry.operation = request.get_str().to_string();
+        match self
+            .tui_state
+            .notifications
+            .send(&key, &sender, request, self.clock.now())
+        {
+            Ok(_) => {
+                self.append_audit(audit_entry);
+                self.set_config_status(format!("Sent: change config on {key}"));
+            }
+            Err(err) => {
+                self.push_rule_alert(alert::Type::Error, &err);
+                self.set_config_status(err);
+            }
+        }
+    }
+
+    fn set_config_status(&mut self, msg: String) {
+        if let Some(editor) = self.tui_state.config_editor.as_mut() {
+            editor.status = Some(msg);
+        }
+    }
+
+    /// Ask to confirm the command `command` picks for the selected node, if any.
+    fn confirm_daemon_command(&mut self, command: impl FnOnce(&Node) -> DaemonCommand) {
+        self.tui_state.confirm_command = self
+            .tui_state
+            .nodes
+            .selected()
+            .map(|(key, node)| (key.to_string(), command(node)));
+    }
+
+    /// Send a command to the selected node, reporting how it went as an alert.
+    fn request_daemon_command(&mut self, key: &str, command: DaemonCommand) {
+        let Some(node) = self.tui_state.nodes.get(key) else {
+            let msg = format!(
+                "Node {key} disconnected, not sending {}",
+                command.describe()
+            );
+            self.push_rule_alert(alert::Type::Warning, &msg);
+            return;
+        };
+        let Some(sender) = node.notification_sender.clone() else {
+            let msg = format!("Node {key} has no open notifications stream");
+            self.push_rule_alert(alert::Type::Warning, &msg);
+            return;
+        };
+        let key = key.to_string();
+        let mut audit_entry = AuditEntry::new(
+            self.clock.now(),
+            &key,
+            AuditKind::DaemonCommand,
+            None,
+            None,
+            AuditOutcome::Requested,
+        );
+        audit_entry.operation = command.get_str().to_string();
+        match self.tui_state.notifications.send(
+            &key,
+            &sender,
+            NotificationRequest::Command(command),
+            self.clock.now(),
+        ) {
+            Ok(_) => {
+                self.append_audit(audit_entry);
+                let msg = format!("Sent: {} on {key}", command.describe());
+                self.push_rule_alert(alert::Type::Info, &msg);
+            }
+            Err(err) => self.push_rule_alert(alert::Type::Error, &err),
+        }
+    }
+
+    /// Show the outcome of a notification on the screen it was requested from.
+    fn set_notification_status(&mut self, request: &NotificationRequest, msg: String) {
+        match request {
+            NotificationRequest::Rule(..) => self.tui_state.rules_view.status = Some(msg),
+            NotificationRequest::ReloadFirewall { .. } => {
+                self.tui_state.firewall_view.status = Some(msg);
+            }
+            NotificationRequest::ChangeConfig(_) => {
+                if let Some(editor) = self.tui_state.config_editor.as_mut() {
+                    editor.status = Some(msg);
+                }
+            }
+            // Commands are sent from the main screen, where their alerts show.
+            NotificationRequest::Command(_) => {}
+        }
+    }
+
+    /// Match a daemon's reply to the notification it answers, and report the outcome.
+    pub fn handle_notification_reply(&mut self, evt: &NotificationReplyEvent) {
+        let code = evt.reply.code();
+        let Some(pending) = self.tui_state.notifications.resolve(evt.reply.id) else {
+            // Not ours or timed out already, but errors are still worth surfacing.
+            if code == pb::NotificationReplyCode::Error {
+                self.push_rule_alert(
+                    alert::Type::Error,
+                    &format!("{}: {}", evt.node, evt.reply.data),
+                );
+            }
+            return;
+        };
+        match code {
+            pb::NotificationReplyCode::Ok => {
+                self.audit_notification(&pending, AuditOutcome::Applied, "");
+            }
+            pb::NotificationReplyCode::Error => {
+                self.audit_notification(&pending, AuditOutcome::Failed, &evt.reply.data);
+            }
+        }
+        let msg = match code {
+            pb::NotificationReplyCode::Ok => {
+                if let Some(node) = self.tui_state.nodes.get_mut(&pending.node) {
+                    pending.apply(node);
+                }
+                let view = &mut self.tui_state.firewall_view;
+                if let NotificationRequest::ReloadFirewall { firewall, .. } = &pending.request
+                    && view.draft_for(&pending.node) == Some(firewall)
+                {
+                    view.draft = None;
+                }
+                if let NotificationRequest::ChangeConfig(json) = &pending.request
+                    && let Some(editor) = self.tui_state.config_editor.as_mut()
+                    && editor.node == pending.node
+                    && let Ok(config) = OpenSnitchDaemonConfig::parse(json)
+                {
+                    editor.original = config;
+                }
+                self.tui_state
+                    .rules_view
+                    .clamp_selection(self.tui_state.nodes.selected_rules());
+                self.tui_state
+                    .firewall_view
+                    .clamp_selection(&self.tui_state.nodes);
+                let msg = if evt.reply.data.is_empty() {
+                    format!("Done: {}", pending.describe())
+                } else {
+                    format!("Done: {}: {}", pending.describe(), evt.reply.data)
+                };
+                self.push_rule_alert(alert::Type::Info, &msg);
+                msg
+            }
+            pb::NotificationReplyCode::Error => {
+                let mut msg = format!("Failed: {}: {}", pending.describe(), evt.reply.data);
+                self.push_rule_alert(alert::Type::Error, &msg);
+                if let NotificationRequest::ReloadFirewall {
+                    rollback: Some(previous),
+                    ..
+                } = &pending.request
+                {
+                    let request = NotificationRequest::ReloadFirewall {
+                        firewall: previous.clone(),
+                        rollback: None,
+                    };
+                    match self.send_firewall_request(&pending.node, request) {
+                        Ok(()) => msg.push_str(", rolling back"),
+                        Err(err) => msg = format!("{msg}, unable to roll back: {err}"),
+                    }
+                }
+                msg
+            }
+        };
+        self.set_notification_status(&pending.request, msg);
+    }
+
+    /// Show an alert, and record it in the history journal.
+    fn push_alert(&mut self, alert: Alert) {
+        self.log_headless("", &format!("{:?}: {}", alert.r#type, alert.msg));
+        self.append_history(&Record::new(
+            alert.timestamp,
+            RecordKind::Alert,
+            "",
+            format!("{:?}: {}", alert.r#type, alert.msg),
+            serde_json::json!({
+                "priority": format!("{:?}", alert.priority),
+                "type": format!("{:?}", alert.r#type),
+                "what": format!("{:?}", alert.what),
+                "msg": alert.msg,
+            }),
+        ));
+        self.tui_state.current_alerts.push_back(alert);
+    }
+
+    /// Print a line to stdout in headless mode, where there's no TUI to show it.
+    fn log_headless(&self, node: &str, msg: &str) {
+        if !self.headless {
+            return;
+        }
+        let ts = self
+            .clock
+            .now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or_default();
+        if node.is_empty() {
+            println!("{} {msg}", history::format_utc(ts));
+        } else {
+            println!("{} [{node}] {msg}", history::format_utc(ts));
+        }
+    }
+
+    /// Append a record to the history journal, if enabled.
+    /// A journal that fails to write is disabled rather than retried on every record.
+    fn append_history(&mut self, record: &Record) {
+        let Some(journal) = self.history.as_mut() else {
+            return;
+        };
+        if let Err(err) = journal.append(record) {
+            self.history = None;
+            self.tui_state.current_alerts.push_back(Alert {
+                timestamp: self.clock.now(),
+                priority: alert::Priority::High,
+                r#type: alert::Type::Error,
+                what: alert::What::Generic,
+                msg: format!("{err}, history journal disabled"),
+            });
+        }
+    }
+
+    /// Append an entry to the audit log, if enabled.
+    /// A log that fails to write is disabled rather than retried on every entry.
+    fn append_audit(&mut self, entry: AuditEntry) {
+        let Some(audit_log) = self.audit_log.as_mut() else {
+            return;
+        };
+        if let Err(err) = audit_log.append(entry) {
+            self.audit_log = None;
+            self.push_alert(Alert {
+                timestamp: self.clock.now(),
+                priority: alert::Priority::High,
+                r#type: alert::Type::Error,
+                what: alert::What::Generic,
+                msg: format!("{err}, audit log disabled"),
+            });
+        }
+    }
+
+    /// Audit how a notification sent to a node turned out.
+    fn audit_notification(
+        &mut self,
+        pending: &PendingNotification,
+        outcome: AuditOutcome,
+        detail: &str,
+    ) {
+        let kind = match pending.request {
+            NotificationRequest::Rule(..) => AuditKind::RuleOperation,
+            NotificationRequest::ReloadFirewall { .. } => AuditKind::FirewallOperation,
+            NotificationRequest::Command(_) => AuditKind::DaemonCommand,
+            NotificationRequest::ChangeConfig(_) => AuditKind::ConfigChange,
+        };
+        let mut entry = AuditEntry::new(
+            self.clock.now(),
+            &pending.node,
+            kind,
+            None,
+            pending.request.rule(),
+            outcome,
+        );
+        entry.operation = pending.request.get_str().to_string();
+        entry.detail = detail.to_string();
+        self.append_audit(entry);
+    }
+
+    /// Start auditing dispositions and rule operations to the log at `path`,
+    /// hash chaining entries if `chain` is set.
+    /// # Errors
+    /// If the log can't be opened.
+    pub fn open_audit_log(&mut self, path: &Path, chain: bool) -> Result<(), String> {
+        let user = match passwd::current_uid() {
+            Some(uid) => match self.tui_state.users.get(&uid) {
+                Some(name) => format!("{name} ({uid})"),
+                None => uid.to_string(),
+            },
+            None => std::env::var("USER").unwrap_or_default(),
+        };
+        self.audit_log = Some(AuditLog::open(path, chain, &user)?);
+        Ok(())
+    }
+
+    /// Start recording to the history journal at `path`.
+    /// # Errors
+    /// If the journal can't be opened.
+    pub fn open_history(&mut self, path: &Path) -> Result<(), String> {
+        self.history = Some(HistoryJournal::open(path)?);
+        Ok(())
+    }
+
+    /// Load the most recent journal records into the history screen.
+    fn load_history(&mut self) {
+        let view = &mut self.tui_state.history_view;
+        view.selected = 0;
+        let Some(journal) = &self.history else {
+            view.records = Vec::default();
+            view.status = Some(String::from("History is disabled, see --history-db"));
+            return;
+        };
+        match history::read_records(journal.path(), HISTORY_QUERY_LIMIT) {
+            Ok(records) => {
+                view.status = Some(format!(
+                    "Loaded {} records from {}",
+                    records.len(),
+                    journal.path().display()
+                ));
+                view.records = records;
+            }
+            Err(err) => {
+                view.records = Vec::default();
+                view.status = Some(err);
+            }
+        }
+    }
+
+    /// Add an alert about rule management.
+    fn push_rule_alert(&mut self, r#type: alert::Type, msg: &str) {
+        self.push_alert(Alert {
+            timestamp: self.clock.now(),
+            priority: alert::Priority::Low,
+            r#type,
+            what: alert::What::Rule,
+            msg: msg.to_string(),
+        });
+    }
+
+    /// Queue latest inbound connection event.
+    pub fn update_connection(&mut self, evt: ConnectionEvent) {
+        self.append_history(&Record::new(
+            self.clock.now(),
+            RecordKind::Connection,
+            &evt.node,
+            ui::format_connection_summary(&evt.connection),
+            serde_json::json!({
+                "connection_id": evt.id,
+                "connection": evt.connection,
+            }),
+        ));
+        let id = evt.id;
+        self.tui_state.pending_connections.push_back(evt);
+        if self.policy.is_some() {
+            self.answer_from_policy(id);
+        }
+    }
+
+    /// Answer a pending connection with the first matching policy rule. Unmatched connections
+    /// are left to the user, or in headless mode answered with a `once` rule of the default
+    /// action. Connections no rule can be generated for are left pending.
+    fn answer_from_policy(&mut self, id: u64) {
+        let Some(evt) = self
+            .tui_state
+            .pending_connections
+            .iter()
+            .find(|conn| conn.id == id)
+        else {
+            return;
+        };
+        let (rule, reason) = if let Some(policy_rule) = self
+            .policy
+            .as_ref()
+            .and_then(|policy| policy.find(&evt.connection))
+        {
+            (
+                policy_rule.make_rule(&evt.connection, &self.preset_combo),
+                format!("policy rule {}", policy_rule.name),
+            )
+        } else if self.headless {
+            let action = constants::Action::from(self.tui_state.default_action);
+            let operators =
+                operator_util::GeneratedOperatorsInputs::from_connection(&evt.connection)
+                    .generate_operators(&self.preset_combo);
+            let rule = (!operators.is_empty()).then(|| {
+                operator_util::build_list_rule(
+                    operator_util::default_rule_name(
+                        action,
+                        constants::Duration::Once,
+                        &evt.connection.process_path,
+                    ),
+                    String::from("No policy rule matched"),
+                    action,
+                    constants::Duration::Once,
+                    operators,
+                )
+            });
+            (rule, String::from("no policy match"))
+        } else {
+            return;
+        };
+        let node = evt.node.clone();
+        let summary = ui::format_connection_summary(&evt.connection);
+        match rule {
+            Some(rule) => {
+                let msg = format!("{} {} ({reason}): {summary}", rule.action, rule.duration);
+                if self.headless {
+                    self.log_headless(&node, &msg);
+                } else {
+                    self.push_alert(Alert {
+                        timestamp: self.clock.now(),
+                        priority: alert::Priority::Low,
+                        r#type: alert::Type::Info,
+                        what: alert::What::Connection,
+                        msg: format!("Auto-answered {msg}"),
+                    });
+                }
+                self.send_rule(id, rule);
+                self.remove_connection(id);
+            }
+            None => self.log_headless(
+                &node,
+                &format!("No rule could be generated ({reason}), waiting for timeout: {summary}"),
+            ),
+        }
+    }
+
+    /// Remove a connection from the pending queue by ID.
+    pub fn remove_connection(&mut self, id: u64) {
+        self.tui_state
+            .pending_connections
+            .retain(|conn| conn.id != id);
+        self.clamp_selected_connection();
+    }
+
+    /// Keep selection within bounds after the pending queue shrinks.
+    fn clamp_selected_connection(&mut self) {
+        self.tui_state.selected_connection = std::cmp::min(
+            self.tui_state.selected_connection,
+            self.tui_state.pending_connections.len().saturating_sub(1),
+        );
+    }
+
+    /// Currently selected connection awaiting a rule, if any.
+    #[must_use]
+    pub fn selected_connection(&self) -> Option<&ConnectionEvent> {
+        self.tui_state
+            .pending_connections
+            .get(self.tui_state.selected_connection)
+    }
+
+    /// Generate a rule for the selected connection being handled by this server.
+    /// Returns `none` if there is no pending connection.
+    /// * `is_allow`: Whether the rule for this connection should allow or deny the flow.
+    fn make_rule(
+        &self,
+        action: constants::Action,
+        duration: constants::Duration,
+    ) -> Option<pb::Rule> {
+        // Noop if there's no connection trapped.
+        let conn: &pb::Connection = &self.selected_connection()?.connection;
 
         // Do the work - generate populated operator list based on desired presets and given inputs.
-        let operators = inputs.generate_operators(&self.preset_combo);
+        let operators = operator_util::GeneratedOperatorsInputs::from_connection(conn)
+            .generate_operators(&self.preset_combo);
         if operators.is_empty() {
             return None;
         }
 
-        let action_str = action.get_str();
-        let duration = String::from(duration.get_str());
-        let pretty_proc_path = conn.process_path.clone().replace('/', "-");
-        let maybe_operator_json = serde_json::to_string(&operators);
-        // Shouldn't really happen due to serde_impl.rs, ideally something caught at build time.
-        assert!(
-            maybe_operator_json.is_ok(),
-            "Operator list JSON serialization failed: {}",
-            maybe_operator_json.unwrap_err()
-        );
-
-        Some(pb::Rule {
-            created: 0,
-            // TODO: Leading slash gets turned into double-dash, may be annoying
-            name: format!("{action_str}-{duration}-simple-via-tui-{pretty_proc_path}"),
-            description: String::default(),
-            enabled: true,
-            precedence: false,
-            nolog: false,
-            action: String::from(action_str),
+        Some(operator_util::build_list_rule(
+            operator_util::default_rule_name(action, duration, &conn.process_path),
+            String::default(),
+            action,
             duration,
-            operator: Some(pb::Operator {
-                r#type: String::from(constants::RuleType::List.get_str()),
-                operand: String::from(constants::Operand::List.get_str()),
-                data: maybe_operator_json.unwrap(),
-                sensitive: false,
-                list: operators,
-            }),
-        })
+            operators,
+        ))
     }
 
-    fn send_rule(&self, rule: pb::Rule) {
-        let send_res = self.rule_sender.try_send(rule);
-        if let Err(err) = send_res {
-            // Shouldn't really happen so bail here.
-            panic!("Unable to send rule: {err}");
+    /// Open the rule editor on the selected connection, prefilled from the rule presets.
+    fn open_rule_editor(&mut self) {
+        let Some(evt) = self.selected_connection() else {
+            self.push_alert(Alert::create_simple(
+                self.clock.now(),
+                "No connection to edit a rule for",
+            ));
+            return;
+        };
+        self.tui_state.rule_editor = Some(RuleEditor::new(
+            evt,
+            &self.preset_combo,
+            constants::Action::Allow,
+            self.tui_state.temp_rule_lifetime,
+        ));
+    }
+
+    /// Answer a pending connection with a rule, and record the decision.
+    /// A rule that can't be handed to the server is left unanswered, like a timed out
+    /// connection, for the daemon to apply its default action.
+    fn send_rule(&mut self, id: u64, rule: pb::Rule) {
+        let Some(evt) = self
+            .tui_state
+            .pending_connections
+            .iter()
+            .find(|conn| conn.id == id)
+            .cloned()
+        else {
+            self.queue_rule(id, rule);
+            return;
+        };
+        let audit_entry = AuditEntry::new(
+            self.clock.now(),
+            &evt.node,
+            AuditKind::Disposition,
+            Some(&evt.connection),
+            Some(&rule),
+            AuditOutcome::Sent,
+        );
+        let record = Record::new(
+            self.clock.now(),
+            RecordKind::Decision,
+            &evt.node,
+            format!(
+                "{} {} with {}: {}",
+                rule.action,
+                rule.duration,
+                rule.name,
+                ui::format_connection_summary(&evt.connection)
+            ),
+            serde_json::json!({
+                "connection_id": id,
+                "connection": evt.connection,
+                "rule": rule,
+            }),
+        );
+        if self.queue_rule(id, rule) {
+            self.append_history(&record);
+            self.append_audit(audit_entry);
+        } else {
+            self.record_timeout(self.clock.now(), &evt);
         }
     }
 
+    /// Hand a rule to the server for the daemon, or raise an alert if the server can't take
+    /// it. Returns whether it was queued.
+    fn queue_rule(&mut self, id: u64, rule: pb::Rule) -> bool {
+        let Err(err) = self.rule_sender.try_send(RuleReply { id, rule }) else {
+            return true;
+        };
+        let msg = format!(
+            "Unable to answer connection {id}, the daemon applies its default action: {err}"
+        );
+        self.push_rule_alert(alert::Type::Error, &msg);
+        false
+    }
+
     fn make_and_send_rule(&mut self, action: constants::Action, duration: constants::Duration) {
-        if let Some(rule) = self.make_rule(action, duration) {
-            self.send_rule(rule);
-            self.clear_connection();
+        if let Some(rule) = self.make_rule(action, duration)
+            && let Some(id) = self.selected_connection().map(|conn| conn.id)
+        {
+            self.send_rule(id, rule);
+            self.remove_connection(id);
         } else {
             // Send an alert to self that no rule was generated due to missing data.
-            self.tui_state
-                .current_alerts
-                .push_back(Alert::create_simple(
-                    std::time::SystemTime::now(),
-                    "No rule created due to lack of connection data",
-                ));
+            self.push_alert(Alert::create_simple(
+                self.clock.now(),
+                "No rule created due to lack of connection data",
+            ));
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::clock::FakeClock;
+    use crate::config_editor::ConfigField;
+    use crate::node::{MISSED_PINGS_THRESHOLD, PING_INTERVAL};
     use crate::opensnitch_proto::pb::{Connection, Rule};
     use std::time::SystemTime;
 
@@ -609,30 +2078,36 @@ mod tests {
 
     /// Simple construction test.
     #[tokio::test]
+    #[allow(clippy::unnecessary_to_owned)]
     async fn test_new() {
         let _ = App::new(
-            &"127.0.0.1:65534".to_string(),
+            "127.0.0.1:65534",
             &"deny".to_string(),
             &"12h".to_string(),
             &60,
             &"exact_dst_ip".to_string(),
+            None,
+            None,
         )
         .expect("new failed");
     }
 
     /// Test that making a rule with no "current connection" generates a noop.
     #[tokio::test]
+    #[allow(clippy::unnecessary_to_owned)]
     async fn test_make_rule_no_conn() {
         let app = App::new(
-            &"127.0.0.1:65534".to_string(),
+            "127.0.0.1:65534",
             &"deny".to_string(),
             &"12h".to_string(),
             &60,
             &"exact_dst_ip".to_string(),
+            None,
+            None,
         )
         .expect("new failed");
 
-        assert!(app.tui_state.current_connection.is_none());
+        assert!(app.tui_state.pending_connections.is_empty());
 
         let maybe_rule = app.make_rule(constants::Action::Allow, constants::Duration::Once);
         assert!(maybe_rule.is_none());
@@ -658,20 +2133,69 @@ mod tests {
         }
     }
 
+    /// Test the details screen scrolls down to the end of wrapped lines.
+    #[tokio::test]
+    async fn test_details_scroll() {
+        let mut app = App::new(
+            "127.0.0.1:65534",
+            &"deny".to_string(),
+            &"12h".to_string(),
+            &60,
+            "exact_dst_ip",
+            None,
+            None,
+        )
+        .expect("new failed");
+        let mut conn = make_fake_connection();
+        let value = format!("{}TAIL", "x".repeat(400));
+        conn.process_env = [(String::from("LONG"), value)].into();
+        app.update_connection(ConnectionEvent {
+            id: 0,
+            node: String::from("unix"),
+            connection: conn,
+            expiry_ts: SystemTime::now() + app.connection_disposition_timeout,
+        });
+        app.set_tui_screen(TuiScreen::ConnectionDetails);
+        app.tui_mut_state.details_scroll = usize::MAX;
+
+        let mut terminal =
+            ratatui::Terminal::new(ratatui::backend::TestBackend::new(40, 10)).expect("terminal");
+        terminal
+            .draw(|frame| {
+                frame.render_stateful_widget(&app.tui_state, frame.area(), &mut app.tui_mut_state);
+            })
+            .expect("draw failed");
+        let rows: Vec<String> = (0..10)
+            .map(|y| {
+                (0..40)
+                    .map(|x| terminal.backend().buffer()[(x, y)].symbol())
+                    .collect()
+            })
+            .collect();
+        assert!(rows[1].contains("TAIL"), "{rows:#?}");
+        // More than the few lines the details have before wrapping.
+        assert!(app.tui_mut_state.details_scroll > 20);
+    }
+
     /// Test that making a rule with a valid "current connection" generates something meaningful.
     #[tokio::test]
+    #[allow(clippy::unnecessary_to_owned, clippy::semicolon_if_nothing_returned)]
     async fn test_make_rule_has_conn() {
         let mut app = App::new(
-            &"127.0.0.1:65534".to_string(),
+            "127.0.0.1:65534",
             &"deny".to_string(),
             &"12h".to_string(),
             &60,
             &"exact_dst_ip".to_string(),
+            None,
+            None,
         )
         .expect("new failed");
 
         let fake_conn = make_fake_connection();
-        app.tui_state.current_connection = Some(ConnectionEvent {
+        app.update_connection(ConnectionEvent {
+            id: 0,
+            node: String::from("unix"),
             connection: fake_conn.clone(),
             expiry_ts: SystemTime::now() + app.connection_disposition_timeout,
         });
@@ -711,4 +2235,1138 @@ mod tests {
 
         assert_eq!(maybe_rule, expected_rule)
     }
+
+    /// Test that queued connections are selected, dispositioned and expired independently.
+    #[tokio::test]
+    async fn test_pending_connection_queue() {
+        let mut app = App::new(
+            "127.0.0.1:65534",
+            &"deny".to_string(),
+            &"12h".to_string(),
+            &60,
+            "exact_dst_ip",
+            None,
+            None,
+        )
+        .expect("new failed");
+        let (rule_sender, mut rule_receiver) = mpsc::channel(8);
+        app.rule_sender = rule_sender;
+
+        let now = SystemTime::now();
+        for id in 0..3 {
+            let mut conn = make_fake_connection();
+            conn.dst_ip = format!("10.0.0.{id}");
+            app.update_connection(ConnectionEvent {
+                id,
+                node: String::from("unix"),
+                connection: conn,
+                // Connection 0 has already expired.
+                expiry_ts: if id == 0 {
+                    now
+                } else {
+                    now + app.connection_disposition_timeout
+                },
+            });
+        }
+        assert_eq!(app.tui_state.pending_connections.len(), 3);
+
+        // Expire connection 0, leaving 1 and 2.
+        assert!(app.tick());
+        assert_eq!(app.tui_state.pending_connections.len(), 2);
+
+        // Disposition connection 2 and check the rule is routed with its ID.
+        app.tui_state.selected_connection = 1;
+        app.make_and_send_rule(constants::Action::Deny, constants::Duration::Once);
+        let reply = rule_receiver.try_recv().expect("missing rule reply");
+        assert_eq!(reply.id, 2);
+        assert_eq!(reply.rule.action, "deny");
+
+        // Connection 1 remains and is selected.
+        assert_eq!(app.tui_state.pending_connections.len(), 1);
+        assert_eq!(app.tui_state.selected_connection, 0);
+        assert_eq!(app.selected_connection().map(|conn| conn.id), Some(1));
+    }
+
+    /// Test a rule that can't be queued for the server is reported instead of crashing, and
+    /// its connection left to the daemon's default action.
+    #[tokio::test]
+    async fn test_rule_channel_full() {
+        let mut app = App::new(
+            "127.0.0.1:65534",
+            &"deny".to_string(),
+            &"12h".to_string(),
+            &60,
+            "exact_dst_ip",
+            None,
+            None,
+        )
+        .expect("new failed");
+        let (rule_sender, rule_receiver) = mpsc::channel(1);
+        app.rule_sender = rule_sender;
+        for id in 0..3 {
+            app.update_connection(ConnectionEvent {
+                id,
+                node: String::from("unix"),
+                connection: make_fake_connection(),
+                expiry_ts: SystemTime::now() + app.connection_disposition_timeout,
+            });
+        }
+
+        app.make_and_send_rule(constants::Action::Deny, constants::Duration::Once);
+        app.make_and_send_rule(constants::Action::Deny, constants::Duration::Once);
+        assert_eq!(app.tui_state.pending_connections.len(), 1);
+        let last_alert = |app: &App| {
+            app.tui_state
+                .current_alerts
+                .back()
+                .map(|alert| alert.msg.clone())
+                .unwrap_or_default()
+        };
+        assert!(
+            last_alert(&app)
+                .contains("the daemon applies its default action: no available capacity"),
+            "{}",
+            last_alert(&app)
+        );
+
+        // Nor does it crash once the server is gone.
+        drop(rule_receiver);
+        app.make_and_send_rule(constants::Action::Deny, constants::Duration::Once);
+        assert!(app.tui_state.pending_connections.is_empty());
+        assert!(
+            last_alert(&app).ends_with("channel closed"),
+            "{}",
+            last_alert(&app)
+        );
+    }
+
+    /// Test headless mode answers trapped connections from the policy, falling through to a
+    /// `once` rule of the default action.
+    #[tokio::test]
+    async fn test_headless_policy() {
+        let mut app = App::new(
+            "127.0.0.1:65534",
+            &"reject".to_string(),
+            &"12h".to_string(),
+            &60,
+            "exact_dst_ip",
+            None,
+            None,
+        )
+        .expect("new failed");
+        let (rule_sender, mut rule_receiver) = mpsc::channel(8);
+        app.rule_sender = rule_sender;
+        app.enable_headless();
+        app.set_policy(
+            "[[rule]]\nname = \"hello\"\nprocess_path = \"/usr/bin/*\"\ndst_ports = \"1000-2000\"\naction = \"allow\"\nduration = \"always\"\npresets = \"exact_ppath\""
+                .parse()
+                .expect("policy parse failed"),
+        );
+
+        let now = SystemTime::now();
+        let mut other = make_fake_connection();
+        other.dst_port = 22;
+        for (id, conn) in [(0, make_fake_connection()), (1, other)] {
+            app.update_connection(ConnectionEvent {
+                id,
+                node: String::from("unix"),
+                connection: conn,
+                expiry_ts: now + app.connection_disposition_timeout,
+            });
+        }
+        assert!(app.tui_state.pending_connections.is_empty());
+
+        let reply = rule_receiver.try_recv().expect("missing rule reply");
+        assert_eq!(reply.id, 0);
+        assert_eq!(reply.rule.action, "allow");
+        assert_eq!(reply.rule.duration, "always");
+        assert_eq!(reply.rule.description, "Matched policy rule hello");
+        assert!(
+            reply
+                .rule
+                .operator
+                .is_some_and(|op| op.data.contains("process.path"))
+        );
+
+        let reply = rule_receiver.try_recv().expect("missing rule reply");
+        assert_eq!(reply.id, 1);
+        assert_eq!(reply.rule.action, "reject");
+        assert_eq!(reply.rule.duration, "once");
+        assert!(rule_receiver.try_recv().is_err());
+    }
+
+    /// Test the interactive app auto-answers connections matching the policy with an alert,
+    /// leaving the rest to the user.
+    #[tokio::test]
+    async fn test_policy_prefilter() {
+        let mut app = App::new(
+            "127.0.0.1:65534",
+            &"deny".to_string(),
+            &"12h".to_string(),
+            &60,
+            "exact_dst_ip",
+            None,
+            None,
+        )
+        .expect("new failed");
+        let (rule_sender, mut rule_receiver) = mpsc::channel(8);
+        app.rule_sender = rule_sender;
+        app.set_policy(
+            "[[rule]]\nname = \"ntp\"\ndst_ports = \"123\"\nprotocol = \"udp\"\naction = \"allow\"\nduration = \"12h\""
+                .parse()
+                .expect("policy parse failed"),
+        );
+
+        let now = SystemTime::now();
+        let mut ntp = make_fake_connection();
+        ntp.protocol = String::from("udp");
+        ntp.dst_port = 123;
+        for (id, conn) in [(0, ntp), (1, make_fake_connection())] {
+            app.update_connection(ConnectionEvent {
+                id,
+                node: String::from("unix"),
+                connection: conn,
+                expiry_ts: now + app.connection_disposition_timeout,
+            });
+        }
+
+        let reply = rule_receiver.try_recv().expect("missing rule reply");
+        assert_eq!(reply.id, 0);
+        assert_eq!(reply.rule.action, "allow");
+        assert_eq!(reply.rule.duration, "12h");
+        assert!(rule_receiver.try_recv().is_err());
+        assert_eq!(app.selected_connection().map(|conn| conn.id), Some(1));
+        assert_eq!(app.tui_state.pending_connections.len(), 1);
+        let alert = app.tui_state.current_alerts.back().expect("missing alert");
+        assert!(
+            alert
+                .msg
+                .starts_with("Auto-answered allow 12h (policy rule ntp)")
+        );
+    }
+
+    /// Test rule operations from the rules screen are sent as notifications and only applied
+    /// once the daemon confirms them.
+    #[tokio::test]
+    async fn test_rule_operation_reply() {
+        let mut app = App::new(
+            "127.0.0.1:65534",
+            &"deny".to_string(),
+            &"12h".to_string(),
+            &60,
+            "exact_dst_ip",
+            None,
+            None,
+        )
+        .expect("new failed");
+        let (sender, mut receiver) = mpsc::channel(8);
+        app.update_node_config(SubscribeEvent {
+            node: String::from("unix"),
+            peer: None,
+            config: pb::ClientConfig {
+                rules: vec![Rule {
+                    name: String::from("allow-curl"),
+                    action: String::from("allow"),
+                    enabled: true,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        });
+        app.tui_state
+            .nodes
+            .get_or_insert("unix")
+            .notification_sender = Some(sender);
+        app.set_tui_screen(TuiScreen::Rules);
+
+        let press = |app: &mut App, c: char| {
+            app.handle_key_events(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+                .expect("key failed");
+        };
+        let reply = |app: &mut App, id: u64, code: pb::NotificationReplyCode| {
+            app.handle_notification_reply(&NotificationReplyEvent {
+                node: String::from("unix"),
+                reply: pb::NotificationReply {
+                    id,
+                    code: code.into(),
+                    data: String::from("oops"),
+                },
+            });
+        };
+
+        // Failed disable leaves the rule alone.
+        press(&mut app, 'd');
+        let notification = receiver.try_recv().expect("missing").expect("error");
+        assert_eq!(notification.r#type(), pb::Action::DisableRule);
+        assert!(!notification.rules[0].enabled);
+        assert!(app.tui_state.notifications.is_pending("unix", "allow-curl"));
+        reply(&mut app, notification.id, pb::NotificationReplyCode::Error);
+        assert!(app.tui_state.nodes.selected_rules()[0].enabled);
+        assert!(app.tui_state.notifications.is_empty());
+
+        // Confirmed action change is applied.
+        press(&mut app, 'a');
+        let notification = receiver.try_recv().expect("missing").expect("error");
+        assert_eq!(notification.r#type(), pb::Action::ChangeRule);
+        reply(&mut app, notification.id, pb::NotificationReplyCode::Ok);
+        assert_eq!(app.tui_state.nodes.selected_rules()[0].action, "deny");
+
+        // Delete only goes out once confirmed.
+        press(&mut app, 'x');
+        press(&mut app, 'n');
+        assert!(receiver.try_recv().is_err());
+        press(&mut app, 'x');
+        press(&mut app, 'y');
+        let notification = receiver.try_recv().expect("missing").expect("error");
+        assert_eq!(notification.r#type(), pb::Action::DeleteRule);
+        reply(&mut app, notification.id, pb::NotificationReplyCode::Ok);
+        assert!(app.tui_state.nodes.selected_rules().is_empty());
+    }
+
+    /// Test browsing a node's system firewall and reapplying it.
+    #[tokio::test]
+    async fn test_firewall_reload() {
+        let mut app = App::new(
+            "127.0.0.1:65534",
+            &"deny".to_string(),
+            &"12h".to_string(),
+            &60,
+            "exact_dst_ip",
+            None,
+            None,
+        )
+        .expect("new failed");
+        let make_firewall = |rules: usize| pb::SysFirewall {
+            enabled: true,
+            version: 1,
+            system_rules: vec![pb::FwChains {
+                rule: None,
+                chains: vec![pb::FwChain {
+                    name: String::from("input"),
+                    rules: (0..rules)
+                        .map(|idx| pb::FwRule {
+                            position: idx as u64,
+                            target: String::from("accept"),
+                            ..Default::default()
+                        })
+                        .collect(),
+                    ..Default::default()
+                }],
+            }],
+        };
+        let subscribe = |app: &mut App, rules: usize| {
+            app.update_node_config(SubscribeEvent {
+                node: String::from("unix"),
+                peer: None,
+                config: pb::ClientConfig {
+                    system_firewall: Some(make_firewall(rules)),
+                    ..Default::default()
+                },
+            });
+        };
+        let press = |app: &mut App, code: KeyCode| {
+            app.handle_key_events(KeyEvent::new(code, KeyModifiers::NONE))
+                .expect("key failed");
+        };
+        subscribe(&mut app, 3);
+        press(&mut app, KeyCode::Char('f'));
+        assert_eq!(app.tui_state.current_screen, TuiScreen::Firewall);
+        press(&mut app, KeyCode::PageDown);
+        assert_eq!(app.tui_state.firewall_view.selected, 3);
+
+        // Nothing to send to without a notifications stream.
+        press(&mut app, KeyCode::Char('r'));
+        press(&mut app, KeyCode::Char('y'));
+        assert!(app.tui_state.notifications.is_empty());
+
+        let (sender, mut receiver) = mpsc::channel(8);
+        app.tui_state
+            .nodes
+            .get_or_insert("unix")
+            .notification_sender = Some(sender);
+        // Nothing is sent unless confirmed.
+        press(&mut app, KeyCode::Char('r'));
+        assert_eq!(
+            app.tui_state.firewall_view.confirm_reload.as_deref(),
+            Some("unix")
+        );
+        press(&mut app, KeyCode::Char('n'));
+        assert!(app.tui_state.firewall_view.confirm_reload.is_none());
+        assert!(receiver.try_recv().is_err());
+        press(&mut app, KeyCode::Char('r'));
+        press(&mut app, KeyCode::Char('y'));
+        let notification = receiver.try_recv().expect("missing").expect("error");
+        assert_eq!(notification.r#type(), pb::Action::ReloadFwRules);
+        assert_eq!(notification.sys_firewall, Some(make_firewall(3)));
+        assert!(app.tui_state.notifications.is_firewall_pending("unix"));
+        app.handle_notification_reply(&NotificationReplyEvent {
+            node: String::from("unix"),
+            reply: pb::NotificationReply {
+                id: notification.id,
+                code: pb::NotificationReplyCode::Ok.into(),
+                data: String::default(),
+            },
+        });
+        assert_eq!(
+            app.tui_state.firewall_view.status.as_deref(),
+            Some("Done: reload firewall on unix")
+        );
+
+        // Resubscribing refreshes the firewall and keeps the selection on it.
+        subscribe(&mut app, 1);
+        assert_eq!(app.tui_state.firewall_view.selected, 1);
+    }
+
+    /// Test firewall edits are previewed, sent as a draft and rolled back on error.
+    #[tokio::test]
+    async fn test_firewall_edit_rollback() {
+        let mut app = App::new(
+            "127.0.0.1:65534",
+            &"deny".to_string(),
+            &"12h".to_string(),
+            &60,
+            "exact_dst_ip",
+            None,
+            None,
+        )
+        .expect("new failed");
+        let firewall = pb::SysFirewall {
+            enabled: true,
+            version: 1,
+            system_rules: vec![pb::FwChains {
+                rule: None,
+                chains: vec![pb::FwChain {
+                    name: String::from("input"),
+                    table: String::from("filter"),
+                    rules: vec![pb::FwRule {
+                        enabled: true,
+                        target: String::from("accept"),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+            }],
+        };
+        app.update_node_config(SubscribeEvent {
+            node: String::from("unix"),
+            peer: None,
+            config: pb::ClientConfig {
+                system_firewall: Some(firewall.clone()),
+                ..Default::default()
+            },
+        });
+        let (sender, mut receiver) = mpsc::channel(8);
+        app.tui_state
+            .nodes
+            .get_or_insert("unix")
+            .notification_sender = Some(sender);
+        let press = |app: &mut App, code: KeyCode| {
+            app.handle_key_events(KeyEvent::new(code, KeyModifiers::NONE))
+                .expect("key failed");
+        };
+        let reply = |app: &mut App, id: u64, error: Option<&str>| {
+            let code = match error {
+                Some(_) => pb::NotificationReplyCode::Error,
+                None => pb::NotificationReplyCode::Ok,
+            };
+            app.handle_notification_reply(&NotificationReplyEvent {
+                node: String::from("unix"),
+                reply: pb::NotificationReply {
+                    id,
+                    code: code.into(),
+                    data: error.unwrap_or_default().to_string(),
+                },
+            });
+        };
+        press(&mut app, KeyCode::Char('f'));
+
+        // Toggling twice leaves nothing to send.
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Char(' '));
+        assert!(app.tui_state.firewall_view.draft_for("unix").is_some());
+        press(&mut app, KeyCode::Char(' '));
+        assert!(app.tui_state.firewall_view.draft.is_none());
+
+        // Add a rule from the first template, after the selected one.
+        press(&mut app, KeyCode::Char('a'));
+        press(&mut app, KeyCode::Enter);
+        let FirewallDraft {
+            firewall: draft, ..
+        } = app.tui_state.firewall_view.draft.clone().expect("no draft");
+        let rules = &draft.system_rules[0].chains[0].rules;
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[1].target, "drop");
+        assert_eq!(app.tui_state.firewall_view.selected, 2);
+
+        // Sending takes a confirmation, and only the first notification carries a rollback.
+        press(&mut app, KeyCode::Char('s'));
+        assert!(app.tui_state.firewall_view.confirm_send);
+        press(&mut app, KeyCode::Char('n'));
+        assert!(receiver.try_recv().is_err());
+        press(&mut app, KeyCode::Char('s'));
+        press(&mut app, KeyCode::Char('y'));
+        let notification = receiver.try_recv().expect("missing").expect("error");
+        assert_eq!(notification.r#type(), pb::Action::ReloadFwRules);
+        assert_eq!(notification.sys_firewall.as_ref(), Some(&draft));
+        reply(&mut app, notification.id, Some("nft: syntax error"));
+        let rollback = receiver.try_recv().expect("missing").expect("error");
+        assert_eq!(rollback.sys_firewall.as_ref(), Some(&firewall));
+        assert_eq!(
+            app.tui_state.firewall_view.status.as_deref(),
+            Some("Failed: change firewall on unix: nft: syntax error, rolling back")
+        );
+        // The edits survive the failure, to fix and send again.
+        assert_eq!(app.tui_state.firewall_view.draft_for("unix"), Some(&draft));
+        assert_eq!(app.tui_state.nodes.selected_firewall(), Some(&firewall));
+
+        reply(&mut app, rollback.id, None);
+        assert!(app.tui_state.firewall_view.draft_for("unix").is_some());
+
+        // Once confirmed, the draft becomes the node's firewall.
+        press(&mut app, KeyCode::Char('s'));
+        press(&mut app, KeyCode::Char('y'));
+        let notification = receiver.try_recv().expect("missing").expect("error");
+        reply(&mut app, notification.id, None);
+        assert!(app.tui_state.firewall_view.draft.is_none());
+        assert_eq!(app.tui_state.nodes.selected_firewall(), Some(&draft));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    /// Test daemon commands are confirmed first, and their replies tracked and reported.
+    #[tokio::test]
+    async fn test_daemon_commands() {
+        let mut app = App::new(
+            "127.0.0.1:65534",
+            &"deny".to_string(),
+            &"12h".to_string(),
+            &60,
+            "exact_dst_ip",
+            None,
+            None,
+        )
+        .expect("new failed");
+        app.update_node_config(SubscribeEvent {
+            node: String::from("unix"),
+            peer: None,
+            config: pb::ClientConfig {
+                is_firewall_running: true,
+                ..Default::default()
+            },
+        });
+        let (sender, mut receiver) = mpsc::channel(8);
+        app.tui_state
+            .nodes
+            .get_or_insert("unix")
+            .notification_sender = Some(sender);
+        let press = |app: &mut App, c: char, modifiers: KeyModifiers| {
+            app.handle_key_events(KeyEvent::new(KeyCode::Char(c), modifiers))
+                .expect("key failed");
+        };
+        let mut reply = |app: &mut App, code: pb::NotificationReplyCode, data: &str| {
+            let notification = receiver.try_recv().expect("missing").expect("error");
+            app.handle_notification_reply(&NotificationReplyEvent {
+                node: String::from("unix"),
+                reply: pb::NotificationReply {
+                    id: notification.id,
+                    code: code.into(),
+                    data: data.to_string(),
+                },
+            });
+            notification.r#type()
+        };
+
+        // Nothing is sent unless confirmed.
+        press(&mut app, 'p', KeyModifiers::NONE);
+        assert_eq!(
+            app.tui_state.confirm_command,
+            Some((String::from("unix"), DaemonCommand::DisableInterception))
+        );
+        press(&mut app, 'n', KeyModifiers::NONE);
+        assert!(app.tui_state.confirm_command.is_none());
+        assert!(app.tui_state.notifications.is_empty());
+
+        press(&mut app, 'p', KeyModifiers::NONE);
+        press(&mut app, 'y', KeyModifiers::NONE);
+        let action = reply(&mut app, pb::NotificationReplyCode::Ok, "");
+        assert_eq!(action, pb::Action::DisableInterception);
+        let node = app.tui_state.nodes.get("unix").expect("no node");
+        assert!(node.interception_paused);
+        // Now paused, the same key resumes.
+        press(&mut app, 'p', KeyModifiers::NONE);
+        assert_eq!(
+            app.tui_state.confirm_command,
+            Some((String::from("unix"), DaemonCommand::EnableInterception))
+        );
+        press(&mut app, 'n', KeyModifiers::NONE);
+
+        // Failures leave the firewall as reported, and the daemon's reply is shown.
+        press(&mut app, 'w', KeyModifiers::NONE);
+        press(&mut app, 'y', KeyModifiers::NONE);
+        let action = reply(&mut app, pb::NotificationReplyCode::Error, "nft not found");
+        assert_eq!(action, pb::Action::DisableFirewall);
+        let config = app
+            .tui_state
+            .nodes
+            .get("unix")
+            .and_then(|node| node.config.as_ref());
+        assert!(config.is_some_and(|config| config.is_firewall_running));
+        assert_eq!(
+            app.tui_state
+                .current_alerts
+                .back()
+                .map(|alert| alert.msg.as_str()),
+            Some("Failed: disable firewall on unix: nft not found")
+        );
+
+        press(&mut app, 'x', KeyModifiers::CONTROL);
+        press(&mut app, 'y', KeyModifiers::NONE);
+        let action = reply(&mut app, pb::NotificationReplyCode::Ok, "stopping");
+        assert_eq!(action, pb::Action::Stop);
+        assert_eq!(
+            app.tui_state
+                .current_alerts
+                .back()
+                .map(|alert| alert.msg.as_str()),
+            Some("Done: stop daemon on unix: stopping")
+        );
+    }
+
+    /// Test a confirmed command goes to the node it was asked for, not the one now selected.
+    #[tokio::test]
+    async fn test_daemon_command_target() {
+        let mut app = App::new(
+            "127.0.0.1:65534",
+            &"deny".to_string(),
+            &"12h".to_string(),
+            &60,
+            "exact_dst_ip",
+            None,
+            None,
+        )
+        .expect("new failed");
+        let mut receivers = HashMap::new();
+        for key in ["unix", "10.0.0.2:50000"] {
+            let (sender, receiver) = mpsc::channel(8);
+            app.tui_state.nodes.get_or_insert(key).notification_sender = Some(sender);
+            receivers.insert(key.to_string(), receiver);
+        }
+        let (key, _) = app.tui_state.nodes.selected().expect("no node");
+        let key = key.to_string();
+        app.handle_key_events(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::CONTROL))
+            .expect("key failed");
+        assert_eq!(
+            app.tui_state.confirm_command,
+            Some((key.clone(), DaemonCommand::Stop))
+        );
+
+        app.tui_state.nodes.select_next();
+        assert_ne!(
+            app.tui_state.nodes.selected().map(|(selected, _)| selected),
+            Some(key.as_str())
+        );
+        app.handle_key_events(KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE))
+            .expect("key failed");
+        for (node, receiver) in &mut receivers {
+            assert_eq!(receiver.try_recv().is_ok(), *node == key, "{node}");
+        }
+        assert_eq!(
+            app.tui_state
+                .current_alerts
+                .back()
+                .map(|alert| alert.msg.as_str()),
+            Some(format!("Sent: stop daemon on {key}").as_str())
+        );
+    }
+
+    /// Test the daemon config is edited, sent and kept once the daemon applies it.
+    #[tokio::test]
+    async fn test_daemon_config() {
+        let mut app = App::new(
+            "127.0.0.1:65534",
+            &"deny".to_string(),
+            &"12h".to_string(),
+            &60,
+            "exact_dst_ip",
+            None,
+            None,
+        )
+        .expect("new failed");
+        app.update_node_config(SubscribeEvent {
+            node: String::from("unix"),
+            peer: None,
+            config: pb::ClientConfig {
+                config: String::from(r#"{"DefaultAction": "allow", "Audit": {"Enabled": true}}"#),
+                ..Default::default()
+            },
+        });
+        let (sender, mut receiver) = mpsc::channel(8);
+        app.tui_state
+            .nodes
+            .get_or_insert("unix")
+            .notification_sender = Some(sender);
+        let press = |app: &mut App, code: KeyCode| {
+            app.handle_key_events(KeyEvent::new(code, KeyModifiers::NONE))
+                .expect("key failed");
+        };
+
+        press(&mut app, KeyCode::Char('g'));
+        assert_eq!(app.tui_state.current_screen, TuiScreen::DaemonConfig);
+        press(&mut app, KeyCode::Char('s'));
+        assert!(app.tui_state.notifications.is_empty());
+        // DefaultAction is focused first, and cycles from allow to deny.
+        press(&mut app, KeyCode::Char(' '));
+        // Nothing is sent unless confirmed.
+        press(&mut app, KeyCode::Char('s'));
+        let editor = app.tui_state.config_editor.as_ref().expect("no editor");
+        assert!(editor.confirm_send);
+        assert_eq!(editor.modified_fields(), vec![ConfigField::DefaultAction]);
+        press(&mut app, KeyCode::Char('n'));
+        assert!(receiver.try_recv().is_err());
+        // Nor are the changes dropped to edit the next node unless confirmed.
+        press(&mut app, KeyCode::Char('n'));
+        press(&mut app, KeyCode::Esc);
+        let editor = app.tui_state.config_editor.as_ref().expect("no editor");
+        assert!(!editor.confirm_send && !editor.confirm_discard && editor.is_modified());
+        assert_eq!(app.tui_state.current_screen, TuiScreen::DaemonConfig);
+        press(&mut app, KeyCode::Char('s'));
+        press(&mut app, KeyCode::Char('y'));
+        let notification = receiver.try_recv().expect("missing").expect("error");
+        assert_eq!(notification.r#type(), pb::Action::ChangeConfig);
+        let sent: serde_json::Value = serde_json::from_str(&notification.data).expect("bad json");
+        assert_eq!(
+            sent,
+            serde_json::json!({"DefaultAction": "deny", "Audit": {"Enabled": true}})
+        );
+        assert!(app.tui_state.notifications.is_config_pending("unix"));
+
+        app.handle_notification_reply(&NotificationReplyEvent {
+            node: String::from("unix"),
+            reply: pb::NotificationReply {
+                id: notification.id,
+                code: pb::NotificationReplyCode::Ok.into(),
+                data: String::default(),
+            },
+        });
+        let editor = app.tui_state.config_editor.as_ref().expect("no editor");
+        assert!(!editor.is_modified());
+        assert_eq!(
+            editor.status.as_deref(),
+            Some("Done: change config on unix")
+        );
+        let node = app.tui_state.nodes.get("unix").expect("no node");
+        assert_eq!(
+            node.config.as_ref().map(|config| config.config.as_str()),
+            Some(notification.data.as_str())
+        );
+
+        // Confirmed, the changes are dropped for the next node's config.
+        press(&mut app, KeyCode::Char(' '));
+        press(&mut app, KeyCode::Char('n'));
+        press(&mut app, KeyCode::Char('y'));
+        let editor = app.tui_state.config_editor.as_ref().expect("no editor");
+        assert!(!editor.is_modified());
+
+        // Configs that don't parse aren't opened.
+        press(&mut app, KeyCode::Esc);
+        app.tui_state
+            .nodes
+            .get_or_insert("unix")
+            .config
+            .as_mut()
+            .expect("no config")
+            .config = String::from("[]");
+        press(&mut app, KeyCode::Char('g'));
+        assert_eq!(app.tui_state.current_screen, TuiScreen::Main);
+    }
+
+    /// Test that pings and subscriptions from different daemons land in separate nodes.
+    #[tokio::test]
+    async fn test_multi_node_stats() {
+        let mut app = App::new(
+            "127.0.0.1:65534",
+            &"deny".to_string(),
+            &"12h".to_string(),
+            &60,
+            "exact_dst_ip",
+            None,
+            None,
+        )
+        .expect("new failed");
+
+        for (node, rules) in [("10.0.0.1", 1), ("10.0.0.2", 2)] {
+            app.update_node_config(SubscribeEvent {
+                node: String::from(node),
+                peer: None,
+                config: pb::ClientConfig {
+                    name: format!("host-{rules}"),
+                    ..Default::default()
+                },
+            });
+            app.update_stats(PingEvent {
+                node: String::from(node),
+                peer: None,
+                stats: pb::Statistics {
+                    rules,
+                    events: vec![pb::Event {
+                        unixnano: 1,
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+            });
+        }
+
+        assert_eq!(app.tui_state.nodes.len(), 2);
+        let node_rules = |key: &str| {
+            app.tui_state
+                .nodes
+                .get(key)
+                .and_then(|node| node.stats.as_ref())
+                .map(|stats| stats.rules)
+        };
+        assert_eq!(node_rules("10.0.0.1"), Some(1));
+        assert_eq!(node_rules("10.0.0.2"), Some(2));
+        // Events move from the stats into the log, one per node.
+        assert_eq!(app.tui_state.event_log.len(), 2);
+        assert!(
+            app.tui_state
+                .nodes
+                .iter()
+                .all(|(_, node)| node.stats.as_ref().is_some_and(|s| s.events.is_empty()))
+        );
+
+        // First node seen is selected, and selection can move on.
+        assert_eq!(app.tui_state.nodes.selected_key(), Some("10.0.0.1"));
+        app.tui_state.nodes.select_next();
+        let (key, node) = app.tui_state.nodes.selected().expect("no selection");
+        assert_eq!(node.display_name(key), "host-2 (10.0.0.2)");
+    }
+
+    /// Test nodes are flagged disconnected after missing pings, and recover on the next ping.
+    #[tokio::test]
+    async fn test_node_staleness() {
+        let mut app = App::new(
+            "127.0.0.1:65534",
+            &"deny".to_string(),
+            &"12h".to_string(),
+            &60,
+            "exact_dst_ip",
+            None,
+            None,
+        )
+        .expect("new failed");
+        let clock = FakeClock::new(SystemTime::now());
+        app.clock = Box::new(clock.clone());
+        let ping = |app: &mut App| {
+            app.update_stats(PingEvent {
+                node: String::from("10.0.0.1"),
+                peer: Some("10.0.0.1:1234".parse().expect("bad addr")),
+                stats: pb::Statistics::default(),
+            });
+        };
+        let disconnected = |app: &App| {
+            app.tui_state
+                .nodes
+                .get("10.0.0.1")
+                .expect("missing node")
+                .disconnected
+        };
+
+        ping(&mut app);
+        clock.advance(PING_INTERVAL * (MISSED_PINGS_THRESHOLD - 1));
+        assert!(!app.tick());
+        assert!(!disconnected(&app));
+
+        clock.advance(PING_INTERVAL);
+        assert!(app.tick());
+        assert!(disconnected(&app));
+        assert_eq!(app.tui_state.current_alerts.len(), 1);
+        // Only alerted once.
+        clock.advance(PING_INTERVAL);
+        assert!(!app.tick());
+        assert_eq!(app.tui_state.current_alerts.len(), 1);
+
+        ping(&mut app);
+        assert!(!disconnected(&app));
+        assert_eq!(app.tui_state.current_alerts.len(), 2);
+
+        // Closing the notifications stream drops the peer right away.
+        app.close_notifications("10.0.0.1", 0);
+        assert!(disconnected(&app));
+        assert!(
+            app.tui_state
+                .nodes
+                .get("10.0.0.1")
+                .expect("missing node")
+                .peer
+                .is_none()
+        );
+    }
+
+    /// Test that a replaced notifications stream closing late leaves the newer one alone.
+    #[tokio::test]
+    async fn test_notifications_reconnect() {
+        let mut app = App::new(
+            "127.0.0.1:65534",
+            &"deny".to_string(),
+            &"12h".to_string(),
+            &60,
+            "exact_dst_ip",
+            None,
+            None,
+        )
+        .expect("new failed");
+        let open = |app: &mut App, stream: u64| {
+            let (sender, _) = mpsc::channel(1);
+            app.handle_app_event(AppEvent::NotificationsOpened(NotificationsEvent {
+                node: String::from("unix"),
+                stream,
+                sender,
+            }));
+        };
+        let connected = |app: &App| {
+            let node = app.tui_state.nodes.get("unix").expect("missing node");
+            node.notification_sender.is_some() && !node.disconnected
+        };
+
+        open(&mut app, 0);
+        open(&mut app, 1);
+        app.handle_app_event(AppEvent::NotificationsClosed(String::from("unix"), 0));
+        assert!(connected(&app));
+
+        app.handle_app_event(AppEvent::NotificationsClosed(String::from("unix"), 1));
+        assert!(!connected(&app));
+    }
+
+    /// Test a stop command is confirmed by the daemon's stream closing, since the daemon exits
+    /// without replying.
+    #[tokio::test]
+    async fn test_stop_daemon_stream_closed() {
+        let mut app = App::new(
+            "127.0.0.1:65534",
+            &"deny".to_string(),
+            &"12h".to_string(),
+            &60,
+            "exact_dst_ip",
+            None,
+            None,
+        )
+        .expect("new failed");
+        let clock = FakeClock::new(SystemTime::now());
+        app.clock = Box::new(clock.clone());
+        let (sender, mut receiver) = mpsc::channel(8);
+        app.handle_app_event(AppEvent::NotificationsOpened(NotificationsEvent {
+            node: String::from("unix"),
+            stream: 0,
+            sender,
+        }));
+        app.handle_key_events(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::CONTROL))
+            .expect("key failed");
+        app.handle_key_events(KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE))
+            .expect("key failed");
+        let notification = receiver.try_recv().expect("missing").expect("error");
+        assert_eq!(notification.r#type(), pb::Action::Stop);
+        assert_eq!(app.tui_state.notifications.len(), 1);
+
+        app.handle_app_event(AppEvent::NotificationsClosed(String::from("unix"), 0));
+        assert!(app.tui_state.notifications.is_empty());
+        let last_alert = |app: &App| {
+            app.tui_state
+                .current_alerts
+                .back()
+                .map(|alert| alert.msg.clone())
+        };
+        assert_eq!(
+            last_alert(&app).as_deref(),
+            Some("Done: stop daemon on unix")
+        );
+        clock.advance(crate::notification::REPLY_TIMEOUT);
+        app.tick();
+        assert_eq!(
+            last_alert(&app).as_deref(),
+            Some("Done: stop daemon on unix")
+        );
+    }
+
+    /// Test connections, decisions, timeouts, alerts and ping events are journaled,
+    /// and the history screen loads them back.
+    #[tokio::test]
+    async fn test_history_journal() {
+        let mut app = App::new(
+            "127.0.0.1:65534",
+            &"deny".to_string(),
+            &"12h".to_string(),
+            &60,
+            "exact_dst_ip",
+            None,
+            None,
+        )
+        .expect("new failed");
+        let (rule_sender, _rule_receiver) = mpsc::channel(8);
+        app.rule_sender = rule_sender;
+        let dir = std::env::temp_dir().join(format!(
+            "opensnitch-tui-test-app-history-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        app.open_history(&dir.join(history::HISTORY_FILE_NAME))
+            .expect("open failed");
+
+        let now = SystemTime::now();
+        for id in 0..2 {
+            app.update_connection(ConnectionEvent {
+                id,
+                node: String::from("unix"),
+                connection: make_fake_connection(),
+                // Connection 0 has already expired.
+                expiry_ts: if id == 0 {
+                    now
+                } else {
+                    now + app.connection_disposition_timeout
+                },
+            });
+        }
+        app.tick();
+        app.make_and_send_rule(constants::Action::Allow, constants::Duration::Once);
+        app.push_alert(Alert::create_simple(now, "hello"));
+        app.update_stats(PingEvent {
+            node: String::from("unix"),
+            peer: None,
+            stats: pb::Statistics {
+                events: vec![pb::Event {
+                    connection: Some(make_fake_connection()),
+                    rule: Some(Rule {
+                        name: String::from("allow-curl"),
+                        action: String::from("allow"),
+                        ..Default::default()
+                    }),
+                    unixnano: 1,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        });
+
+        app.load_history();
+        let kinds: Vec<RecordKind> = app
+            .tui_state
+            .history_view
+            .records
+            .iter()
+            .map(|record| record.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                RecordKind::Connection,
+                RecordKind::Connection,
+                RecordKind::Timeout,
+                RecordKind::Decision,
+                RecordKind::Alert,
+                RecordKind::Event,
+            ]
+        );
+        let records = &app.tui_state.history_view.records;
+        assert_eq!(records[3].data["connection_id"], 1);
+        assert_eq!(records[3].data["rule"]["action"], "allow");
+        assert_eq!(records[4].summary, "Warning: hello");
+        assert_eq!(records[5].data["rule"]["name"], "allow-curl");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// Test dispositions, timeouts and rule operations land in a verifiable audit log.
+    #[tokio::test]
+    async fn test_audit_log() {
+        let mut app = App::new(
+            "127.0.0.1:65534",
+            &"reject".to_string(),
+            &"12h".to_string(),
+            &60,
+            "exact_dst_ip",
+            None,
+            None,
+        )
+        .expect("new failed");
+        let (rule_sender, _rule_receiver) = mpsc::channel(8);
+        app.rule_sender = rule_sender;
+        let path = std::env::temp_dir().join(format!(
+            "opensnitch-tui-test-app-audit-{}.jsonl",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        app.open_audit_log(&path, true).expect("open failed");
+
+        let now = SystemTime::now();
+        for id in 0..2 {
+            app.update_connection(ConnectionEvent {
+                id,
+                node: String::from("unix"),
+                connection: make_fake_connection(),
+                // Connection 0 has already expired.
+                expiry_ts: if id == 0 {
+                    now
+                } else {
+                    now + app.connection_disposition_timeout
+                },
+            });
+        }
+        app.tick();
+        app.make_and_send_rule(constants::Action::Allow, constants::Duration::Once);
+
+        let (sender, mut receiver) = mpsc::channel(8);
+        app.update_node_config(SubscribeEvent {
+            node: String::from("unix"),
+            peer: None,
+            config: pb::ClientConfig {
+                rules: vec![Rule {
+                    name: String::from("allow-curl"),
+                    enabled: true,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        });
+        app.tui_state
+            .nodes
+            .get_or_insert("unix")
+            .notification_sender = Some(sender);
+        app.set_tui_screen(TuiScreen::Rules);
+        app.handle_key_events(KeyEvent::new(KeyCode::Char('d'), KeyModifiers::NONE))
+            .expect("key failed");
+        let notification = receiver.try_recv().expect("missing").expect("error");
+        app.handle_notification_reply(&NotificationReplyEvent {
+            node: String::from("unix"),
+            reply: pb::NotificationReply {
+                id: notification.id,
+                code: pb::NotificationReplyCode::Error.into(),
+                data: String::from("oops"),
+            },
+        });
+
+        assert_eq!(crate::audit::verify_chain(&path), Ok(4));
+        let entries: Vec<AuditEntry> = std::fs::read_to_string(&path)
+            .expect("read failed")
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("parse failed"))
+            .collect();
+        let outcomes: Vec<AuditOutcome> = entries.iter().map(|entry| entry.outcome).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                AuditOutcome::DefaultAction,
+                AuditOutcome::Sent,
+                AuditOutcome::Requested,
+                AuditOutcome::Failed,
+            ]
+        );
+        assert_eq!(entries[0].action, "reject");
+        assert_eq!(entries[1].dst_ip, make_fake_connection().dst_ip);
+        assert_eq!(entries[1].action, "allow");
+        assert_eq!(entries[1].duration, "once");
+        assert_eq!(entries[2].operation, "disable");
+        assert_eq!(entries[3].rule_name, "allow-curl");
+        assert_eq!(entries[3].detail, "oops");
+        assert!(!entries[0].user.is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
 }
@@ -0,0 +1,390 @@
+//! Append-only JSON-lines audit log of the dispositions and rule changes made through
+//! the TUI, optionally hash chained so edits and deletions can be detected.
+
+use std::fmt::Write as _;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+
+use crate::opensnitch_proto::pb;
+
+/// `prev_hash` of the first entry of a chain.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// What an audit entry records.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditKind {
+    /// A rule was sent in answer to a trapped connection.
+    Disposition,
+    /// A trapped connection went unanswered and the daemon applied its default action.
+    Timeout,
+    /// A rule operation was sent over a node's notifications stream, or replied to.
+    RuleOperation,
+    /// A system firewall change was sent over a node's notifications stream, or replied to.
+    FirewallOperation,
+    /// A command switching interception or the system firewall, or stopping the daemon,
+    /// was sent over a node's notifications stream, or replied to.
+    DaemonCommand,
+    /// A change of a daemon's configuration was sent over its notifications stream, or
+    /// replied to.
+    ConfigChange,
+}
+
+/// How an audited action turned out.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    /// Rule handed to the daemon waiting on the connection.
+    Sent,
+    /// Daemon left to apply its default action.
+    DefaultAction,
+    /// Rule, firewall or daemon operation sent, awaiting the daemon's reply.
+    Requested,
+    /// Daemon confirmed the operation.
+    Applied,
+    /// Daemon rejected the operation.
+    Failed,
+    /// Daemon never replied to the operation.
+    NoReply,
+}
+
+/// A line of the audit log.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AuditEntry {
+    /// Seconds since the Unix epoch.
+    pub ts: u64,
+    /// Local user running the TUI.
+    pub user: String,
+    /// Key of the node acted on.
+    pub node: String,
+    pub kind: AuditKind,
+    /// Operation, e.g. `delete`, for entries of operations sent over notifications streams.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub operation: String,
+    #[serde(default)]
+    pub protocol: String,
+    #[serde(default)]
+    pub src_ip: String,
+    #[serde(default)]
+    pub src_port: u32,
+    #[serde(default)]
+    pub dst_ip: String,
+    #[serde(default)]
+    pub dst_port: u32,
+    #[serde(default)]
+    pub process_path: String,
+    #[serde(default)]
+    pub rule_name: String,
+    #[serde(default)]
+    pub action: String,
+    #[serde(default)]
+    pub duration: String,
+    pub outcome: AuditOutcome,
+    /// Error reported by the daemon, if any.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub detail: String,
+    /// Hash of the previous entry, when chained.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prev_hash: Option<String>,
+    /// Hash of this entry including `prev_hash`, when chained.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+impl AuditEntry {
+    /// Entry about `node`, filled in from the connection and rule involved, if any.
+    #[must_use]
+    pub fn new(
+        ts: SystemTime,
+        node: &str,
+        kind: AuditKind,
+        connection: Option<&pb::Connection>,
+        rule: Option<&pb::Rule>,
+        outcome: AuditOutcome,
+    ) -> Self {
+        let conn = connection.cloned().unwrap_or_default();
+        let rule = rule.cloned().unwrap_or_default();
+        AuditEntry {
+            ts: ts
+                .duration_since(UNIX_EPOCH)
+                .map(|since| since.as_secs())
+                .unwrap_or_default(),
+            user: String::default(),
+            node: node.to_string(),
+            kind,
+            operation: String::default(),
+            protocol: conn.protocol,
+            src_ip: conn.src_ip,
+            src_port: conn.src_port,
+            dst_ip: conn.dst_ip,
+            dst_port: conn.dst_port,
+            process_path: conn.process_path,
+            rule_name: rule.name,
+            action: rule.action,
+            duration: rule.duration,
+            outcome,
+            detail: String::default(),
+            prev_hash: None,
+            hash: None,
+        }
+    }
+
+    /// Hex SHA-256 of the entry as serialized without its own hash.
+    fn compute_hash(&self) -> Result<String, String> {
+        let unhashed = AuditEntry {
+            hash: None,
+            ..self.clone()
+        };
+        let json = serde_json::to_string(&unhashed)
+            .map_err(|err| format!("Unable to serialize audit entry: {err}"))?;
+        let mut hex = String::with_capacity(GENESIS_HASH.len());
+        for byte in Sha256::digest(json.as_bytes()) {
+            let _ = write!(hex, "{byte:02x}");
+        }
+        Ok(hex)
+    }
+}
+
+/// Writer of the audit log.
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    file: File,
+    /// Stamped on every entry.
+    user: String,
+    /// Hash of the latest entry, if chaining.
+    last_hash: Option<String>,
+}
+
+impl AuditLog {
+    /// Open the audit log for appending, creating it and its directory if needed.
+    /// When `chain` is set, entries are chained onto the last entry already in the log.
+    /// # Errors
+    /// If the log can't be opened, or read to resume the chain, or if chaining onto a log
+    /// with unchained entries, which could then never be verified.
+    pub fn open(path: &Path, chain: bool, user: &str) -> Result<Self, String> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .map_err(|err| format!("Unable to create {}: {err}", dir.display()))?;
+        }
+        let last_hash = if chain {
+            match last_entry(path)? {
+                Some(AuditEntry {
+                    hash: Some(hash), ..
+                }) => Some(hash),
+                Some(_) => {
+                    return Err(format!(
+                        "Unable to chain {}: its entries aren't hash chained",
+                        path.display()
+                    ));
+                }
+                None => Some(GENESIS_HASH.to_string()),
+            }
+        } else {
+            None
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|err| format!("Unable to open {}: {err}", path.display()))?;
+        Ok(AuditLog {
+            path: path.to_path_buf(),
+            file,
+            user: user.to_string(),
+            last_hash,
+        })
+    }
+
+    /// Stamp, chain if enabled, and append an entry.
+    /// # Errors
+    /// If the entry can't be serialized or written.
+    pub fn append(&mut self, mut entry: AuditEntry) -> Result<(), String> {
+        entry.user.clone_from(&self.user);
+        if let Some(last_hash) = &self.last_hash {
+            entry.prev_hash = Some(last_hash.clone());
+            entry.hash = Some(entry.compute_hash()?);
+        }
+        let mut line = serde_json::to_string(&entry)
+            .map_err(|err| format!("Unable to serialize audit entry: {err}"))?;
+        line.push('\n');
+        self.file
+            .write_all(line.as_bytes())
+            .and_then(|()| self.file.flush())
+            .map_err(|err| format!("Unable to write {}: {err}", self.path.display()))?;
+        if entry.hash.is_some() {
+            self.last_hash = entry.hash;
+        }
+        Ok(())
+    }
+}
+
+/// Last entry of the log at `path`, if any.
+fn last_entry(path: &Path) -> Result<Option<AuditEntry>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let file =
+        File::open(path).map_err(|err| format!("Unable to open {}: {err}", path.display()))?;
+    let mut last = None;
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|err| format!("Unable to read {}: {err}", path.display()))?;
+        if !line.trim().is_empty() {
+            last = Some(line);
+        }
+    }
+    last.map(|line| {
+        serde_json::from_str(&line)
+            .map_err(|err| format!("Unable to parse last entry of {}: {err}", path.display()))
+    })
+    .transpose()
+}
+
+/// Check every entry of a hash chained audit log links to the one before it and
+/// hashes to what it claims. Returns the number of entries.
+/// # Errors
+/// Describing the first line that breaks the chain.
+pub fn verify_chain(path: &Path) -> Result<usize, String> {
+    let file =
+        File::open(path).map_err(|err| format!("Unable to open {}: {err}", path.display()))?;
+    let mut expected_prev = GENESIS_HASH.to_string();
+    let mut count = 0;
+    for (idx, line) in BufReader::new(file).lines().enumerate() {
+        let lineno = idx + 1;
+        let line = line.map_err(|err| format!("Unable to read {}: {err}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: AuditEntry = serde_json::from_str(&line)
+            .map_err(|err| format!("Line {lineno}: unable to parse entry: {err}"))?;
+        let (Some(prev_hash), Some(hash)) = (&entry.prev_hash, &entry.hash) else {
+            return Err(format!("Line {lineno}: entry is not hash chained"));
+        };
+        if *prev_hash != expected_prev {
+            return Err(format!(
+                "Line {lineno}: previous hash doesn't match, an entry was removed or reordered"
+            ));
+        }
+        if *hash != entry.compute_hash()? {
+            return Err(format!(
+                "Line {lineno}: hash doesn't match, the entry was modified"
+            ));
+        }
+        expected_prev.clone_from(hash);
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "opensnitch-tui-test-audit-{name}-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join("audit.jsonl")
+    }
+
+    fn entry(port: u32) -> AuditEntry {
+        AuditEntry::new(
+            UNIX_EPOCH,
+            "unix",
+            AuditKind::Disposition,
+            Some(&pb::Connection {
+                protocol: String::from("tcp"),
+                dst_ip: String::from("10.0.0.1"),
+                dst_port: port,
+                process_path: String::from("/usr/bin/curl"),
+                ..Default::default()
+            }),
+            Some(&pb::Rule {
+                name: String::from("allow-curl"),
+                action: String::from("allow"),
+                duration: String::from("once"),
+                ..Default::default()
+            }),
+            AuditOutcome::Sent,
+        )
+    }
+
+    fn write_lines(path: &Path, lines: &[String]) {
+        std::fs::write(path, lines.join("\n") + "\n").expect("write failed");
+    }
+
+    /// Test a chain verifies across reopening, and edits, removals and reordering are caught.
+    #[test]
+    fn test_chain_verification() {
+        let path = scratch_path("chain");
+        let mut log = AuditLog::open(&path, true, "alice (1000)").expect("open failed");
+        log.append(entry(1)).expect("append failed");
+        log.append(entry(2)).expect("append failed");
+        drop(log);
+        let mut log = AuditLog::open(&path, true, "alice (1000)").expect("reopen failed");
+        log.append(entry(3)).expect("append failed");
+        assert_eq!(verify_chain(&path), Ok(3));
+
+        let lines: Vec<String> = std::fs::read_to_string(&path)
+            .expect("read failed")
+            .lines()
+            .map(String::from)
+            .collect();
+        let first: AuditEntry = serde_json::from_str(&lines[0]).expect("parse failed");
+        assert_eq!(first.user, "alice (1000)");
+        assert_eq!(first.prev_hash.as_deref(), Some(GENESIS_HASH));
+        assert_eq!(first.dst_port, 1);
+        assert_eq!(first.outcome, AuditOutcome::Sent);
+
+        let mut tampered = lines.clone();
+        tampered[1] = tampered[1].replace("\"allow\"", "\"deny\"");
+        write_lines(&path, &tampered);
+        assert!(verify_chain(&path).unwrap_err().starts_with("Line 2: hash"));
+
+        write_lines(&path, &[lines[0].clone(), lines[2].clone()]);
+        assert!(
+            verify_chain(&path)
+                .unwrap_err()
+                .starts_with("Line 2: previous")
+        );
+
+        write_lines(&path, &[lines[1].clone(), lines[0].clone()]);
+        assert!(
+            verify_chain(&path)
+                .unwrap_err()
+                .starts_with("Line 1: previous")
+        );
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    /// Test unchained logs carry no hashes and don't verify.
+    #[test]
+    fn test_unchained() {
+        let path = scratch_path("unchained");
+        let mut log = AuditLog::open(&path, false, "root (0)").expect("open failed");
+        log.append(entry(1)).expect("append failed");
+        let line = std::fs::read_to_string(&path).expect("read failed");
+        assert!(!line.contains("hash"));
+        assert!(
+            verify_chain(&path)
+                .unwrap_err()
+                .contains("not hash chained")
+        );
+
+        // Chaining onto it would leave a chain that never verifies.
+        drop(log);
+        assert!(
+            AuditLog::open(&path, true, "root (0)")
+                .unwrap_err()
+                .contains("aren't hash chained")
+        );
+        assert_eq!(std::fs::read_to_string(&path).expect("read failed"), line);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
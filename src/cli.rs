@@ -1,4 +1,4 @@
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, Command};
 
 #[must_use]
 pub fn setup() -> Command {
@@ -6,11 +6,26 @@ pub fn setup() -> Command {
     .author("Amal Bansode")
     .version(env!("CARGO_PKG_VERSION"))
     .about("A Terminal UI control plane for OpenSnitch.")
+    .arg(
+        Arg::new("config")
+        .long("config")
+        .help("Path of a TOML config file whose keys mirror these flags, e.g. conn_dispo_timeout. Flags on the command line take precedence. Default: \"$XDG_CONFIG_HOME/opensnitch-tui/config.toml\".")
+    )
     .arg(
         Arg::new("ip_port")
         .long("bind")
         .default_value("127.0.0.1:50051")
-        .help("IP address and port for OpenSnitch gRPC server to bind to. Format: \"A.B.C.D:port\" or \"[A:B:C::D]:port\".")
+        .help("Address for OpenSnitch gRPC server to bind to. Format: \"A.B.C.D:port\", \"[A:B:C::D]:port\" or \"unix:///path/to.sock\".")
+    )
+    .arg(
+        Arg::new("socket_mode")
+        .long("socket-mode")
+        .help("Octal file mode to set on the Unix domain socket, e.g. \"0660\".")
+    )
+    .arg(
+        Arg::new("socket_owner")
+        .long("socket-owner")
+        .help("Numeric owner to set on the Unix domain socket. Format: \"uid:gid\", \"uid\" or \":gid\".")
     )
     .arg(
         Arg::new("dispo_seconds")
@@ -38,7 +53,54 @@ pub fn setup() -> Command {
         .default_value("exact_user_id,exact_ppath,exact_dst_ip,exact_dst_port,exact_protocol")
          // TODO: Single source of truth from operator_util.rs?
         .help("Comma-separated list of presets to use in rules created by TUI. \n\
-        Available: [exact_user_id | exact_ppath | exact_dst_ip | exact_dst_port | exact_protocol | <exact_hostname|any_subdomain_hostname> ]\n")
+        Available: [exact_user_id | <exact_ppath|process_path_regexp_dir> | exact_cmdline | \
+        exact_md5 | exact_sha1 | process_tree | exact_dst_ip | \
+        <dst_network_/24|dst_network_/16> | dst_network_/64 | exact_dst_port | exact_protocol | \
+        <exact_hostname|any_subdomain_hostname> | lists_domains:<path> ]\n")
+    )
+    .arg(
+        Arg::new("alert_retention")
+        .long("alert-retention")
+        .value_parser(clap::value_parser!(u64).range(1..=u64::from(u32::MAX)))
+        .help("Duration in seconds that alerts are shown for. Default: 60.")
+    )
+    .arg(
+        Arg::new("theme")
+        .long("theme")
+        .value_parser(["dark", "light", "high-contrast", "no-color"])
+        .help("Color theme. Default: \"no-color\" if the NO_COLOR environment variable is set, else \"dark\".")
+    )
+    .arg(
+        Arg::new("history_db")
+        .long("history-db")
+        .help("Path of the JSON-lines journal recording trapped connections, decisions, timeouts, alerts and connection events, rotated as it grows. Default: \"$XDG_DATA_HOME/opensnitch-tui/history.jsonl\". Pass \"none\" to disable.")
+    )
+    .arg(
+        Arg::new("audit_log")
+        .long("audit-log")
+        .help("Path of a JSON-lines audit log of every rule sent for a trapped connection, every connection left to the default action and every rule operation sent to a daemon, with the local user, node, connection and rule involved.")
+    )
+    .arg(
+        Arg::new("audit_chain")
+        .long("audit-chain")
+        .action(ArgAction::SetTrue)
+        .help("Chain audit log entries with SHA-256 hashes so edits, removals and reordering can be detected with --verify-audit-log. Requires --audit-log.")
+    )
+    .arg(
+        Arg::new("verify_audit_log")
+        .long("verify-audit-log")
+        .help("Verify the hash chain of an audit log, then exit.")
+    )
+    .arg(
+        Arg::new("policy")
+        .long("policy")
+        .help("Path of a TOML policy file whose rules answer trapped connections before they're shown, matching on process path glob, uid, destination host suffix, port ranges and protocol. Unmatched connections are left to the user.")
+    )
+    .arg(
+        Arg::new("headless")
+        .long("headless")
+        .action(ArgAction::SetTrue)
+        .help("Run without a terminal UI, answering trapped connections from --policy and logging decisions to stdout. Connections no policy rule matches get a \"once\" rule of --default-action. Requires --policy.")
     )
     .max_term_width(100)
 }
@@ -0,0 +1,44 @@
+//! Source of the current time, swappable so time-driven app logic can be tested.
+
+use std::time::SystemTime;
+
+/// Provides the current time.
+pub trait Clock: std::fmt::Debug + Send {
+    fn now(&self) -> SystemTime;
+}
+
+/// Wall clock time.
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Clock that only moves when told to. Clones share the same time.
+#[cfg(test)]
+#[derive(Clone, Debug)]
+pub struct FakeClock(std::sync::Arc<std::sync::Mutex<SystemTime>>);
+
+#[cfg(test)]
+impl FakeClock {
+    #[must_use]
+    pub fn new(now: SystemTime) -> Self {
+        FakeClock(std::sync::Arc::new(std::sync::Mutex::new(now)))
+    }
+
+    /// # Panics
+    /// If a holder of the clock panicked mid-update.
+    pub fn advance(&self, by: std::time::Duration) {
+        *self.0.lock().expect("poisoned clock") += by;
+    }
+}
+
+#[cfg(test)]
+impl Clock for FakeClock {
+    fn now(&self) -> SystemTime {
+        *self.0.lock().expect("poisoned clock")
+    }
+}
@@ -0,0 +1,402 @@
+//! TOML configuration file for the TUI, merged with command line flags.
+//!
+//! Keys mirror the long flags, e.g. `--conn-dispo-timeout` is `conn_dispo_timeout`.
+//! Flags given on the command line take precedence over the file, which takes
+//! precedence over flag defaults.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use clap::ArgMatches;
+use clap::parser::ValueSource;
+
+use crate::constants;
+use crate::keymap::{KeyAction, KeyChord};
+use crate::operator_util::PresetCombination;
+use crate::server::BindAddress;
+use crate::theme::{Theme, ThemeName};
+use crate::unix_socket::UnixSocketOptions;
+
+/// Config file name under the config directory.
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Seconds an alert is shown for, unless configured otherwise.
+pub const DEFAULT_ALERT_RETENTION_SECS: u64 = 60;
+
+/// Contents of the config file. Unset keys fall back to flags and their defaults.
+#[derive(Debug, Default, PartialEq)]
+pub struct Config {
+    pub bind: Option<String>,
+    pub socket_mode: Option<String>,
+    pub socket_owner: Option<String>,
+    pub conn_dispo_timeout: Option<u64>,
+    pub default_action: Option<String>,
+    pub temp_rule_lifetime: Option<String>,
+    pub rule_presets: Option<String>,
+    pub history_db: Option<String>,
+    pub audit_log: Option<String>,
+    pub audit_chain: Option<bool>,
+    pub policy: Option<String>,
+    /// Seconds an alert is shown for.
+    pub alert_retention: Option<u64>,
+    /// Name of the color theme.
+    pub theme: Option<String>,
+    /// Style overrides by element name, from the `[colors]` table.
+    pub colors: BTreeMap<String, String>,
+    /// Key overrides by action name, from the `[keybindings]` table.
+    pub keybindings: BTreeMap<String, String>,
+}
+
+/// Default config location under the XDG config directory, if one can be determined.
+#[must_use]
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("opensnitch-tui").join(CONFIG_FILE_NAME))
+}
+
+impl FromStr for Config {
+    type Err = String;
+
+    /// Parse and validate config file contents.
+    /// # Errors
+    /// If the TOML is malformed, or with one line per unknown or invalid key.
+    fn from_str(s: &str) -> Result<Config, Self::Err> {
+        let table: toml::Table = s.parse().map_err(|err| format!("Invalid TOML: {err}"))?;
+        let mut config = Config::default();
+        let mut errors = Vec::new();
+        for (key, value) in table {
+            if let Err(err) = config.set(&key, value) {
+                errors.push(format!("{key}: {err}"));
+            }
+        }
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+}
+
+impl Config {
+    /// Load the config file at `path`, or the default location if unset.
+    /// A missing file at the default location is an empty config.
+    /// # Errors
+    /// If the file can't be read or is invalid.
+    pub fn load(path: Option<&Path>) -> Result<Config, String> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => contents.parse().map_err(|err: String| {
+                err.lines()
+                    .map(|line| format!("{}: {line}", path.display()))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }),
+            Err(err) if !required && err.kind() == std::io::ErrorKind::NotFound => {
+                Ok(Config::default())
+            }
+            Err(err) => Err(format!("Unable to read {}: {err}", path.display())),
+        }
+    }
+
+    /// Validate and set a single key.
+    fn set(&mut self, key: &str, value: toml::Value) -> Result<(), String> {
+        match key {
+            "bind" => {
+                let bind = as_string(value)?;
+                BindAddress::new(&bind, None, None)?;
+                self.bind = Some(bind);
+            }
+            "socket_mode" => {
+                let mode = as_string(value)?;
+                UnixSocketOptions::new(Some(&mode), None)?;
+                self.socket_mode = Some(mode);
+            }
+            "socket_owner" => {
+                let owner = as_string(value)?;
+                UnixSocketOptions::new(None, Some(&owner))?;
+                self.socket_owner = Some(owner);
+            }
+            "conn_dispo_timeout" => {
+                self.conn_dispo_timeout = Some(as_secs(&value, 1..=114)?);
+            }
+            "default_action" => {
+                let action = as_string(value)?;
+                constants::DefaultAction::new(&action).map_err(|err| err.to_string())?;
+                self.default_action = Some(action);
+            }
+            "temp_rule_lifetime" => {
+                let lifetime = as_string(value)?;
+                constants::Duration::new(&lifetime).map_err(|err| err.to_string())?;
+                self.temp_rule_lifetime = Some(lifetime);
+            }
+            "rule_presets" => {
+                let presets = as_string(value)?;
+                PresetCombination::from_str(&presets)?;
+                self.rule_presets = Some(presets);
+            }
+            "history_db" => self.history_db = Some(as_string(value)?),
+            "audit_log" => self.audit_log = Some(as_string(value)?),
+            "audit_chain" => {
+                self.audit_chain = Some(
+                    value
+                        .as_bool()
+                        .ok_or_else(|| format!("expected a boolean, got {}", value.type_str()))?,
+                );
+            }
+            "policy" => self.policy = Some(as_string(value)?),
+            "alert_retention" => {
+                self.alert_retention = Some(as_secs(&value, 1..=u64::from(u32::MAX))?);
+            }
+            "theme" => {
+                let theme = as_string(value)?;
+                ThemeName::from_str(&theme)?;
+                self.theme = Some(theme);
+            }
+            "colors" => {
+                let toml::Value::Table(table) = value else {
+                    return Err(format!("expected a table, got {}", value.type_str()));
+                };
+                for (element, spec) in table {
+                    let spec = as_string(spec).map_err(|err| format!("{element}: {err}"))?;
+                    Theme::default()
+                        .set(&element, &spec)
+                        .map_err(|err| format!("{element}: {err}"))?;
+                    self.colors.insert(element, spec);
+                }
+            }
+            "keybindings" => {
+                let toml::Value::Table(table) = value else {
+                    return Err(format!("expected a table, got {}", value.type_str()));
+                };
+                for (action, key) in table {
+                    let key = as_string(key).map_err(|err| format!("{action}: {err}"))?;
+                    KeyAction::from_str(&action)
+                        .and_then(|_| KeyChord::from_str(&key))
+                        .map_err(|err| format!("{action}: {err}"))?;
+                    self.keybindings.insert(action, key);
+                }
+            }
+            _ => return Err(String::from("unknown key")),
+        }
+        Ok(())
+    }
+}
+
+fn as_string(value: toml::Value) -> Result<String, String> {
+    match value {
+        toml::Value::String(s) => Ok(s),
+        _ => Err(format!("expected a string, got {}", value.type_str())),
+    }
+}
+
+fn as_secs(value: &toml::Value, range: std::ops::RangeInclusive<u64>) -> Result<u64, String> {
+    let toml::Value::Integer(secs) = *value else {
+        return Err(format!("expected an integer, got {}", value.type_str()));
+    };
+    u64::try_from(secs)
+        .ok()
+        .filter(|secs| range.contains(secs))
+        .ok_or_else(|| format!("{secs} is out of range {}..={}", range.start(), range.end()))
+}
+
+/// Settings resolved from the command line, the config file and flag defaults, in that order.
+#[derive(Debug)]
+pub struct Settings {
+    pub bind: String,
+    pub socket_mode: Option<String>,
+    pub socket_owner: Option<String>,
+    pub conn_dispo_timeout: u64,
+    pub default_action: String,
+    pub temp_rule_lifetime: String,
+    pub rule_presets: String,
+    pub history_db: Option<String>,
+    pub audit_log: Option<String>,
+    pub audit_chain: bool,
+    pub policy: Option<String>,
+    pub alert_retention: u64,
+    pub theme: Option<String>,
+    pub colors: BTreeMap<String, String>,
+    pub keybindings: BTreeMap<String, String>,
+}
+
+/// Value of flag `id` if given on the command line, else `config_value` if set, else the flag's
+/// default.
+fn pick<T: Clone + Send + Sync + 'static>(
+    matches: &ArgMatches,
+    id: &str,
+    config_value: Option<T>,
+) -> Option<T> {
+    if matches.value_source(id) == Some(ValueSource::CommandLine) {
+        return matches.get_one::<T>(id).cloned();
+    }
+    config_value.or_else(|| matches.get_one::<T>(id).cloned())
+}
+
+impl Settings {
+    /// Merge command line flags over the config file.
+    /// # Errors
+    /// If a flag is missing its default, or a setting requires another that's unset.
+    pub fn resolve(matches: &ArgMatches, config: Config) -> Result<Settings, String> {
+        let required = |id: &str| format!("Missing value for {id}");
+        let settings = Settings {
+            bind: pick(matches, "ip_port", config.bind).ok_or_else(|| required("bind"))?,
+            socket_mode: pick(matches, "socket_mode", config.socket_mode),
+            socket_owner: pick(matches, "socket_owner", config.socket_owner),
+            conn_dispo_timeout: pick(matches, "dispo_seconds", config.conn_dispo_timeout)
+                .ok_or_else(|| required("conn_dispo_timeout"))?,
+            default_action: pick(matches, "default_action", config.default_action)
+                .ok_or_else(|| required("default_action"))?,
+            temp_rule_lifetime: pick(matches, "temp_rule_lifetime", config.temp_rule_lifetime)
+                .ok_or_else(|| required("temp_rule_lifetime"))?,
+            rule_presets: pick(matches, "rule_presets", config.rule_presets)
+                .ok_or_else(|| required("rule_presets"))?,
+            history_db: pick(matches, "history_db", config.history_db),
+            audit_log: pick(matches, "audit_log", config.audit_log),
+            audit_chain: pick(matches, "audit_chain", config.audit_chain).unwrap_or_default(),
+            policy: pick(matches, "policy", config.policy),
+            alert_retention: pick(matches, "alert_retention", config.alert_retention)
+                .unwrap_or(DEFAULT_ALERT_RETENTION_SECS),
+            theme: pick(matches, "theme", config.theme),
+            colors: config.colors,
+            keybindings: config.keybindings,
+        };
+        if settings.audit_chain && settings.audit_log.is_none() {
+            return Err(String::from("audit_chain requires audit_log"));
+        }
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test a valid config parses into the keys it sets.
+    #[test]
+    fn test_parse() {
+        let config: Config = r#"
+            bind = "unix:///tmp/osui.sock"
+            socket_mode = "0660"
+            conn_dispo_timeout = 60
+            default_action = "reject"
+            temp_rule_lifetime = "1h"
+            rule_presets = "exact_ppath,exact_dst_port"
+            audit_log = "/var/log/opensnitch-tui.audit"
+            audit_chain = true
+            alert_retention = 120
+            theme = "light"
+
+            [colors]
+            allow = "light-green"
+            pending = "bold black on #ffaf00"
+
+            [keybindings]
+            quit = "q"
+            allow_temp = "ctrl+y"
+        "#
+        .parse()
+        .expect("parse failed");
+        assert_eq!(config.bind.as_deref(), Some("unix:///tmp/osui.sock"));
+        assert_eq!(config.conn_dispo_timeout, Some(60));
+        assert_eq!(config.default_action.as_deref(), Some("reject"));
+        assert_eq!(config.audit_chain, Some(true));
+        assert_eq!(config.alert_retention, Some(120));
+        assert_eq!(config.history_db, None);
+        assert_eq!(config.theme.as_deref(), Some("light"));
+        assert_eq!(
+            config.colors.get("pending").map(String::as_str),
+            Some("bold black on #ffaf00")
+        );
+        assert_eq!(
+            config.keybindings.get("quit").map(String::as_str),
+            Some("q")
+        );
+        assert_eq!(
+            Config::from_str("").expect("parse failed"),
+            Config::default()
+        );
+    }
+
+    /// Test every invalid key is reported, each on its own line.
+    #[test]
+    fn test_invalid_keys() {
+        let err = Config::from_str(
+            r#"
+            bind = "nowhere"
+            conn_dispo_timeout = 300
+            default_action = "maybe"
+            audit_chain = "yes"
+            rule_presets = "exact_ppath,bogus"
+            colour = "red"
+            temp_rule_lifetime = "1h"
+            theme = "solarized"
+
+            [colors]
+            deny = "bright pink"
+
+            [keybindings]
+            quit = "hyper+q"
+        "#,
+        )
+        .expect_err("parse succeeded");
+        let lines: Vec<&str> = err.lines().collect();
+        assert_eq!(lines.len(), 9, "{err}");
+        assert!(lines.contains(&"colors: deny: unknown color \"bright\""));
+        assert!(
+            lines
+                .iter()
+                .any(|line| line.starts_with("theme: unknown theme \"solarized\""))
+        );
+        assert!(lines.contains(&"keybindings: quit: unknown modifier \"hyper\" in \"hyper+q\""));
+        assert!(lines.contains(&"audit_chain: expected a boolean, got string"));
+        assert!(lines.contains(&"colour: unknown key"));
+        assert!(lines.contains(&"conn_dispo_timeout: 300 is out of range 1..=114"));
+        assert!(lines.contains(&"default_action: Bad Option: maybe"));
+        assert!(lines.contains(&"rule_presets: Unknown preset key: bogus"));
+        assert!(lines.iter().any(|line| line.starts_with("bind: ")));
+        assert!(Config::from_str("bind = ").is_err());
+    }
+
+    /// Test flags given on the command line override the config, which overrides defaults.
+    #[test]
+    fn test_resolve() {
+        let config = Config {
+            bind: Some(String::from("unix:///tmp/osui.sock")),
+            default_action: Some(String::from("reject")),
+            alert_retention: Some(5),
+            ..Default::default()
+        };
+        let matches = crate::cli::setup()
+            .try_get_matches_from([
+                "opensnitch-tui",
+                "--default-action",
+                "allow",
+                "--theme",
+                "no-color",
+            ])
+            .expect("parse failed");
+        let settings = Settings::resolve(&matches, config).expect("resolve failed");
+        assert_eq!(settings.bind, "unix:///tmp/osui.sock");
+        assert_eq!(settings.default_action, "allow");
+        assert_eq!(settings.temp_rule_lifetime, "12h");
+        assert_eq!(settings.conn_dispo_timeout, 30);
+        assert_eq!(settings.alert_retention, 5);
+        assert_eq!(settings.theme.as_deref(), Some("no-color"));
+        assert!(!settings.audit_chain);
+
+        let matches = crate::cli::setup()
+            .try_get_matches_from(["opensnitch-tui", "--audit-chain"])
+            .expect("parse failed");
+        assert!(Settings::resolve(&matches, Config::default()).is_err());
+    }
+}
@@ -0,0 +1,383 @@
+//! Form to review and change the configuration of a node's daemon.
+
+use crate::constants;
+use crate::opensnitch_json::OpenSnitchDaemonConfig;
+use crate::rule_editor;
+use serde_json::Value;
+
+/// Names of the daemon's log levels, from 0 up.
+const LOG_LEVELS: [&str; 5] = ["debug", "info", "important", "warning", "error"];
+const FIREWALLS: [&str; 2] = ["nftables", "iptables"];
+const PROC_MONITOR_METHODS: [&str; 3] = ["ebpf", "proc", "audit"];
+
+/// Editable settings, in the order they're listed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfigField {
+    DefaultAction,
+    DefaultDuration,
+    InterceptUnknown,
+    ProcMonitorMethod,
+    Firewall,
+    LogLevel,
+    LogUtc,
+    LogMicro,
+    FwMonitorInterval,
+    FwQueueBypass,
+    RulesPath,
+    RulesEnableChecksums,
+    EbpfEventsWorkers,
+    EbpfQueueEventsSize,
+    StatsMaxEvents,
+    StatsMaxStats,
+    StatsWorkers,
+}
+
+impl ConfigField {
+    pub const ALL: [ConfigField; 17] = [
+        ConfigField::DefaultAction,
+        ConfigField::DefaultDuration,
+        ConfigField::InterceptUnknown,
+        ConfigField::ProcMonitorMethod,
+        ConfigField::Firewall,
+        ConfigField::LogLevel,
+        ConfigField::LogUtc,
+        ConfigField::LogMicro,
+        ConfigField::FwMonitorInterval,
+        ConfigField::FwQueueBypass,
+        ConfigField::RulesPath,
+        ConfigField::RulesEnableChecksums,
+        ConfigField::EbpfEventsWorkers,
+        ConfigField::EbpfQueueEventsSize,
+        ConfigField::StatsMaxEvents,
+        ConfigField::StatsMaxStats,
+        ConfigField::StatsWorkers,
+    ];
+
+    /// Key of the setting in the daemon's config.
+    #[must_use]
+    pub fn get_str(&self) -> &str {
+        match self {
+            ConfigField::DefaultAction => "DefaultAction",
+            ConfigField::DefaultDuration => "DefaultDuration",
+            ConfigField::InterceptUnknown => "InterceptUnknown",
+            ConfigField::ProcMonitorMethod => "ProcMonitorMethod",
+            ConfigField::Firewall => "Firewall",
+            ConfigField::LogLevel => "LogLevel",
+            ConfigField::LogUtc => "LogUTC",
+            ConfigField::LogMicro => "LogMicro",
+            ConfigField::FwMonitorInterval => "FwOptions.MonitorInterval",
+            ConfigField::FwQueueBypass => "FwOptions.QueueBypass",
+            ConfigField::RulesPath => "Rules.Path",
+            ConfigField::RulesEnableChecksums => "Rules.EnableChecksums",
+            ConfigField::EbpfEventsWorkers => "Ebpf.EventsWorkers",
+            ConfigField::EbpfQueueEventsSize => "Ebpf.QueueEventsSize",
+            ConfigField::StatsMaxEvents => "Stats.MaxEvents",
+            ConfigField::StatsMaxStats => "Stats.MaxStats",
+            ConfigField::StatsWorkers => "Stats.Workers",
+        }
+    }
+
+    /// Whether the setting is typed in rather than toggled or cycled.
+    #[must_use]
+    pub fn is_text(&self) -> bool {
+        matches!(
+            self,
+            ConfigField::FwMonitorInterval
+                | ConfigField::RulesPath
+                | ConfigField::EbpfEventsWorkers
+                | ConfigField::EbpfQueueEventsSize
+                | ConfigField::StatsMaxEvents
+                | ConfigField::StatsMaxStats
+                | ConfigField::StatsWorkers
+        )
+    }
+}
+
+/// Choice after `current`, or the first one if `current` isn't a choice.
+fn next_choice(choices: &[&str], current: Option<&str>) -> String {
+    let idx = choices
+        .iter()
+        .position(|choice| Some(*choice) == current)
+        .map_or(0, |idx| (idx + 1) % choices.len());
+    choices[idx].to_string()
+}
+
+/// Number typed into `field`.
+fn parse_number(field: ConfigField, text: &str) -> Result<Value, String> {
+    text.parse::<u64>()
+        .map(Value::from)
+        .map_err(|_| format!("{} must be a number", field.get_str()))
+}
+
+/// Daemon config being edited for a node.
+#[derive(Debug)]
+pub struct ConfigEditor {
+    /// Key of the node the config belongs to.
+    pub node: String,
+    /// Config as the daemon reported it, or as last confirmed.
+    pub original: OpenSnitchDaemonConfig,
+    pub config: OpenSnitchDaemonConfig,
+    /// Index into `ConfigField::ALL`.
+    pub focus: usize,
+    /// Text being typed into the focused field, until committed.
+    pub text: Option<String>,
+    /// Outcome of the latest attempt to change a setting or send the config.
+    pub status: Option<String>,
+    /// Whether the user is being asked to confirm sending the changed settings.
+    pub confirm_send: bool,
+    /// Whether the user is being asked to confirm dropping the changes to edit the next node.
+    pub confirm_discard: bool,
+}
+
+impl ConfigEditor {
+    #[must_use]
+    pub fn new(node: &str, config: OpenSnitchDaemonConfig) -> Self {
+        ConfigEditor {
+            node: node.to_string(),
+            original: config.clone(),
+            config,
+            focus: 0,
+            text: None,
+            status: None,
+            confirm_send: false,
+            confirm_discard: false,
+        }
+    }
+
+    #[must_use]
+    pub fn focused(&self) -> ConfigField {
+        ConfigField::ALL[self.focus]
+    }
+
+    pub fn focus_prev(&mut self) {
+        self.focus = self.focus.saturating_sub(1);
+    }
+
+    pub fn focus_next(&mut self) {
+        self.focus = std::cmp::min(self.focus + 1, ConfigField::ALL.len() - 1);
+    }
+
+    /// Whether the config differs from the daemon's.
+    #[must_use]
+    pub fn is_modified(&self) -> bool {
+        self.config != self.original
+    }
+
+    /// Toggle or cycle the focused field, or start typing into a text field.
+    pub fn toggle(&mut self) {
+        let field = self.focused();
+        let key = field.get_str();
+        let current = self.config.get(key);
+        if field.is_text() {
+            self.text = Some(match current {
+                Some(Value::String(text)) => text.clone(),
+                Some(Value::Number(number)) => number.to_string(),
+                _ => String::default(),
+            });
+            return;
+        }
+        let current_str = current.and_then(Value::as_str);
+        let value = match field {
+            ConfigField::DefaultAction => {
+                let actions = [
+                    constants::DefaultAction::Allow,
+                    constants::DefaultAction::Deny,
+                    constants::DefaultAction::Reject,
+                ]
+                .map(|action| action.get_str().to_string());
+                let actions: Vec<&str> = actions.iter().map(String::as_str).collect();
+                Value::from(next_choice(&actions, current_str))
+            }
+            ConfigField::DefaultDuration => {
+                let duration = current_str
+                    .and_then(|duration| constants::Duration::new(duration).ok())
+                    .map_or(constants::Duration::Once, rule_editor::next_duration);
+                Value::from(duration.get_str())
+            }
+            ConfigField::ProcMonitorMethod => {
+                Value::from(next_choice(&PROC_MONITOR_METHODS, current_str))
+            }
+            ConfigField::Firewall => Value::from(next_choice(&FIREWALLS, current_str)),
+            ConfigField::LogLevel => Value::from(match current.and_then(Value::as_i64) {
+                Some(level) if (0..4).contains(&level) => level + 1,
+                _ => 0,
+            }),
+            _ => Value::from(!current.and_then(Value::as_bool).unwrap_or_default()),
+        };
+        self.config.set(key, value);
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        if let Some(text) = self.text.as_mut() {
+            text.push(c);
+        }
+    }
+
+    pub fn pop_char(&mut self) {
+        if let Some(text) = self.text.as_mut() {
+            text.pop();
+        }
+    }
+
+    /// Stop typing, discarding the text.
+    pub fn cancel_text(&mut self) {
+        self.text = None;
+    }
+
+    /// Stop typing and set the focused field to the text. Nothing sets a text field to an
+    /// empty string.
+    /// # Errors
+    /// If a number field isn't set to a number. Typing goes on then.
+    pub fn commit_text(&mut self) -> Result<(), String> {
+        let Some(text) = self.text.as_deref() else {
+            return Ok(());
+        };
+        let text = text.trim();
+        let field = self.focused();
+        let value = match field {
+            ConfigField::FwMonitorInterval | ConfigField::RulesPath => Value::from(text),
+            _ => parse_number(field, text)?,
+        };
+        self.config.set(field.get_str(), value);
+        self.text = None;
+        Ok(())
+    }
+
+    /// Display value of a field: `-` if the daemon didn't send it, the level name along log
+    /// levels, and strings unquoted.
+    #[must_use]
+    pub fn field_value(&self, field: ConfigField) -> String {
+        if let Some(text) = self.text.as_ref().filter(|_| field == self.focused()) {
+            return format!("{text}_");
+        }
+        match (field, self.config.get(field.get_str())) {
+            (_, None) => String::from("-"),
+            (_, Some(Value::String(text))) => text.clone(),
+            (ConfigField::LogLevel, Some(Value::Number(level))) => {
+                let name = level
+                    .as_u64()
+                    .and_then(|level| usize::try_from(level).ok())
+                    .and_then(|level| LOG_LEVELS.get(level))
+                    .unwrap_or(&"unknown");
+                format!("{level} ({name})")
+            }
+            (_, Some(value)) => value.to_string(),
+        }
+    }
+
+    /// Whether a field was changed from the daemon's config.
+    #[must_use]
+    pub fn is_field_modified(&self, field: ConfigField) -> bool {
+        self.config.get(field.get_str()) != self.original.get(field.get_str())
+    }
+
+    /// Fields changed from the daemon's config, in the order they're listed.
+    #[must_use]
+    pub fn modified_fields(&self) -> Vec<ConfigField> {
+        ConfigField::ALL
+            .into_iter()
+            .filter(|field| self.is_field_modified(*field))
+            .collect()
+    }
+
+    /// Config as it would be sent to the daemon.
+    #[must_use]
+    pub fn preview(&self) -> String {
+        self.config.to_json().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn focus(editor: &mut ConfigEditor, field: ConfigField) {
+        editor.focus = ConfigField::ALL
+            .iter()
+            .position(|f| *f == field)
+            .expect("unknown field");
+    }
+
+    /// Test settings are cycled, toggled and typed in, leaving other keys alone.
+    #[test]
+    fn test_edit_fields() {
+        let config = OpenSnitchDaemonConfig::parse(
+            r#"{"DefaultAction": "deny", "LogLevel": 4, "Audit": {"Enabled": true}}"#,
+        )
+        .expect("parse failed");
+        let mut editor = ConfigEditor::new("unix", config);
+        assert!(!editor.is_modified());
+
+        focus(&mut editor, ConfigField::DefaultAction);
+        editor.toggle();
+        assert_eq!(editor.field_value(ConfigField::DefaultAction), "reject");
+        focus(&mut editor, ConfigField::LogLevel);
+        assert_eq!(editor.field_value(ConfigField::LogLevel), "4 (error)");
+        editor.toggle();
+        assert_eq!(editor.field_value(ConfigField::LogLevel), "0 (debug)");
+        focus(&mut editor, ConfigField::DefaultDuration);
+        assert_eq!(editor.field_value(ConfigField::DefaultDuration), "-");
+        editor.toggle();
+        assert_eq!(editor.field_value(ConfigField::DefaultDuration), "once");
+        focus(&mut editor, ConfigField::FwQueueBypass);
+        editor.toggle();
+        assert_eq!(editor.field_value(ConfigField::FwQueueBypass), "true");
+
+        focus(&mut editor, ConfigField::StatsWorkers);
+        editor.toggle();
+        editor.push_char('x');
+        assert_eq!(editor.field_value(ConfigField::StatsWorkers), "x_");
+        assert!(editor.commit_text().is_err());
+        editor.pop_char();
+        editor.push_char('6');
+        assert_eq!(editor.commit_text(), Ok(()));
+        assert_eq!(editor.field_value(ConfigField::StatsWorkers), "6");
+        assert!(editor.is_field_modified(ConfigField::StatsWorkers));
+        assert!(!editor.is_field_modified(ConfigField::RulesPath));
+        assert_eq!(
+            editor.modified_fields(),
+            vec![
+                ConfigField::DefaultAction,
+                ConfigField::DefaultDuration,
+                ConfigField::LogLevel,
+                ConfigField::FwQueueBypass,
+                ConfigField::StatsWorkers,
+            ]
+        );
+
+        // Numbers can't be typed in as nothing.
+        editor.toggle();
+        editor.pop_char();
+        assert!(editor.commit_text().is_err());
+        editor.cancel_text();
+        assert_eq!(editor.field_value(ConfigField::StatsWorkers), "6");
+
+        assert!(editor.is_modified());
+        assert!(editor.preview().contains(r#""Audit": {"#));
+    }
+
+    /// Test nulls, values of unexpected types and cleared fields are sent back as they are.
+    #[test]
+    fn test_round_trip() {
+        let json = r#"{"LogLevel": null, "Rules": {"Path": "/etc/opensnitchd/rules"}, "Stats": {"Workers": "6"}}"#;
+        let config = OpenSnitchDaemonConfig::parse(json).expect("parse failed");
+        let mut editor = ConfigEditor::new("unix", config);
+        assert_eq!(editor.field_value(ConfigField::LogLevel), "null");
+        assert_eq!(editor.field_value(ConfigField::StatsWorkers), "6");
+
+        focus(&mut editor, ConfigField::RulesPath);
+        editor.toggle();
+        for _ in 0.."/etc/opensnitchd/rules".len() {
+            editor.pop_char();
+        }
+        assert_eq!(editor.commit_text(), Ok(()));
+        assert_eq!(editor.field_value(ConfigField::RulesPath), "");
+        assert_eq!(editor.modified_fields(), vec![ConfigField::RulesPath]);
+
+        let sent: Value = serde_json::from_str(&editor.preview()).expect("bad json");
+        assert_eq!(
+            sent,
+            serde_json::json!({"LogLevel": null, "Rules": {"Path": ""}, "Stats": {"Workers": "6"}})
+        );
+    }
+}
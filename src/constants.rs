@@ -7,6 +7,7 @@
 pub enum Operand {
     ProcessId,
     ProcessPath,
+    ProcessParentPath,
     ProcessCmd,
     ProcessEnv,
     ProcessHashMd5,
@@ -36,6 +37,7 @@ impl Operand {
         match self {
             Operand::ProcessId => "process.id",
             Operand::ProcessPath => "process.path",
+            Operand::ProcessParentPath => "process.parent.path",
             Operand::ProcessCmd => "process.command",
             Operand::ProcessEnv => "process.env.",
             Operand::ProcessHashMd5 => "process.hash.md5",
@@ -244,6 +246,16 @@ impl DefaultAction {
     }
 }
 
+impl From<DefaultAction> for Action {
+    fn from(default_action: DefaultAction) -> Self {
+        match default_action {
+            DefaultAction::Allow => Action::Allow,
+            DefaultAction::Deny => Action::Deny,
+            DefaultAction::Reject => Action::Reject,
+        }
+    }
+}
+
 /// Error type for bad option provided to enum constructor.
 #[derive(Debug, Clone)]
 pub struct BadOption {
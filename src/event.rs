@@ -1,5 +1,6 @@
 use crate::alert::Alert;
-use crate::opensnitch_proto::pb::{Connection, Statistics};
+use crate::node::NotificationSender;
+use crate::opensnitch_proto::pb::{ClientConfig, Connection, NotificationReply, Rule, Statistics};
 use color_eyre::eyre::OptionExt;
 use futures::{FutureExt, StreamExt};
 use ratatui::crossterm::event::Event as CrosstermEvent;
@@ -29,8 +30,14 @@ pub enum AppEvent {
     Alert(Alert),
     /// Daemon trapped a new connection that requires action.
     AskRule(ConnectionEvent),
-    /// Test-only: trigger a notification that does nothing.
-    TestNotify,
+    /// Daemon subscribed and shared its configuration.
+    Subscribe(SubscribeEvent),
+    /// Daemon opened its notifications stream.
+    NotificationsOpened(NotificationsEvent),
+    /// Daemon's notifications stream closed. Carries the node key and the stream's ID.
+    NotificationsClosed(String, u64),
+    /// Daemon replied to a notification.
+    NotificationReply(NotificationReplyEvent),
     /// Quit the application.
     Quit,
 }
@@ -38,8 +45,10 @@ pub enum AppEvent {
 /// Wrapper for pings with extra metadata.
 #[derive(Clone, Debug)]
 pub struct PingEvent {
+    /// Key of the node that sent ping.
+    pub node: String,
     /// The peer addr+port that sent ping.
-    /// May be none if a domain socket was to be supported in the future.
+    /// None if connected over a domain socket.
     pub peer: Option<std::net::SocketAddr>,
     /// Ping payload - stats.
     pub stats: Statistics,
@@ -48,12 +57,57 @@ pub struct PingEvent {
 /// Wrapper for connections with extra metadata.
 #[derive(Clone, Debug)]
 pub struct ConnectionEvent {
+    /// Server-assigned ID of the `AskRule` request that trapped this connection.
+    /// Used to route the resulting rule back to the right RPC.
+    pub id: u64,
+    /// Key of the node that trapped this connection.
+    pub node: String,
     /// The connection that created this event.
     pub connection: Connection,
     /// Expiry timestamp at which point some default action is taken.
     pub expiry_ts: SystemTime,
 }
 
+/// Wrapper for subscriptions with extra metadata.
+#[derive(Clone, Debug)]
+pub struct SubscribeEvent {
+    /// Key of the node that subscribed.
+    pub node: String,
+    /// The peer addr+port that subscribed.
+    pub peer: Option<std::net::SocketAddr>,
+    /// Configuration sent by the daemon.
+    pub config: ClientConfig,
+}
+
+/// A daemon's notifications stream opening.
+#[derive(Clone, Debug)]
+pub struct NotificationsEvent {
+    /// Key of the node that opened the stream.
+    pub node: String,
+    /// Server-assigned ID of the stream, told apart from later streams of the same node.
+    pub stream: u64,
+    /// Handle for the app to send notifications to this node.
+    pub sender: NotificationSender,
+}
+
+/// A daemon's reply to a notification sent by the app.
+#[derive(Clone, Debug)]
+pub struct NotificationReplyEvent {
+    /// Key of the node that replied.
+    pub node: String,
+    /// The reply, carrying the ID of the notification it answers.
+    pub reply: NotificationReply,
+}
+
+/// Rule generated by the app in response to a `ConnectionEvent`.
+#[derive(Clone, Debug)]
+pub struct RuleReply {
+    /// ID of the `ConnectionEvent` this rule answers.
+    pub id: u64,
+    /// The rule to be sent to the daemon.
+    pub rule: Rule,
+}
+
 /// Terminal event handler.
 #[derive(Debug)]
 pub struct EventHandler {
@@ -70,15 +124,20 @@ impl Default for EventHandler {
 }
 
 impl EventHandler {
-    /// Constructs a new instance of [`EventHandler`] and spawns a new thread to handle events.
+    /// Constructs a new instance of [`EventHandler`]. No events are emitted until
+    /// [`EventHandler::spawn`] is called.
     #[must_use]
     pub fn new() -> Self {
         let (sender, receiver) = mpsc::unbounded_channel();
-        let actor = EventTask::new(sender.clone());
-        tokio::spawn(async { actor.run().await });
         Self { sender, receiver }
     }
 
+    /// Spawns a new thread emitting ticks and, if `read_terminal` is set, crossterm events.
+    pub fn spawn(&self, read_terminal: bool) {
+        let actor = EventTask::new(self.sender.clone(), read_terminal);
+        tokio::spawn(async { actor.run().await });
+    }
+
     /// Receives an event from the sender.
     ///
     /// This function blocks until an event is received.
@@ -110,12 +169,17 @@ impl EventHandler {
 struct EventTask {
     /// Event sender channel.
     sender: mpsc::UnboundedSender<Event>,
+    /// Whether to read crossterm events from the terminal.
+    read_terminal: bool,
 }
 
 impl EventTask {
     /// Constructs a new instance of [`EventThread`].
-    fn new(sender: mpsc::UnboundedSender<Event>) -> Self {
-        Self { sender }
+    fn new(sender: mpsc::UnboundedSender<Event>, read_terminal: bool) -> Self {
+        Self {
+            sender,
+            read_terminal,
+        }
     }
 
     /// Runs the event thread.
@@ -123,11 +187,17 @@ impl EventTask {
     /// This function emits tick events at a fixed rate and polls for crossterm events in between.
     async fn run(self) -> color_eyre::Result<()> {
         let tick_rate = Duration::from_secs_f64(1.0 / TICK_FPS);
-        let mut reader = crossterm::event::EventStream::new();
+        let mut reader = self.read_terminal.then(crossterm::event::EventStream::new);
         let mut tick = tokio::time::interval(tick_rate);
         loop {
             let tick_delay = tick.tick();
-            let crossterm_event = reader.next().fuse();
+            let crossterm_event = async {
+                match reader.as_mut() {
+                    Some(reader) => reader.next().await,
+                    None => std::future::pending().await,
+                }
+            }
+            .fuse();
             tokio::select! {
               () = self.sender.closed() => {
                 break;
@@ -0,0 +1,329 @@
+//! Bounded, de-duplicated log of the connection events daemons report in pings.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::opensnitch_proto::pb;
+
+/// Default number of events kept before the oldest are dropped.
+pub const EVENT_LOG_CAPACITY: usize = 1000;
+
+/// A connection event as reported by a node.
+#[derive(Clone, Debug)]
+pub struct LogEntry {
+    /// Order of arrival, increasing.
+    pub seq: u64,
+    /// Key of the node that reported the event.
+    pub node: String,
+    pub event: pb::Event,
+}
+
+impl LogEntry {
+    /// Action of the rule that matched, empty if the daemon sent none.
+    #[must_use]
+    pub fn action(&self) -> &str {
+        self.event
+            .rule
+            .as_ref()
+            .map(|rule| rule.action.as_str())
+            .unwrap_or_default()
+    }
+
+    /// Name of the rule that matched, empty if the daemon sent none.
+    #[must_use]
+    pub fn rule_name(&self) -> &str {
+        self.event
+            .rule
+            .as_ref()
+            .map(|rule| rule.name.as_str())
+            .unwrap_or_default()
+    }
+
+    /// Lowercased text a filter is matched against.
+    fn search_text(&self) -> String {
+        let (process, dst_host, dst_ip, dst_port, protocol) = match &self.event.connection {
+            Some(conn) => (
+                conn.process_path.as_str(),
+                conn.dst_host.as_str(),
+                conn.dst_ip.as_str(),
+                conn.dst_port,
+                conn.protocol.as_str(),
+            ),
+            None => ("", "", "", 0, ""),
+        };
+        format!(
+            "{} {} {} {process} {dst_host} {dst_ip} {dst_port} {protocol}",
+            self.node,
+            self.action(),
+            self.rule_name()
+        )
+        .to_lowercase()
+    }
+}
+
+/// Identity of an event. Daemons keep resending their recent events on every
+/// ping, so this is what tells a new event from one already logged.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct EventKey {
+    node: String,
+    time: String,
+    unixnano: i64,
+    rule: String,
+    pid: u32,
+    process_path: String,
+    dst_ip: String,
+    dst_port: u32,
+}
+
+impl EventKey {
+    fn new(node: &str, event: &pb::Event) -> Self {
+        let conn = event.connection.clone().unwrap_or_default();
+        EventKey {
+            node: node.to_string(),
+            time: event.time.clone(),
+            unixnano: event.unixnano,
+            rule: event
+                .rule
+                .as_ref()
+                .map(|rule| rule.name.clone())
+                .unwrap_or_default(),
+            pid: conn.process_id,
+            process_path: conn.process_path,
+            dst_ip: conn.dst_ip,
+            dst_port: conn.dst_port,
+        }
+    }
+}
+
+/// Ring buffer of logged events plus the event log screen's state.
+#[derive(Debug)]
+pub struct EventLog {
+    /// Oldest first.
+    entries: VecDeque<LogEntry>,
+    /// Keys of `entries`.
+    seen: HashSet<EventKey>,
+    /// Keys of the events each node reported last. Daemons resend events until they roll
+    /// off their list, so these stay known even once evicted from `entries`.
+    reported: HashMap<String, HashSet<EventKey>>,
+    capacity: usize,
+    next_seq: u64,
+    /// Last sequence number shown while paused.
+    paused_at: Option<u64>,
+    /// Index into the visible (filtered, newest first) entries.
+    pub selected: usize,
+    /// Case-insensitive substring to match against node, rule, process and destination.
+    pub filter: String,
+    /// Whether keystrokes are currently going into the filter.
+    pub editing_filter: bool,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        EventLog::new(EVENT_LOG_CAPACITY)
+    }
+}
+
+impl EventLog {
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        EventLog {
+            entries: VecDeque::with_capacity(capacity),
+            seen: HashSet::with_capacity(capacity),
+            reported: HashMap::new(),
+            capacity,
+            next_seq: 0,
+            paused_at: None,
+            selected: 0,
+            filter: String::default(),
+            editing_filter: false,
+        }
+    }
+
+    /// Log the events `node` reported that aren't logged yet, dropping the
+    /// oldest entries beyond capacity. Returns the number of new entries.
+    pub fn record(&mut self, node: &str, events: impl IntoIterator<Item = pb::Event>) -> usize {
+        let mut added = 0;
+        let mut reported = HashSet::new();
+        for event in events {
+            let key = EventKey::new(node, &event);
+            reported.insert(key.clone());
+            if self
+                .reported
+                .get(node)
+                .is_some_and(|keys| keys.contains(&key))
+                || !self.seen.insert(key)
+            {
+                continue;
+            }
+            self.entries.push_back(LogEntry {
+                seq: self.next_seq,
+                node: node.to_string(),
+                event,
+            });
+            self.next_seq += 1;
+            added += 1;
+        }
+        self.reported.insert(node.to_string(), reported);
+        while self.entries.len() > self.capacity {
+            if let Some(evicted) = self.entries.pop_front() {
+                self.seen
+                    .remove(&EventKey::new(&evicted.node, &evicted.event));
+            }
+        }
+        added
+    }
+
+    /// The `n` most recently logged entries, oldest first.
+    pub fn newest(&self, n: usize) -> impl Iterator<Item = &LogEntry> {
+        self.entries
+            .iter()
+            .skip(self.entries.len().saturating_sub(n))
+    }
+
+    /// Freeze or unfreeze the visible entries. Events keep being logged while paused.
+    pub fn toggle_pause(&mut self) {
+        self.paused_at = match self.paused_at {
+            Some(_) => None,
+            None => Some(self.next_seq),
+        };
+    }
+
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    /// Number of entries logged since pausing.
+    #[must_use]
+    pub fn held(&self) -> usize {
+        match self.paused_at {
+            Some(paused_at) => self
+                .entries
+                .iter()
+                .filter(|entry| entry.seq >= paused_at)
+                .count(),
+            None => 0,
+        }
+    }
+
+    /// Entries shown on screen: newest first, matching the filter, none newer than a pause.
+    #[must_use]
+    pub fn visible(&self) -> Vec<&LogEntry> {
+        let filter = self.filter.to_lowercase();
+        self.entries
+            .iter()
+            .rev()
+            .filter(|entry| self.paused_at.is_none_or(|paused_at| entry.seq < paused_at))
+            .filter(|entry| filter.is_empty() || entry.search_text().contains(&filter))
+            .collect()
+    }
+
+    /// Keep selection within bounds of the visible entries.
+    pub fn clamp_selection(&mut self) {
+        self.selected = std::cmp::min(self.selected, self.visible().len().saturating_sub(1));
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_event(unixnano: i64, action: &str, host: &str) -> pb::Event {
+        pb::Event {
+            time: String::from("2025-01-01 00:00:00"),
+            connection: Some(pb::Connection {
+                process_path: String::from("/usr/bin/curl"),
+                dst_host: host.to_string(),
+                dst_port: 443,
+                ..Default::default()
+            }),
+            rule: Some(pb::Rule {
+                name: format!("{action}-{host}"),
+                action: action.to_string(),
+                ..Default::default()
+            }),
+            unixnano,
+        }
+    }
+
+    fn hosts(log: &EventLog) -> Vec<String> {
+        log.visible()
+            .iter()
+            .map(|entry| entry.event.connection.clone().unwrap().dst_host)
+            .collect()
+    }
+
+    /// Test resent events are logged once and the oldest are dropped beyond capacity.
+    #[test]
+    fn test_dedupe_and_capacity() {
+        let mut log = EventLog::new(3);
+        let first = vec![
+            make_event(1, "allow", "a.com"),
+            make_event(2, "deny", "b.com"),
+        ];
+        assert_eq!(log.record("unix", first.clone()), 2);
+        assert_eq!(log.record("unix", first.clone()), 0);
+        // Same event from another node is a different event.
+        assert_eq!(log.record("tcp:[::1]:1234", first), 2);
+        assert_eq!(log.len(), 3);
+        assert_eq!(hosts(&log), vec!["b.com", "a.com", "b.com"]);
+        assert_eq!(log.visible()[2].node, "unix");
+
+        assert_eq!(log.record("unix", vec![make_event(3, "allow", "c.com")]), 1);
+        assert_eq!(log.len(), 3);
+        assert_eq!(log.visible()[0].action(), "allow");
+        assert_eq!(log.visible()[0].rule_name(), "allow-c.com");
+    }
+
+    /// Test events evicted from the log aren't logged again when the daemon resends them.
+    #[test]
+    fn test_resend_after_eviction() {
+        let mut log = EventLog::new(2);
+        let mut events = vec![
+            make_event(1, "allow", "a.com"),
+            make_event(2, "deny", "b.com"),
+            make_event(3, "allow", "c.com"),
+        ];
+        assert_eq!(log.record("unix", events.clone()), 3);
+        assert_eq!(hosts(&log), vec!["c.com", "b.com"]);
+        assert_eq!(log.record("unix", events.clone()), 0);
+
+        // The oldest event rolls off the daemon's list as a new one comes in.
+        events.remove(0);
+        events.push(make_event(4, "deny", "d.com"));
+        assert_eq!(log.record("unix", events.clone()), 1);
+        assert_eq!(log.record("unix", events), 0);
+        assert_eq!(hosts(&log), vec!["d.com", "c.com"]);
+    }
+
+    /// Test pausing freezes the visible entries without losing new ones, and filtering.
+    #[test]
+    fn test_pause_and_filter() {
+        let mut log = EventLog::default();
+        log.record("unix", vec![make_event(1, "allow", "a.com")]);
+        log.toggle_pause();
+        assert!(log.is_paused());
+        log.record("unix", vec![make_event(2, "deny", "b.com")]);
+        assert_eq!(hosts(&log), vec!["a.com"]);
+        assert_eq!(log.held(), 1);
+
+        log.toggle_pause();
+        assert_eq!(log.held(), 0);
+        assert_eq!(hosts(&log), vec!["b.com", "a.com"]);
+
+        log.filter = String::from("DENY");
+        assert_eq!(hosts(&log), vec!["b.com"]);
+        log.selected = 5;
+        log.clamp_selection();
+        assert_eq!(log.selected, 0);
+    }
+}
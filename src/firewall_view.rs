@@ -0,0 +1,802 @@
+//! Decoding and editing of the system firewall chains and rules a daemon reports, for the
+//! firewall screen.
+
+use std::fmt::Write as _;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+
+use crate::node::NodeRegistry;
+use crate::opensnitch_proto::pb;
+
+/// Comparison operators accepted in rule specs.
+const OPERATORS: [&str; 6] = ["==", "!=", "<", ">", "<=", ">="];
+
+/// Rules offered when adding one, as a name and a spec for `parse_rule`.
+pub const RULE_TEMPLATES: [(&str, &str); 4] = [
+    (
+        "drop inbound SSH from non-LAN",
+        "drop tcp dport == 22, ip saddr != 10.0.0.0/8, ip saddr != 172.16.0.0/12, \
+        ip saddr != 192.168.0.0/16 # Drop inbound SSH from non-LAN",
+    ),
+    (
+        "accept established",
+        "accept ct state == related,established # Accept established connections",
+    ),
+    (
+        "accept loopback",
+        "accept meta iifname == lo # Accept loopback",
+    ),
+    (
+        "drop inbound port",
+        "drop tcp dport == 8080 # Drop inbound port 8080",
+    ),
+];
+
+/// A line of the firewall screen, addressing its chain or rule by position so it can be
+/// looked up again in a newer copy of the firewall.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FirewallRow {
+    /// Chain `chain` of `SystemRules[group]`.
+    Chain { group: usize, chain: usize },
+    /// Rule `rule` of that chain.
+    Rule {
+        group: usize,
+        chain: usize,
+        rule: usize,
+    },
+    /// Deprecated iptables rule of `SystemRules[group]`.
+    Legacy { group: usize },
+}
+
+/// All rows of a firewall, each chain followed by its rules.
+#[must_use]
+pub fn rows(firewall: &pb::SysFirewall) -> Vec<FirewallRow> {
+    let mut res = Vec::default();
+    for (group, chains) in firewall.system_rules.iter().enumerate() {
+        if chains.rule.is_some() {
+            res.push(FirewallRow::Legacy { group });
+        }
+        for (chain_idx, chain) in chains.chains.iter().enumerate() {
+            res.push(FirewallRow::Chain {
+                group,
+                chain: chain_idx,
+            });
+            res.extend((0..chain.rules.len()).map(|rule| FirewallRow::Rule {
+                group,
+                chain: chain_idx,
+                rule,
+            }));
+        }
+    }
+    res
+}
+
+/// Chain at the given position, if still there.
+#[must_use]
+pub fn chain(firewall: &pb::SysFirewall, group: usize, chain: usize) -> Option<&pb::FwChain> {
+    firewall.system_rules.get(group)?.chains.get(chain)
+}
+
+fn chain_mut(
+    firewall: &mut pb::SysFirewall,
+    group: usize,
+    chain: usize,
+) -> Option<&mut pb::FwChain> {
+    firewall.system_rules.get_mut(group)?.chains.get_mut(chain)
+}
+
+/// Enable or disable the rule at `row`. Returns whether there was a rule to toggle.
+pub fn toggle_rule(firewall: &mut pb::SysFirewall, row: FirewallRow) -> bool {
+    let FirewallRow::Rule { group, chain, rule } = row else {
+        return false;
+    };
+    match chain_mut(firewall, group, chain).and_then(|chain| chain.rules.get_mut(rule)) {
+        Some(rule) => {
+            rule.enabled = !rule.enabled;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Remove the rule at `row`. Returns whether there was a rule to remove.
+pub fn remove_rule(firewall: &mut pb::SysFirewall, row: FirewallRow) -> bool {
+    let FirewallRow::Rule { group, chain, rule } = row else {
+        return false;
+    };
+    match chain_mut(firewall, group, chain) {
+        Some(chain) if rule < chain.rules.len() => {
+            chain.rules.remove(rule);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Swap the rule at `row` with the one above it, or below it if `down`, within its chain.
+/// Returns the rule's new row.
+pub fn move_rule(
+    firewall: &mut pb::SysFirewall,
+    row: FirewallRow,
+    down: bool,
+) -> Option<FirewallRow> {
+    let FirewallRow::Rule { group, chain, rule } = row else {
+        return None;
+    };
+    let rules = &mut chain_mut(firewall, group, chain)?.rules;
+    let target = if down {
+        rule.checked_add(1)?
+    } else {
+        rule.checked_sub(1)?
+    };
+    if target >= rules.len() {
+        return None;
+    }
+    rules.swap(rule, target);
+    Some(FirewallRow::Rule {
+        group,
+        chain,
+        rule: target,
+    })
+}
+
+/// Insert `new_rule` after the rule at `row`, or first in the chain at `row`.
+/// Returns the new rule's row.
+pub fn insert_rule(
+    firewall: &mut pb::SysFirewall,
+    row: FirewallRow,
+    new_rule: pb::FwRule,
+) -> Option<FirewallRow> {
+    let (group, chain, idx) = match row {
+        FirewallRow::Chain { group, chain } => (group, chain, 0),
+        FirewallRow::Rule { group, chain, rule } => (group, chain, rule + 1),
+        FirewallRow::Legacy { .. } => return None,
+    };
+    let fw_chain = chain_mut(firewall, group, chain)?;
+    let mut new_rule = new_rule;
+    new_rule.table.clone_from(&fw_chain.table);
+    new_rule.chain.clone_from(&fw_chain.name);
+    fw_chain.rules.insert(idx, new_rule);
+    Some(FirewallRow::Rule {
+        group,
+        chain,
+        rule: idx,
+    })
+}
+
+/// Parse a rule written like it's listed, `<target> <statement>, ... [# description]`, where a
+/// statement is `<name> <key> [<operator>] [<value>]` and the operator defaults to `==`, e.g.
+/// `drop tcp dport == 22, ip saddr != 192.168.0.0/16 # no ssh from outside`.
+/// # Errors
+/// If the target is missing or a statement is malformed.
+pub fn parse_rule(spec: &str) -> Result<pb::FwRule, String> {
+    let (body, description) = match spec.split_once('#') {
+        Some((body, description)) => (body, description.trim()),
+        None => (spec, ""),
+    };
+    let body = body.trim();
+    let (target, statements) = body.split_once(' ').unwrap_or((body, ""));
+    if target.is_empty() {
+        return Err(String::from("Missing target, e.g. accept or drop"));
+    }
+    let mut expressions: Vec<pb::Expressions> = Vec::new();
+    for piece in statements.split(',').map(str::trim) {
+        if piece.is_empty() {
+            continue;
+        }
+        // A lone word adds to the values of the statement before it, as in
+        // `ct state == related,established`.
+        if !piece.contains(char::is_whitespace)
+            && let Some(statement) = expressions
+                .last_mut()
+                .and_then(|expr| expr.statement.as_mut())
+            && let Some(key) = statement
+                .values
+                .last()
+                .filter(|value| !value.value.is_empty())
+                .map(|value| value.key.clone())
+        {
+            statement.values.push(pb::StatementValues {
+                key,
+                value: piece.to_string(),
+            });
+            continue;
+        }
+        expressions.push(parse_statement(piece)?);
+    }
+    Ok(pb::FwRule {
+        uuid: make_uuid(spec),
+        enabled: true,
+        description: description.to_string(),
+        expressions,
+        target: target.to_lowercase(),
+        ..Default::default()
+    })
+}
+
+fn parse_statement(statement: &str) -> Result<pb::Expressions, String> {
+    let words: Vec<&str> = statement.split_whitespace().collect();
+    let (name, key, op, value) = match words.as_slice() {
+        [name, key] => (*name, *key, "", ""),
+        [name, key, value] => (*name, *key, "==", *value),
+        [name, key, op, value] if OPERATORS.contains(op) => (*name, *key, *op, *value),
+        _ => {
+            return Err(format!(
+                "Invalid statement \"{statement}\", expected <name> <key> [<operator>] [<value>]"
+            ));
+        }
+    };
+    Ok(pb::Expressions {
+        statement: Some(pb::Statement {
+            op: op.to_string(),
+            name: name.to_string(),
+            values: vec![pb::StatementValues {
+                key: key.to_string(),
+                value: value.to_string(),
+            }],
+        }),
+    })
+}
+
+/// Random-looking version 4 UUID for a new rule, derived from the clock and its spec.
+fn make_uuid(seed: &str) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_nanos())
+        .unwrap_or_default();
+    let mut bytes: [u8; 16] = Sha256::digest(format!("{nanos}{seed}").as_bytes())[..16]
+        .try_into()
+        .unwrap_or_default();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let mut hex = String::with_capacity(36);
+    for (idx, byte) in bytes.iter().enumerate() {
+        if matches!(idx, 4 | 6 | 8 | 10) {
+            hex.push('-');
+        }
+        let _ = write!(hex, "{byte:02x}");
+    }
+    hex
+}
+
+/// Changed lines between two firewalls as listed on the firewall screen, prefixed with `-`
+/// or `+`, under the header of the chain they belong to.
+#[must_use]
+pub fn diff_lines(old: &pb::SysFirewall, new: &pb::SysFirewall) -> Vec<String> {
+    let old_lines: Vec<String> = rows(old)
+        .into_iter()
+        .map(|row| row_summary(old, row))
+        .collect();
+    let new_lines: Vec<String> = rows(new)
+        .into_iter()
+        .map(|row| row_summary(new, row))
+        .collect();
+    // Longest common subsequence table, from the end of both lists.
+    let mut lcs = vec![vec![0usize; new_lines.len() + 1]; old_lines.len() + 1];
+    for i in (0..old_lines.len()).rev() {
+        for j in (0..new_lines.len()).rev() {
+            lcs[i][j] = if old_lines[i] == new_lines[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                std::cmp::max(lcs[i + 1][j], lcs[i][j + 1])
+            };
+        }
+    }
+    let mut res = Vec::default();
+    let mut header: Option<&str> = None;
+    let push = |res: &mut Vec<String>, header: &mut Option<&str>, line: String| {
+        if let Some(header) = header.take() {
+            res.push(format!("  {header}"));
+        }
+        res.push(line);
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < old_lines.len() || j < new_lines.len() {
+        if i < old_lines.len() && j < new_lines.len() && old_lines[i] == new_lines[j] {
+            // Rule lines are indented, chain headers aren't.
+            if !old_lines[i].starts_with(' ') {
+                header = Some(&old_lines[i]);
+            }
+            i += 1;
+            j += 1;
+        } else if i < old_lines.len() && (j == new_lines.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            push(
+                &mut res,
+                &mut header,
+                format!("- {}", old_lines[i].trim_start()),
+            );
+            i += 1;
+        } else {
+            push(
+                &mut res,
+                &mut header,
+                format!("+ {}", new_lines[j].trim_start()),
+            );
+            j += 1;
+        }
+    }
+    res
+}
+
+/// One-line summary of a chain, e.g.
+/// `input (table filter, family inet, type filter, hook input, priority 0, policy accept)`.
+#[must_use]
+pub fn chain_summary(chain: &pb::FwChain) -> String {
+    let attrs: Vec<String> = [
+        ("table", &chain.table),
+        ("family", &chain.family),
+        ("type", &chain.r#type),
+        ("hook", &chain.hook),
+        ("priority", &chain.priority),
+        ("policy", &chain.policy),
+    ]
+    .into_iter()
+    .filter(|(_, value)| !value.is_empty())
+    .map(|(key, value)| format!("{key} {value}"))
+    .collect();
+    format!("{} ({})", chain.name, attrs.join(", "))
+}
+
+/// A statement in nftables-like syntax, e.g. `tcp dport == 22` or `ct state established,related`.
+#[must_use]
+pub fn statement_str(statement: &pb::Statement) -> String {
+    let mut words = vec![statement.name.clone()];
+    let mut values = statement.values.iter().peekable();
+    while let Some(value) = values.next() {
+        words.push(value.key.clone());
+        if !value.value.is_empty() {
+            // Values of the same key are listed together.
+            let mut list = vec![value.value.as_str()];
+            while let Some(next) =
+                values.next_if(|next| next.key == value.key && !next.value.is_empty())
+            {
+                list.push(&next.value);
+            }
+            words.push(statement.op.clone());
+            words.push(list.join(","));
+        }
+    }
+    words.retain(|word| !word.is_empty());
+    words.join(" ")
+}
+
+/// Match of a rule: its decoded expressions, or the raw iptables parameters if it has none.
+#[must_use]
+pub fn rule_match(rule: &pb::FwRule) -> String {
+    let statements: Vec<String> = rule
+        .expressions
+        .iter()
+        .filter_map(|expr| expr.statement.as_ref())
+        .map(statement_str)
+        .collect();
+    if statements.is_empty() {
+        rule.parameters.clone()
+    } else {
+        statements.join(", ")
+    }
+}
+
+/// Verdict of a rule with its parameters, e.g. `jump other-chain`.
+#[must_use]
+pub fn target_str(rule: &pb::FwRule) -> String {
+    if rule.target_parameters.is_empty() {
+        rule.target.clone()
+    } else {
+        format!("{} {}", rule.target, rule.target_parameters)
+    }
+}
+
+/// One-line summary of a rule, e.g. `  1 on  drop    tcp dport == 22 # no ssh`.
+#[must_use]
+pub fn rule_summary(rule: &pb::FwRule) -> String {
+    let description = if rule.description.is_empty() {
+        String::default()
+    } else {
+        format!(" # {}", rule.description)
+    };
+    format!(
+        "{:>3} {:<3} {:<7} {}{description}",
+        rule.position,
+        if rule.enabled { "on" } else { "off" },
+        target_str(rule),
+        rule_match(rule)
+    )
+}
+
+/// Text of a row as listed on the firewall screen.
+#[must_use]
+pub fn row_summary(firewall: &pb::SysFirewall, row: FirewallRow) -> String {
+    match row {
+        FirewallRow::Chain { group, chain: idx } => chain(firewall, group, idx)
+            .map(chain_summary)
+            .unwrap_or_default(),
+        FirewallRow::Rule {
+            group,
+            chain: idx,
+            rule,
+        } => chain(firewall, group, idx)
+            .and_then(|chain| chain.rules.get(rule))
+            .map(|rule| format!("  {}", rule_summary(rule)))
+            .unwrap_or_default(),
+        FirewallRow::Legacy { group } => legacy_rule(firewall, group)
+            .map(|rule| {
+                format!(
+                    "iptables {} {}: {} -j {}",
+                    rule.table,
+                    rule.chain,
+                    rule.parameters,
+                    target_str(rule)
+                )
+            })
+            .unwrap_or_default(),
+    }
+}
+
+/// Every attribute of a row, one per line.
+#[must_use]
+pub fn row_detail_lines(firewall: &pb::SysFirewall, row: FirewallRow) -> Vec<String> {
+    match row {
+        FirewallRow::Chain { group, chain: idx } => chain(firewall, group, idx)
+            .map(|chain| {
+                vec![
+                    format!("chain: {}", chain.name),
+                    format!("table: {} | family: {}", chain.table, chain.family),
+                    format!(
+                        "type: {} | hook: {} | priority: {}",
+                        chain.r#type, chain.hook, chain.priority
+                    ),
+                    format!("policy: {} | rules: {}", chain.policy, chain.rules.len()),
+                ]
+            })
+            .unwrap_or_default(),
+        FirewallRow::Rule {
+            group,
+            chain: idx,
+            rule,
+        } => chain(firewall, group, idx)
+            .and_then(|chain| chain.rules.get(rule))
+            .map(rule_detail_lines)
+            .unwrap_or_default(),
+        FirewallRow::Legacy { group } => legacy_rule(firewall, group)
+            .map(rule_detail_lines)
+            .unwrap_or_default(),
+    }
+}
+
+fn rule_detail_lines(rule: &pb::FwRule) -> Vec<String> {
+    let mut lines = vec![
+        format!(
+            "{} | position: {} | enabled: {} | uuid: {}",
+            if rule.description.is_empty() {
+                "no description"
+            } else {
+                &rule.description
+            },
+            rule.position,
+            rule.enabled,
+            rule.uuid
+        ),
+        format!("target: {}", target_str(rule)),
+    ];
+    if !rule.parameters.is_empty() {
+        lines.push(format!("parameters: {}", rule.parameters));
+    }
+    lines.extend(
+        rule.expressions
+            .iter()
+            .filter_map(|expr| expr.statement.as_ref())
+            .map(|statement| format!("  {}", statement_str(statement))),
+    );
+    lines
+}
+
+fn legacy_rule(firewall: &pb::SysFirewall, group: usize) -> Option<&pb::FwRule> {
+    firewall.system_rules.get(group)?.rule.as_ref()
+}
+
+/// Edited copy of a node's system firewall.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FirewallDraft {
+    /// Key of the node the firewall belongs to.
+    pub node: String,
+    pub firewall: pb::SysFirewall,
+}
+
+/// User-controlled state of the firewall screen.
+#[derive(Debug, Default)]
+pub struct FirewallView {
+    /// Index into the rows of the shown firewall.
+    pub selected: usize,
+    /// Outcome of the latest firewall operation.
+    pub status: Option<String>,
+    /// Edits not sent yet, shown instead of the firewall their node reported.
+    pub draft: Option<FirewallDraft>,
+    /// Spec of a rule being typed in, to be added after the selected row.
+    pub new_rule: Option<String>,
+    /// Index into `RULE_TEMPLATES` of the template last put into `new_rule`.
+    pub template: usize,
+    /// Whether the user is being asked to confirm sending the draft, seeing its diff.
+    pub confirm_send: bool,
+    /// Node the user is being asked to confirm reapplying the reported firewall of.
+    pub confirm_reload: Option<String>,
+}
+
+impl FirewallView {
+    /// Draft of the given node's firewall, if it's being edited.
+    #[must_use]
+    pub fn draft_for(&self, node: &str) -> Option<&pb::SysFirewall> {
+        self.draft
+            .as_ref()
+            .filter(|draft| draft.node == node)
+            .map(|draft| &draft.firewall)
+    }
+
+    /// Firewall of the selected node, the draft of it if there is one.
+    #[must_use]
+    pub fn shown<'a>(&'a self, nodes: &'a NodeRegistry) -> Option<&'a pb::SysFirewall> {
+        nodes
+            .selected_key()
+            .and_then(|key| self.draft_for(key))
+            .or_else(|| nodes.selected_firewall())
+    }
+
+    /// Replace the rule being typed in with the next template.
+    pub fn next_template(&mut self) {
+        if self.new_rule.as_deref() == Some(RULE_TEMPLATES[self.template].1) {
+            self.template = (self.template + 1) % RULE_TEMPLATES.len();
+        }
+        self.new_rule = Some(RULE_TEMPLATES[self.template].1.to_string());
+    }
+
+    /// The selected row of `firewall`, if any.
+    #[must_use]
+    pub fn selected_row(&self, firewall: &pb::SysFirewall) -> Option<FirewallRow> {
+        rows(firewall).get(self.selected).copied()
+    }
+
+    /// Select `row` of `firewall`, if it's there.
+    pub fn select_row(&mut self, firewall: &pb::SysFirewall, row: FirewallRow) {
+        if let Some(idx) = rows(firewall).iter().position(|other| *other == row) {
+            self.selected = idx;
+        }
+    }
+
+    /// Keep selection within bounds of the shown firewall's rows.
+    pub fn clamp_selection(&mut self, nodes: &NodeRegistry) {
+        let len = self.shown(nodes).map_or(0, |firewall| rows(firewall).len());
+        self.selected = std::cmp::min(self.selected, len.saturating_sub(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_statement(name: &str, op: &str, values: &[(&str, &str)]) -> pb::Expressions {
+        pb::Expressions {
+            statement: Some(pb::Statement {
+                op: op.to_string(),
+                name: name.to_string(),
+                values: values
+                    .iter()
+                    .map(|(key, value)| pb::StatementValues {
+                        key: (*key).to_string(),
+                        value: (*value).to_string(),
+                    })
+                    .collect(),
+            }),
+        }
+    }
+
+    fn make_firewall() -> pb::SysFirewall {
+        pb::SysFirewall {
+            enabled: true,
+            version: 1,
+            system_rules: vec![pb::FwChains {
+                rule: None,
+                chains: vec![pb::FwChain {
+                    name: String::from("input"),
+                    table: String::from("filter"),
+                    rules: ["a", "b", "c"]
+                        .into_iter()
+                        .map(|description| pb::FwRule {
+                            enabled: true,
+                            description: description.to_string(),
+                            target: String::from("accept"),
+                            ..Default::default()
+                        })
+                        .collect(),
+                    ..Default::default()
+                }],
+            }],
+        }
+    }
+
+    /// Test rule specs parse into statements, with `==` as default operator.
+    #[test]
+    fn test_parse_rule() {
+        let rule = parse_rule(RULE_TEMPLATES[0].1).expect("parse failed");
+        assert_eq!(rule.target, "drop");
+        assert_eq!(rule.description, "Drop inbound SSH from non-LAN");
+        assert_eq!(
+            rule_match(&rule),
+            "tcp dport == 22, ip saddr != 10.0.0.0/8, ip saddr != 172.16.0.0/12, \
+            ip saddr != 192.168.0.0/16"
+        );
+        assert!(rule.enabled);
+        assert_eq!(rule.uuid.len(), 36);
+        assert_eq!(&rule.uuid[14..15], "4");
+
+        let rule = parse_rule("ACCEPT tcp dport 443, counter packets").expect("parse failed");
+        assert_eq!(rule_match(&rule), "tcp dport == 443, counter packets");
+        assert_eq!(rule.target, "accept");
+
+        let rule = parse_rule(RULE_TEMPLATES[1].1).expect("parse failed");
+        assert_eq!(rule_match(&rule), "ct state == related,established");
+        let values = &rule.expressions[0]
+            .statement
+            .as_ref()
+            .expect("no statement")
+            .values;
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[1].key, "state");
+        assert_eq!(values[1].value, "established");
+
+        assert!(parse_rule(" # nothing").is_err());
+        assert!(parse_rule("drop tcp dport ~ 22").is_err());
+        assert!(parse_rule("drop tcp").is_err());
+        assert!(parse_rule("drop counter, packets").is_err());
+    }
+
+    /// Test every built-in template can be added.
+    #[test]
+    fn test_rule_templates() {
+        for (name, spec) in RULE_TEMPLATES {
+            assert!(parse_rule(spec).is_ok(), "template {name} doesn't parse");
+        }
+    }
+
+    /// Test editing a firewall and diffing it against the original.
+    #[test]
+    fn test_edit_and_diff() {
+        let original = make_firewall();
+        let mut firewall = original.clone();
+        let rule = |rule| FirewallRow::Rule {
+            group: 0,
+            chain: 0,
+            rule,
+        };
+        assert!(toggle_rule(&mut firewall, rule(0)));
+        assert!(!firewall.system_rules[0].chains[0].rules[0].enabled);
+        assert!(!toggle_rule(
+            &mut firewall,
+            FirewallRow::Chain { group: 0, chain: 0 }
+        ));
+
+        assert_eq!(move_rule(&mut firewall, rule(2), true), None);
+        assert_eq!(move_rule(&mut firewall, rule(2), false), Some(rule(1)));
+        assert!(remove_rule(&mut firewall, rule(2)));
+        assert!(!remove_rule(&mut firewall, rule(2)));
+
+        let new_rule = parse_rule("drop tcp dport 22 # d").expect("parse failed");
+        assert_eq!(
+            insert_rule(
+                &mut firewall,
+                FirewallRow::Chain { group: 0, chain: 0 },
+                new_rule
+            ),
+            Some(rule(0))
+        );
+        let inserted = &firewall.system_rules[0].chains[0].rules[0];
+        assert_eq!(
+            (inserted.table.as_str(), inserted.chain.as_str()),
+            ("filter", "input")
+        );
+
+        let descriptions: Vec<&str> = firewall.system_rules[0].chains[0]
+            .rules
+            .iter()
+            .map(|rule| rule.description.as_str())
+            .collect();
+        assert_eq!(descriptions, vec!["d", "a", "c"]);
+        assert_eq!(
+            diff_lines(&original, &firewall),
+            vec![
+                "  input (table filter)",
+                "- 0 on  accept   # a",
+                "- 0 on  accept   # b",
+                "+ 0 on  drop    tcp dport == 22 # d",
+                "+ 0 off accept   # a",
+            ]
+        );
+        assert!(diff_lines(&original, &original).is_empty());
+    }
+
+    /// Test chains and rules are listed in order and decoded.
+    #[test]
+    fn test_rows() {
+        let firewall = pb::SysFirewall {
+            enabled: true,
+            version: 1,
+            system_rules: vec![pb::FwChains {
+                rule: None,
+                chains: vec![
+                    pb::FwChain {
+                        name: String::from("input"),
+                        table: String::from("filter"),
+                        family: String::from("inet"),
+                        priority: String::from("0"),
+                        r#type: String::from("filter"),
+                        hook: String::from("input"),
+                        policy: String::from("accept"),
+                        rules: vec![pb::FwRule {
+                            enabled: true,
+                            position: 1,
+                            description: String::from("no ssh"),
+                            expressions: vec![
+                                make_statement("tcp", "==", &[("dport", "22")]),
+                                make_statement("ct", "", &[("state", "new")]),
+                                make_statement("counter", "", &[("packets", "")]),
+                            ],
+                            target: String::from("drop"),
+                            ..Default::default()
+                        }],
+                    },
+                    pb::FwChain {
+                        name: String::from("forward"),
+                        ..Default::default()
+                    },
+                ],
+            }],
+        };
+        let rows = rows(&firewall);
+        assert_eq!(
+            rows,
+            vec![
+                FirewallRow::Chain { group: 0, chain: 0 },
+                FirewallRow::Rule {
+                    group: 0,
+                    chain: 0,
+                    rule: 0
+                },
+                FirewallRow::Chain { group: 0, chain: 1 },
+            ]
+        );
+        assert_eq!(
+            row_summary(&firewall, rows[0]),
+            "input (table filter, family inet, type filter, hook input, priority 0, policy accept)"
+        );
+        assert_eq!(
+            row_summary(&firewall, rows[1]),
+            "    1 on  drop    tcp dport == 22, ct state new, counter packets # no ssh"
+        );
+        assert_eq!(row_summary(&firewall, rows[2]), "forward ()");
+        assert_eq!(row_detail_lines(&firewall, rows[1]).len(), 5);
+
+        let mut view = FirewallView {
+            selected: 7,
+            ..Default::default()
+        };
+        let mut nodes = NodeRegistry::default();
+        view.clamp_selection(&nodes);
+        assert_eq!(view.selected, 0);
+        nodes.get_or_insert("unix").config = Some(pb::ClientConfig {
+            system_firewall: Some(firewall.clone()),
+            ..Default::default()
+        });
+        view.selected = 7;
+        view.clamp_selection(&nodes);
+        assert_eq!(view.selected_row(&firewall), Some(rows[2]));
+
+        // Drafts are shown instead of the reported firewall, only for their node.
+        view.draft = Some(FirewallDraft {
+            node: String::from("unix"),
+            firewall: pb::SysFirewall::default(),
+        });
+        assert_eq!(view.shown(&nodes), Some(&pb::SysFirewall::default()));
+        assert!(view.draft_for("10.0.0.1").is_none());
+        view.clamp_selection(&nodes);
+        assert_eq!(view.selected, 0);
+    }
+}
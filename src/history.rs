@@ -0,0 +1,340 @@
+//! Append-only, rotated JSON-lines journal of what the TUI saw and decided.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Journal file name under the data directory.
+pub const HISTORY_FILE_NAME: &str = "history.jsonl";
+
+/// Size past which the journal is rotated.
+pub const MAX_JOURNAL_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Number of rotated journals kept, as `history.jsonl.1` (newest) and up.
+pub const MAX_ROTATED_JOURNALS: usize = 3;
+
+/// `--history-db` value that disables the journal.
+pub const HISTORY_DISABLED: &str = "none";
+
+/// What a journal record is about.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordKind {
+    /// A daemon asked what to do with a connection.
+    Connection,
+    /// We answered a connection with a rule.
+    Decision,
+    /// A connection went unanswered and the daemon fell back to its default action.
+    Timeout,
+    Alert,
+    /// A connection event a daemon reported in a ping.
+    Event,
+}
+
+impl RecordKind {
+    pub const ALL: [RecordKind; 5] = [
+        RecordKind::Connection,
+        RecordKind::Decision,
+        RecordKind::Timeout,
+        RecordKind::Alert,
+        RecordKind::Event,
+    ];
+
+    #[must_use]
+    pub fn get_str(&self) -> &str {
+        match self {
+            RecordKind::Connection => "connection",
+            RecordKind::Decision => "decision",
+            RecordKind::Timeout => "timeout",
+            RecordKind::Alert => "alert",
+            RecordKind::Event => "event",
+        }
+    }
+}
+
+/// A line of the journal.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Record {
+    /// Seconds since the Unix epoch.
+    pub ts: u64,
+    pub kind: RecordKind,
+    /// Key of the node the record is about, empty if none.
+    pub node: String,
+    /// One-line, human readable description.
+    pub summary: String,
+    /// Full payload, e.g. the connection or rule.
+    #[serde(default)]
+    pub data: serde_json::Value,
+}
+
+impl Record {
+    #[must_use]
+    pub fn new(
+        ts: SystemTime,
+        kind: RecordKind,
+        node: &str,
+        summary: String,
+        data: serde_json::Value,
+    ) -> Self {
+        Record {
+            ts: ts
+                .duration_since(UNIX_EPOCH)
+                .map(|since| since.as_secs())
+                .unwrap_or_default(),
+            kind,
+            node: node.to_string(),
+            summary,
+            data,
+        }
+    }
+}
+
+/// Journal location from the `--history-db` flag, defaulting to the XDG data
+/// directory. `None` if disabled or no data directory can be determined.
+#[must_use]
+pub fn resolve_path(flag: Option<&str>) -> Option<PathBuf> {
+    match flag {
+        Some(HISTORY_DISABLED) => None,
+        Some(path) => Some(PathBuf::from(path)),
+        None => {
+            let data_home = std::env::var_os("XDG_DATA_HOME")
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+                .or_else(|| {
+                    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+                })?;
+            Some(data_home.join("opensnitch-tui").join(HISTORY_FILE_NAME))
+        }
+    }
+}
+
+/// Path of the `n`th rotated journal, `n` starting at 1.
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
+
+/// Writer of the journal.
+#[derive(Debug)]
+pub struct HistoryJournal {
+    path: PathBuf,
+    file: File,
+    /// Current size of the file at `path`.
+    size: u64,
+    max_bytes: u64,
+}
+
+impl HistoryJournal {
+    /// Open the journal for appending, creating it and its directory if needed.
+    /// # Errors
+    /// If the directory or file can't be created or opened.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        HistoryJournal::open_with_max_bytes(path, MAX_JOURNAL_BYTES)
+    }
+
+    fn open_with_max_bytes(path: &Path, max_bytes: u64) -> Result<Self, String> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .map_err(|err| format!("Unable to create {}: {err}", dir.display()))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|err| format!("Unable to open {}: {err}", path.display()))?;
+        let size = file
+            .metadata()
+            .map_err(|err| format!("Unable to stat {}: {err}", path.display()))?
+            .len();
+        Ok(HistoryJournal {
+            path: path.to_path_buf(),
+            file,
+            size,
+            max_bytes,
+        })
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a record, rotating the journal first if it's full.
+    /// # Errors
+    /// If the record can't be serialized or written, or rotation fails.
+    pub fn append(&mut self, record: &Record) -> Result<(), String> {
+        let mut line = serde_json::to_string(record)
+            .map_err(|err| format!("Unable to serialize history record: {err}"))?;
+        line.push('\n');
+        if self.size > 0 && self.size + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file
+            .write_all(line.as_bytes())
+            .map_err(|err| format!("Unable to write {}: {err}", self.path.display()))?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Shift rotated journals up by one, dropping the oldest, and start a new one.
+    fn rotate(&mut self) -> Result<(), String> {
+        for n in (1..MAX_ROTATED_JOURNALS).rev() {
+            let from = rotated_path(&self.path, n);
+            if from.exists() {
+                std::fs::rename(&from, rotated_path(&self.path, n + 1))
+                    .map_err(|err| format!("Unable to rotate {}: {err}", from.display()))?;
+            }
+        }
+        std::fs::rename(&self.path, rotated_path(&self.path, 1))
+            .map_err(|err| format!("Unable to rotate {}: {err}", self.path.display()))?;
+        *self = HistoryJournal::open_with_max_bytes(&self.path, self.max_bytes)?;
+        Ok(())
+    }
+}
+
+/// Read up to `limit` of the most recent records of the journal at `path` and
+/// its rotations, oldest first. Lines that fail to parse are skipped.
+/// # Errors
+/// If the journal exists but can't be read.
+pub fn read_records(path: &Path, limit: usize) -> Result<Vec<Record>, String> {
+    let mut paths: Vec<PathBuf> = (1..=MAX_ROTATED_JOURNALS)
+        .rev()
+        .map(|n| rotated_path(path, n))
+        .collect();
+    paths.push(path.to_path_buf());
+
+    let mut res = std::collections::VecDeque::with_capacity(limit);
+    for path in paths.iter().filter(|path| path.exists()) {
+        let file =
+            File::open(path).map_err(|err| format!("Unable to open {}: {err}", path.display()))?;
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|err| format!("Unable to read {}: {err}", path.display()))?;
+            if let Ok(record) = serde_json::from_str::<Record>(&line) {
+                if res.len() == limit {
+                    res.pop_front();
+                }
+                if limit > 0 {
+                    res.push_back(record);
+                }
+            }
+        }
+    }
+    Ok(res.into())
+}
+
+/// Format seconds since the Unix epoch as a UTC date and time.
+#[must_use]
+pub fn format_utc(ts: u64) -> String {
+    let days = ts / 86_400;
+    let secs = ts % 86_400;
+    // Civil from days, see https://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_path(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("opensnitch-tui-test-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join("nested").join(HISTORY_FILE_NAME)
+    }
+
+    fn record(n: u64) -> Record {
+        Record::new(
+            UNIX_EPOCH + std::time::Duration::from_secs(n),
+            RecordKind::Decision,
+            "unix",
+            format!("record {n}"),
+            serde_json::json!({"n": n}),
+        )
+    }
+
+    /// Test records round trip through the journal, surviving reopening.
+    #[test]
+    fn test_append_and_read() {
+        let path = scratch_path("append");
+        let mut journal = HistoryJournal::open(&path).expect("open failed");
+        journal.append(&record(1)).expect("append failed");
+        drop(journal);
+        let mut journal = HistoryJournal::open(&path).expect("reopen failed");
+        journal.append(&record(2)).expect("append failed");
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(b"not json\n"))
+            .expect("write failed");
+
+        assert_eq!(
+            read_records(&path, 10).expect("read failed"),
+            vec![record(1), record(2)]
+        );
+        assert_eq!(
+            read_records(&path, 1).expect("read failed"),
+            vec![record(2)]
+        );
+        assert!(
+            read_records(&path.with_file_name("missing.jsonl"), 10)
+                .expect("read failed")
+                .is_empty()
+        );
+        let _ = std::fs::remove_dir_all(path.parent().unwrap().parent().unwrap());
+    }
+
+    /// Test the journal rotates when full, dropping the oldest rotation.
+    #[test]
+    fn test_rotation() {
+        let path = scratch_path("rotation");
+        let line_len = serde_json::to_string(&record(0)).unwrap().len() as u64 + 1;
+        // Two records per file.
+        let mut journal =
+            HistoryJournal::open_with_max_bytes(&path, line_len * 2).expect("open failed");
+        for n in 0..10 {
+            journal.append(&record(n)).expect("append failed");
+        }
+        assert!(rotated_path(&path, MAX_ROTATED_JOURNALS).exists());
+        assert!(!rotated_path(&path, MAX_ROTATED_JOURNALS + 1).exists());
+
+        let summaries: Vec<String> = read_records(&path, 100)
+            .expect("read failed")
+            .into_iter()
+            .map(|record| record.summary)
+            .collect();
+        assert_eq!(
+            summaries,
+            (2..10).map(|n| format!("record {n}")).collect::<Vec<_>>()
+        );
+        let _ = std::fs::remove_dir_all(path.parent().unwrap().parent().unwrap());
+    }
+
+    /// Test path resolution and UTC formatting.
+    #[test]
+    fn test_resolve_path_and_format() {
+        assert_eq!(resolve_path(Some(HISTORY_DISABLED)), None);
+        assert_eq!(
+            resolve_path(Some("/tmp/h.jsonl")),
+            Some(PathBuf::from("/tmp/h.jsonl"))
+        );
+        assert_eq!(format_utc(0), "1970-01-01 00:00:00");
+        assert_eq!(format_utc(951_782_400 + 3_661), "2000-02-29 01:01:01");
+        assert_eq!(format_utc(1_735_689_599), "2024-12-31 23:59:59");
+    }
+}
@@ -0,0 +1,107 @@
+//! Filtering and selection of journal records for the history screen.
+
+use crate::history::{Record, RecordKind};
+
+/// Most recent records loaded into the history screen.
+pub const HISTORY_QUERY_LIMIT: usize = 5000;
+
+/// User-controlled state of the history screen, plus the records it last loaded.
+#[derive(Debug, Default)]
+pub struct HistoryView {
+    /// Records as of the latest load, oldest first.
+    pub records: Vec<Record>,
+    /// Only show records of this kind, if set.
+    pub kind: Option<RecordKind>,
+    /// Case-insensitive substring to match against node and summary.
+    pub filter: String,
+    /// Whether keystrokes are currently going into the filter.
+    pub editing_filter: bool,
+    /// Index into the visible (filtered, newest first) records.
+    pub selected: usize,
+    /// Outcome of the latest load.
+    pub status: Option<String>,
+}
+
+impl HistoryView {
+    /// Records that match the kind and filter, newest first.
+    #[must_use]
+    pub fn visible(&self) -> Vec<&Record> {
+        let filter = self.filter.to_lowercase();
+        self.records
+            .iter()
+            .rev()
+            .filter(|record| self.kind.is_none_or(|kind| record.kind == kind))
+            .filter(|record| {
+                filter.is_empty()
+                    || format!("{} {}", record.node, record.summary)
+                        .to_lowercase()
+                        .contains(&filter)
+            })
+            .collect()
+    }
+
+    /// Cycle the kind filter through all kinds, then none.
+    pub fn cycle_kind(&mut self) {
+        self.kind = match self.kind {
+            None => Some(RecordKind::ALL[0]),
+            Some(kind) => RecordKind::ALL
+                .iter()
+                .position(|k| *k == kind)
+                .and_then(|idx| RecordKind::ALL.get(idx + 1))
+                .copied(),
+        };
+        self.selected = 0;
+    }
+
+    /// Keep selection within bounds of the visible records.
+    pub fn clamp_selection(&mut self) {
+        self.selected = std::cmp::min(self.selected, self.visible().len().saturating_sub(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test filtering by kind and text, and cycling kinds.
+    #[test]
+    fn test_visible() {
+        let record = |kind: RecordKind, summary: &str| Record {
+            ts: 0,
+            kind,
+            node: String::from("unix"),
+            summary: summary.to_string(),
+            data: serde_json::Value::Null,
+        };
+        let mut view = HistoryView {
+            records: vec![
+                record(RecordKind::Connection, "curl -> a.com:443"),
+                record(RecordKind::Decision, "allow curl -> a.com:443"),
+                record(RecordKind::Alert, "something broke"),
+            ],
+            ..Default::default()
+        };
+        let summaries = |view: &HistoryView| -> Vec<String> {
+            view.visible()
+                .iter()
+                .map(|record| record.summary.clone())
+                .collect()
+        };
+        assert_eq!(summaries(&view).len(), 3);
+        assert_eq!(summaries(&view)[0], "something broke");
+
+        view.filter = String::from("A.COM");
+        assert_eq!(
+            summaries(&view),
+            vec!["allow curl -> a.com:443", "curl -> a.com:443"]
+        );
+        view.cycle_kind();
+        assert_eq!(view.kind, Some(RecordKind::Connection));
+        assert_eq!(summaries(&view), vec!["curl -> a.com:443"]);
+
+        for _ in 0..RecordKind::ALL.len() {
+            view.cycle_kind();
+        }
+        assert_eq!(view.kind, None);
+    }
+}
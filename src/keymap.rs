@@ -0,0 +1,422 @@
+//! Main screen key bindings, overridable from the `[keybindings]` config table.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Main screen actions that can be bound to a key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KeyAction {
+    Quit,
+    Help,
+    AllowTemp,
+    DenyTemp,
+    AllowForever,
+    DenyForever,
+    EditRule,
+    Inspect,
+    NextNode,
+    Rules,
+    Stats,
+    EventLog,
+    History,
+    Firewall,
+    ToggleInterception,
+    ToggleFirewall,
+    StopDaemon,
+    DaemonConfig,
+    PrevConnection,
+    NextConnection,
+    AlertsUp,
+    AlertsDown,
+}
+
+impl KeyAction {
+    /// All actions, in the order they're listed on the help screen.
+    pub const ALL: [KeyAction; 22] = [
+        KeyAction::Quit,
+        KeyAction::Help,
+        KeyAction::AllowTemp,
+        KeyAction::DenyTemp,
+        KeyAction::AllowForever,
+        KeyAction::DenyForever,
+        KeyAction::AlertsUp,
+        KeyAction::AlertsDown,
+        KeyAction::PrevConnection,
+        KeyAction::NextConnection,
+        KeyAction::EditRule,
+        KeyAction::Inspect,
+        KeyAction::NextNode,
+        KeyAction::Rules,
+        KeyAction::Stats,
+        KeyAction::EventLog,
+        KeyAction::History,
+        KeyAction::Firewall,
+        KeyAction::ToggleInterception,
+        KeyAction::ToggleFirewall,
+        KeyAction::StopDaemon,
+        KeyAction::DaemonConfig,
+    ];
+
+    /// Name of the action in the `[keybindings]` config table.
+    #[must_use]
+    pub fn get_str(&self) -> &str {
+        match self {
+            KeyAction::Quit => "quit",
+            KeyAction::Help => "help",
+            KeyAction::AllowTemp => "allow_temp",
+            KeyAction::DenyTemp => "deny_temp",
+            KeyAction::AllowForever => "allow_forever",
+            KeyAction::DenyForever => "deny_forever",
+            KeyAction::EditRule => "edit_rule",
+            KeyAction::Inspect => "inspect",
+            KeyAction::NextNode => "next_node",
+            KeyAction::Rules => "rules",
+            KeyAction::Stats => "stats",
+            KeyAction::EventLog => "event_log",
+            KeyAction::History => "history",
+            KeyAction::Firewall => "firewall",
+            KeyAction::ToggleInterception => "toggle_interception",
+            KeyAction::ToggleFirewall => "toggle_firewall",
+            KeyAction::StopDaemon => "stop_daemon",
+            KeyAction::DaemonConfig => "daemon_config",
+            KeyAction::PrevConnection => "prev_connection",
+            KeyAction::NextConnection => "next_connection",
+            KeyAction::AlertsUp => "alerts_up",
+            KeyAction::AlertsDown => "alerts_down",
+        }
+    }
+
+    /// Description for the help screen.
+    #[must_use]
+    pub fn description(&self) -> &str {
+        match self {
+            KeyAction::Quit => "Quit",
+            KeyAction::Help => "Display this help screen",
+            KeyAction::AllowTemp => "Allow connection temporarily",
+            KeyAction::DenyTemp => "Deny connection temporarily",
+            KeyAction::AllowForever => "Allow connection forever",
+            KeyAction::DenyForever => "Deny connection forever",
+            KeyAction::EditRule => "Edit rule for selected connection",
+            KeyAction::Inspect => "Inspect selected connection's process",
+            KeyAction::NextNode => "Select next node",
+            KeyAction::Rules => "Browse rules of selected node",
+            KeyAction::Stats => "Statistics breakdown of selected node",
+            KeyAction::EventLog => "Log of connection events from all nodes",
+            KeyAction::History => "Query the history journal",
+            KeyAction::Firewall => "System firewall of selected node",
+            KeyAction::ToggleInterception => "Pause or resume interception on selected node",
+            KeyAction::ToggleFirewall => "Disable or enable firewall of selected node",
+            KeyAction::StopDaemon => "Stop daemon of selected node",
+            KeyAction::DaemonConfig => "Configuration of selected node's daemon",
+            KeyAction::PrevConnection => "Select previous pending connection",
+            KeyAction::NextConnection => "Select next pending connection",
+            KeyAction::AlertsUp => "Scroll alert list up",
+            KeyAction::AlertsDown => "Scroll alert list down",
+        }
+    }
+}
+
+impl FromStr for KeyAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<KeyAction, Self::Err> {
+        KeyAction::ALL
+            .into_iter()
+            .find(|action| action.get_str() == s)
+            .ok_or_else(|| String::from("unknown action"))
+    }
+}
+
+/// A key and its modifiers. Letters are stored lowercase and match either case.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    #[must_use]
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let code = match code {
+            KeyCode::Char(c) => KeyCode::Char(c.to_ascii_lowercase()),
+            code => code,
+        };
+        KeyChord { code, modifiers }
+    }
+
+    /// Chord of a key press. Shift is dropped for characters, since it's already
+    /// reflected in the character.
+    #[must_use]
+    pub fn from_event(event: KeyEvent) -> Self {
+        let modifiers = match event.code {
+            KeyCode::Char(_) => event.modifiers - KeyModifiers::SHIFT,
+            _ => event.modifiers,
+        };
+        KeyChord::new(event.code, modifiers)
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = String;
+
+    /// Parse chords like `a`, `ctrl+c`, `alt+left` or `f5`, case-insensitively.
+    fn from_str(s: &str) -> Result<KeyChord, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').collect();
+        // A trailing empty part means the key itself is '+', e.g. "ctrl++".
+        let key = match parts.pop() {
+            Some("") if parts.last() == Some(&"") => {
+                parts.pop();
+                "+"
+            }
+            Some(key) => key,
+            None => "",
+        };
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in parts {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier \"{modifier}\" in \"{s}\"")),
+            };
+        }
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_lowercase().as_str() {
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" | "pgup" => KeyCode::PageUp,
+                "pagedown" | "pgdn" => KeyCode::PageDown,
+                "enter" => KeyCode::Enter,
+                "tab" => KeyCode::Tab,
+                "space" => KeyCode::Char(' '),
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "esc" => KeyCode::Esc,
+                name => name
+                    .strip_prefix('f')
+                    .and_then(|n| n.parse::<u8>().ok())
+                    .filter(|n| (1..=12).contains(n))
+                    .map(KeyCode::F)
+                    .ok_or_else(|| format!("unknown key \"{key}\" in \"{s}\""))?,
+            },
+        };
+        if matches!(code, KeyCode::Char(_)) && modifiers.contains(KeyModifiers::SHIFT) {
+            return Err(format!(
+                "shift can't be combined with a character in \"{s}\""
+            ));
+        }
+        Ok(KeyChord::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "Ctrl+"),
+            (KeyModifiers::ALT, "Alt+"),
+            (KeyModifiers::SHIFT, "Shift+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::PageUp => f.write_str("PgUp"),
+            KeyCode::PageDown => f.write_str("PgDn"),
+            KeyCode::Esc => f.write_str("ESC"),
+            code => write!(f, "{code}"),
+        }
+    }
+}
+
+/// Bindings of main screen actions to keys, one key per action.
+#[derive(Debug)]
+pub struct Keymap {
+    keys: HashMap<KeyAction, KeyChord>,
+    actions: HashMap<KeyChord, KeyAction>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = KeyAction::ALL.map(|action| {
+            let (code, modifiers) = match action {
+                KeyAction::Quit => (KeyCode::Char('c'), KeyModifiers::CONTROL),
+                KeyAction::Help => (KeyCode::Char('h'), KeyModifiers::NONE),
+                KeyAction::AllowTemp => (KeyCode::Char('a'), KeyModifiers::NONE),
+                KeyAction::DenyTemp => (KeyCode::Char('d'), KeyModifiers::NONE),
+                KeyAction::AllowForever => (KeyCode::Char('j'), KeyModifiers::NONE),
+                KeyAction::DenyForever => (KeyCode::Char('l'), KeyModifiers::NONE),
+                KeyAction::EditRule => (KeyCode::Char('e'), KeyModifiers::NONE),
+                KeyAction::Inspect => (KeyCode::Char('i'), KeyModifiers::NONE),
+                KeyAction::NextNode => (KeyCode::Char('n'), KeyModifiers::NONE),
+                KeyAction::Rules => (KeyCode::Char('r'), KeyModifiers::NONE),
+                KeyAction::Stats => (KeyCode::Char('s'), KeyModifiers::NONE),
+                KeyAction::EventLog => (KeyCode::Char('v'), KeyModifiers::NONE),
+                KeyAction::History => (KeyCode::Char('o'), KeyModifiers::NONE),
+                KeyAction::Firewall => (KeyCode::Char('f'), KeyModifiers::NONE),
+                KeyAction::ToggleInterception => (KeyCode::Char('p'), KeyModifiers::NONE),
+                KeyAction::ToggleFirewall => (KeyCode::Char('w'), KeyModifiers::NONE),
+                KeyAction::StopDaemon => (KeyCode::Char('x'), KeyModifiers::CONTROL),
+                KeyAction::DaemonConfig => (KeyCode::Char('g'), KeyModifiers::NONE),
+                KeyAction::PrevConnection => (KeyCode::Left, KeyModifiers::NONE),
+                KeyAction::NextConnection => (KeyCode::Right, KeyModifiers::NONE),
+                KeyAction::AlertsUp => (KeyCode::Up, KeyModifiers::NONE),
+                KeyAction::AlertsDown => (KeyCode::Down, KeyModifiers::NONE),
+            };
+            (action, KeyChord::new(code, modifiers))
+        });
+        Keymap {
+            keys: bindings.into_iter().collect(),
+            actions: bindings
+                .into_iter()
+                .map(|(action, chord)| (chord, action))
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    /// Default bindings with `overrides` of action name to key applied.
+    /// # Errors
+    /// With one line per unknown action, unparsable key, or key bound to several actions.
+    pub fn new(overrides: &BTreeMap<String, String>) -> Result<Keymap, String> {
+        let mut keys = Keymap::default().keys;
+        let mut errors = Vec::new();
+        for (name, key) in overrides {
+            match (KeyAction::from_str(name), KeyChord::from_str(key)) {
+                (Ok(action), Ok(chord)) => {
+                    keys.insert(action, chord);
+                }
+                (Err(err), _) | (_, Err(err)) => errors.push(format!("{name}: {err}")),
+            }
+        }
+
+        let mut actions: HashMap<KeyChord, KeyAction> = HashMap::new();
+        for action in KeyAction::ALL {
+            let chord = keys[&action];
+            if let Some(other) = actions.insert(chord, action) {
+                errors.push(format!(
+                    "{}: {chord} is also bound to {}",
+                    action.get_str(),
+                    other.get_str()
+                ));
+            }
+        }
+        if errors.is_empty() {
+            Ok(Keymap { keys, actions })
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    /// Key bound to `action`.
+    #[must_use]
+    pub fn key(&self, action: KeyAction) -> KeyChord {
+        self.keys[&action]
+    }
+
+    /// Action bound to the pressed key, if any.
+    #[must_use]
+    pub fn action(&self, event: KeyEvent) -> Option<KeyAction> {
+        self.actions.get(&KeyChord::from_event(event)).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    /// Test default bindings match either case, and keys display like the help screen.
+    #[test]
+    fn test_default() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            keymap.action(press(KeyCode::Char('a'), KeyModifiers::NONE)),
+            Some(KeyAction::AllowTemp)
+        );
+        assert_eq!(
+            keymap.action(press(KeyCode::Char('A'), KeyModifiers::SHIFT)),
+            Some(KeyAction::AllowTemp)
+        );
+        assert_eq!(
+            keymap.action(press(KeyCode::Char('C'), KeyModifiers::CONTROL)),
+            Some(KeyAction::Quit)
+        );
+        assert_eq!(
+            keymap.action(press(KeyCode::Char('c'), KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(keymap.key(KeyAction::Quit).to_string(), "Ctrl+C");
+        assert_eq!(keymap.key(KeyAction::PrevConnection).to_string(), "Left");
+    }
+
+    /// Test parsing of key chords.
+    #[test]
+    fn test_parse_chord() {
+        let chord = |s: &str| KeyChord::from_str(s).map(|chord| chord.to_string());
+        assert_eq!(chord("Y"), Ok(String::from("Y")));
+        assert_eq!(chord("ctrl+q"), Ok(String::from("Ctrl+Q")));
+        assert_eq!(chord("Alt+PageDown"), Ok(String::from("Alt+PgDn")));
+        assert_eq!(chord("shift+f5"), Ok(String::from("Shift+F5")));
+        assert_eq!(chord("space"), Ok(String::from("Space")));
+        assert_eq!(chord("ctrl++"), Ok(String::from("Ctrl++")));
+        assert!(chord("hyper+a").is_err());
+        assert!(chord("f13").is_err());
+        assert!(chord("shift+a").is_err());
+        assert!(chord("").is_err());
+    }
+
+    /// Test overrides apply, and every bad or conflicting binding is reported.
+    #[test]
+    fn test_overrides() {
+        let overrides = |pairs: &[(&str, &str)]| -> BTreeMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+                .collect()
+        };
+        let keymap =
+            Keymap::new(&overrides(&[("quit", "q"), ("allow_temp", "y")])).expect("new failed");
+        assert_eq!(
+            keymap.action(press(KeyCode::Char('q'), KeyModifiers::NONE)),
+            Some(KeyAction::Quit)
+        );
+        assert_eq!(
+            keymap.action(press(KeyCode::Char('a'), KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(keymap.key(KeyAction::AllowTemp).to_string(), "Y");
+
+        let err = Keymap::new(&overrides(&[
+            ("stats", "a"),
+            ("bogus", "b"),
+            ("rules", "meta+r"),
+        ]))
+        .expect_err("new succeeded");
+        let lines: Vec<&str> = err.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "bogus: unknown action",
+                "rules: unknown modifier \"meta\" in \"meta+r\"",
+                "stats: A is also bound to allow_temp",
+            ]
+        );
+    }
+}
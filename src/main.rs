@@ -2,15 +2,34 @@
 
 pub mod alert;
 pub mod app;
+pub mod audit;
 pub mod cli;
+pub mod clock;
+pub mod config;
+pub mod config_editor;
 pub mod constants;
 pub mod event;
+pub mod event_log;
+pub mod firewall_view;
+pub mod history;
+pub mod history_view;
+pub mod keymap;
+pub mod node;
+pub mod notification;
 pub mod opensnitch_json;
 pub mod opensnitch_proto;
 pub mod operator_util;
+pub mod passwd;
+pub mod policy;
+pub mod rule_editor;
+pub mod rules_view;
 pub mod serde_impl;
 pub mod server;
+pub mod stats_history;
+pub mod stats_view;
+pub mod theme;
 pub mod ui;
+pub mod unix_socket;
 
 use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
 use crossterm::execute;
@@ -22,16 +41,69 @@ use crossterm::execute;
 async fn main() -> color_eyre::Result<()> {
     let matches = cli::setup().get_matches();
 
+    if let Some(path) = matches.get_one::<String>("verify_audit_log") {
+        let count = audit::verify_chain(std::path::Path::new(path))
+            .map_err(|err| color_eyre::eyre::eyre!("Audit log verification failed: {err}"))?;
+        println!("Audit log OK, {count} entries verified.");
+        return Ok(());
+    }
+
     color_eyre::install()?;
-    let terminal = ratatui::init();
-    let app = app::App::new(
-        matches.get_one::<String>("ip_port").unwrap(),
-        matches.get_one::<String>("default_action").unwrap(),
-        matches.get_one::<String>("temp_rule_lifetime").unwrap(),
-        matches.get_one::<u64>("dispo_seconds").unwrap(),
-        matches.get_one::<String>("rule_presets").unwrap(),
+    let config = config::Config::load(
+        matches
+            .get_one::<String>("config")
+            .map(std::path::Path::new),
+    )
+    .map_err(|err| color_eyre::eyre::eyre!("Invalid config:\n{err}"))?;
+    let settings =
+        config::Settings::resolve(&matches, config).map_err(|err| color_eyre::eyre::eyre!(err))?;
+    let mut app = app::App::new(
+        &settings.bind,
+        &settings.default_action,
+        &settings.temp_rule_lifetime,
+        &settings.conn_dispo_timeout,
+        &settings.rule_presets,
+        settings.socket_mode.as_deref(),
+        settings.socket_owner.as_deref(),
     )
     .expect("Initialization failed: ");
+    app.set_keymap(
+        keymap::Keymap::new(&settings.keybindings)
+            .map_err(|err| color_eyre::eyre::eyre!("Invalid keybindings:\n{err}"))?,
+    );
+    app.set_theme(
+        theme::Theme::new(settings.theme.as_deref(), &settings.colors)
+            .map_err(|err| color_eyre::eyre::eyre!("Invalid colors:\n{err}"))?,
+    );
+    app.set_alert_retention(std::time::Duration::from_secs(settings.alert_retention));
+    if let Some(path) = history::resolve_path(settings.history_db.as_deref()) {
+        app.open_history(&path).expect("Initialization failed: ");
+    }
+    if let Some(path) = &settings.audit_log {
+        app.open_audit_log(std::path::Path::new(path), settings.audit_chain)
+            .expect("Initialization failed: ");
+    }
+    if let Some(path) = &settings.policy {
+        let policy = policy::Policy::load(std::path::Path::new(path))
+            .map_err(|err| color_eyre::eyre::eyre!(err))?;
+        if matches.get_flag("headless") {
+            println!(
+                "Loaded {} policy rules from {path}, listening on {}",
+                policy.rules.len(),
+                settings.bind
+            );
+        }
+        app.set_policy(policy);
+    } else if matches.get_flag("headless") {
+        return Err(color_eyre::eyre::eyre!(
+            "--headless requires a policy, set with --policy or in the config file"
+        ));
+    }
+    if matches.get_flag("headless") {
+        app.enable_headless();
+        return app.run_headless().await;
+    }
+    let terminal = ratatui::init();
     execute!(std::io::stdout(), EnableMouseCapture)?;
     let result = app.run(terminal).await;
     let _ = execute!(std::io::stdout(), DisableMouseCapture);
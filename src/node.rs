@@ -0,0 +1,284 @@
+//! Registry of `OpenSnitch` daemons (nodes) connected to this server.
+
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime};
+
+use tokio::sync::mpsc;
+use tonic::Status;
+
+use crate::opensnitch_proto::pb;
+use crate::stats_history::StatsHistory;
+
+/// Channel sender for notifications towards a single daemon.
+pub type NotificationSender = mpsc::Sender<Result<pb::Notification, Status>>;
+
+/// Interval at which daemons ping the server.
+pub const PING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Consecutive ping intervals a node may miss before it's considered disconnected.
+pub const MISSED_PINGS_THRESHOLD: u32 = 5;
+
+/// Key identifying a node across its RPCs and reconnections: the host the daemon connects
+/// from, e.g. `10.0.0.1` or `unix:/tmp/osui.sock`, after the name the daemon reported on
+/// subscribing. Source ports are left out, since they change whenever the daemon reconnects.
+/// Daemons behind the same NAT, or on the same domain socket, are told apart by name.
+#[must_use]
+pub fn node_key(host: &str, name: &str) -> String {
+    if name.is_empty() {
+        host.to_string()
+    } else {
+        format!("{name}@{host}")
+    }
+}
+
+/// State tracked per connected daemon.
+#[derive(Debug, Default)]
+pub struct Node {
+    /// Peer address of the daemon's latest RPC, if not connected over a domain socket.
+    pub peer: Option<SocketAddr>,
+    /// Configuration sent by the daemon on `Subscribe`.
+    pub config: Option<pb::ClientConfig>,
+    /// Latest stats from the daemon's pings.
+    pub stats: Option<pb::Statistics>,
+    /// Stats from the ping before the latest, to compute deltas against.
+    pub prev_stats: Option<pb::Statistics>,
+    /// Counters of recent pings, for charting rates.
+    pub history: StatsHistory,
+    /// Rx Pings from daemon.
+    pub rx_pings: u64,
+    /// Sender for the daemon's notifications stream, if open.
+    pub notification_sender: Option<NotificationSender>,
+    /// ID of the notifications stream `notification_sender` belongs to.
+    pub notification_stream: u64,
+    /// When the latest ping was received.
+    pub last_ping: Option<SystemTime>,
+    /// Whether the daemon stopped pinging or closed its notifications stream.
+    pub disconnected: bool,
+    /// Whether interception was paused from the TUI. Daemons don't report it, so changes
+    /// made elsewhere, or by restarting the daemon, go unnoticed.
+    pub interception_paused: bool,
+}
+
+impl Node {
+    /// Human-friendly name, preferring the daemon-reported name over its key.
+    #[must_use]
+    pub fn display_name(&self, key: &str) -> String {
+        match &self.config {
+            Some(config) if !config.name.is_empty() && config.name != key => {
+                let peer = key
+                    .strip_prefix(config.name.as_str())
+                    .and_then(|peer| peer.strip_prefix('@'))
+                    .unwrap_or(key);
+                format!("{} ({peer})", config.name)
+            }
+            _ => key.to_string(),
+        }
+    }
+
+    /// Whether the node went `MISSED_PINGS_THRESHOLD` ping intervals without pinging.
+    #[must_use]
+    pub fn missed_pings(&self, now: SystemTime) -> bool {
+        self.last_ping
+            .and_then(|last_ping| now.duration_since(last_ping).ok())
+            .is_some_and(|since| since >= PING_INTERVAL * MISSED_PINGS_THRESHOLD)
+    }
+}
+
+/// Nodes keyed by `node_key`, plus which one the user has selected.
+#[derive(Debug, Default)]
+pub struct NodeRegistry {
+    nodes: BTreeMap<String, Node>,
+    selected: Option<String>,
+}
+
+impl NodeRegistry {
+    /// Get a node, registering it on first sight. The first node registered becomes selected.
+    pub fn get_or_insert(&mut self, key: &str) -> &mut Node {
+        if self.selected.is_none() {
+            self.selected = Some(key.to_string());
+        }
+        self.nodes.entry(key.to_string()).or_default()
+    }
+
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&Node> {
+        self.nodes.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Node> {
+        self.nodes.get_mut(key)
+    }
+
+    /// Move a node to a new key, keeping it selected. A node already there is the same
+    /// daemon from before it reconnected, and takes over the moved node's pings.
+    pub fn rename(&mut self, from: &str, to: &str) {
+        let Some(node) = self.nodes.remove(from) else {
+            return;
+        };
+        match self.nodes.entry(to.to_string()) {
+            Entry::Vacant(entry) => {
+                entry.insert(node);
+            }
+            Entry::Occupied(mut entry) => {
+                let existing = entry.get_mut();
+                existing.rx_pings += node.rx_pings;
+                if node.last_ping > existing.last_ping {
+                    existing.peer = node.peer;
+                    existing.prev_stats = node.prev_stats;
+                    existing.stats = node.stats;
+                    existing.last_ping = node.last_ping;
+                    existing.disconnected = node.disconnected;
+                }
+            }
+        }
+        if self.selected.as_deref() == Some(from) {
+            self.selected = Some(to.to_string());
+        }
+    }
+
+    /// Key of the selected node.
+    #[must_use]
+    pub fn selected_key(&self) -> Option<&str> {
+        self.selected.as_deref()
+    }
+
+    /// The selected node and its key.
+    #[must_use]
+    pub fn selected(&self) -> Option<(&str, &Node)> {
+        let key = self.selected.as_deref()?;
+        self.nodes.get(key).map(|node| (key, node))
+    }
+
+    /// Rules the selected node reported on subscribing.
+    #[must_use]
+    pub fn selected_rules(&self) -> &[pb::Rule] {
+        self.selected()
+            .and_then(|(_, node)| node.config.as_ref())
+            .map_or(&[], |config| config.rules.as_slice())
+    }
+
+    /// System firewall the selected node reported, as updated by confirmed reloads.
+    #[must_use]
+    pub fn selected_firewall(&self) -> Option<&pb::SysFirewall> {
+        self.selected()
+            .and_then(|(_, node)| node.config.as_ref())
+            .and_then(|config| config.system_firewall.as_ref())
+    }
+
+    /// Select the node after the current one, wrapping around.
+    pub fn select_next(&mut self) {
+        let next = match &self.selected {
+            Some(current) => self
+                .nodes
+                .range::<String, _>((
+                    std::ops::Bound::Excluded(current),
+                    std::ops::Bound::Unbounded,
+                ))
+                .next()
+                .or_else(|| self.nodes.iter().next()),
+            None => self.nodes.iter().next(),
+        };
+        self.selected = next.map(|(key, _)| key.clone());
+    }
+
+    /// Iterate over nodes in key order.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Node)> {
+        self.nodes.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&String, &mut Node)> {
+        self.nodes.iter_mut()
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test daemons behind the same host get their own node by name, and keep it across
+    /// reconnections.
+    #[test]
+    fn test_node_key() {
+        let a = node_key("10.0.0.1", "web-1");
+        let b = node_key("10.0.0.1", "web-2");
+        let c = node_key("unix:/tmp/osui.sock", "web-1");
+        assert_eq!(a, "web-1@10.0.0.1");
+        assert_eq!(node_key("unix:/tmp/osui.sock", ""), "unix:/tmp/osui.sock");
+
+        let mut registry = NodeRegistry::default();
+        for key in [&a, &b, &c, &a] {
+            registry.get_or_insert(key).rx_pings += 1;
+        }
+        assert_eq!(registry.len(), 3);
+        assert_eq!(registry.get(&a).map(|node| node.rx_pings), Some(2));
+
+        // Pings that beat the subscription follow the node to its named key.
+        let now = SystemTime::now();
+        let node = registry.get_or_insert("10.0.0.2");
+        node.rx_pings = 3;
+        node.last_ping = Some(now);
+        registry.rename("10.0.0.2", "web-3@10.0.0.2");
+        assert!(registry.get("10.0.0.2").is_none());
+        assert_eq!(
+            registry.get("web-3@10.0.0.2").map(|node| node.rx_pings),
+            Some(3)
+        );
+
+        // Or into the node the daemon had before reconnecting.
+        let node = registry.get_or_insert("10.0.0.2");
+        node.rx_pings = 1;
+        node.last_ping = Some(now + PING_INTERVAL);
+        node.disconnected = false;
+        registry.get_or_insert("web-3@10.0.0.2").disconnected = true;
+        registry.rename("10.0.0.2", "web-3@10.0.0.2");
+        assert_eq!(registry.len(), 4);
+        let node = registry.get("web-3@10.0.0.2").expect("no node");
+        assert_eq!(node.rx_pings, 4);
+        assert_eq!(node.last_ping, Some(now + PING_INTERVAL));
+        assert!(!node.disconnected);
+    }
+
+    /// Test node selection and cycling.
+    #[test]
+    fn test_registry_selection() {
+        let mut registry = NodeRegistry::default();
+        assert!(registry.selected().is_none());
+
+        registry.get_or_insert("10.0.0.2");
+        registry.get_or_insert("10.0.0.1");
+        registry.get_or_insert("10.0.0.3");
+        assert_eq!(registry.len(), 3);
+        // First node seen stays selected.
+        assert_eq!(registry.selected_key(), Some("10.0.0.2"));
+
+        registry.select_next();
+        assert_eq!(registry.selected_key(), Some("10.0.0.3"));
+        registry.select_next();
+        assert_eq!(registry.selected_key(), Some("10.0.0.1"));
+    }
+
+    /// Test display names.
+    #[test]
+    fn test_display_name() {
+        let mut node = Node::default();
+        assert_eq!(node.display_name("10.0.0.1"), "10.0.0.1");
+        node.config = Some(pb::ClientConfig {
+            name: String::from("web-1"),
+            ..Default::default()
+        });
+        assert_eq!(node.display_name("10.0.0.1"), "web-1 (10.0.0.1)");
+        assert_eq!(node.display_name("web-1@10.0.0.1"), "web-1 (10.0.0.1)");
+    }
+}
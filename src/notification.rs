@@ -0,0 +1,520 @@
+//! Rule, firewall and daemon management over the notifications stream, tracking each
+//! notification until the daemon replies.
+
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use crate::node::{Node, NotificationSender};
+use crate::opensnitch_proto::pb;
+
+/// How long to wait for a daemon to reply before giving up on a notification.
+pub const REPLY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Operations on a daemon's rules.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RuleOperation {
+    Enable,
+    Disable,
+    Delete,
+    Change,
+}
+
+impl RuleOperation {
+    /// Notification action carrying this operation.
+    #[must_use]
+    pub fn action(self) -> pb::Action {
+        match self {
+            RuleOperation::Enable => pb::Action::EnableRule,
+            RuleOperation::Disable => pb::Action::DisableRule,
+            RuleOperation::Delete => pb::Action::DeleteRule,
+            RuleOperation::Change => pb::Action::ChangeRule,
+        }
+    }
+
+    #[must_use]
+    pub fn get_str(&self) -> &str {
+        match self {
+            RuleOperation::Enable => "enable",
+            RuleOperation::Disable => "disable",
+            RuleOperation::Delete => "delete",
+            RuleOperation::Change => "change",
+        }
+    }
+}
+
+/// Commands switching a daemon's interception or system firewall, or stopping it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DaemonCommand {
+    EnableInterception,
+    DisableInterception,
+    EnableFirewall,
+    DisableFirewall,
+    Stop,
+}
+
+impl DaemonCommand {
+    /// Notification action carrying this command.
+    #[must_use]
+    pub fn action(self) -> pb::Action {
+        match self {
+            DaemonCommand::EnableInterception => pb::Action::EnableInterception,
+            DaemonCommand::DisableInterception => pb::Action::DisableInterception,
+            DaemonCommand::EnableFirewall => pb::Action::EnableFirewall,
+            DaemonCommand::DisableFirewall => pb::Action::DisableFirewall,
+            DaemonCommand::Stop => pb::Action::Stop,
+        }
+    }
+
+    #[must_use]
+    pub fn get_str(&self) -> &str {
+        match self {
+            DaemonCommand::EnableInterception => "enable_interception",
+            DaemonCommand::DisableInterception => "disable_interception",
+            DaemonCommand::EnableFirewall => "enable_firewall",
+            DaemonCommand::DisableFirewall => "disable_firewall",
+            DaemonCommand::Stop => "stop",
+        }
+    }
+
+    /// What the command does, e.g. `pause interception`.
+    #[must_use]
+    pub fn describe(&self) -> &str {
+        match self {
+            DaemonCommand::EnableInterception => "resume interception",
+            DaemonCommand::DisableInterception => "pause interception",
+            DaemonCommand::EnableFirewall => "enable firewall",
+            DaemonCommand::DisableFirewall => "disable firewall",
+            DaemonCommand::Stop => "stop daemon",
+        }
+    }
+}
+
+/// What a notification asks of a daemon.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NotificationRequest {
+    /// An operation on one of the daemon's rules, carrying the rule as it should end up.
+    Rule(RuleOperation, pb::Rule),
+    /// Apply and save a system firewall configuration.
+    ReloadFirewall {
+        firewall: pb::SysFirewall,
+        /// Configuration to restore if the daemon fails to apply `firewall`, when it
+        /// changes the one the daemon had.
+        rollback: Option<pb::SysFirewall>,
+    },
+    /// Switch interception or the system firewall, or stop the daemon.
+    Command(DaemonCommand),
+    /// Replace the daemon's configuration with the given JSON.
+    ChangeConfig(String),
+}
+
+impl NotificationRequest {
+    /// Notification action carrying this request.
+    #[must_use]
+    pub fn action(&self) -> pb::Action {
+        match self {
+            NotificationRequest::Rule(operation, _) => operation.action(),
+            NotificationRequest::ReloadFirewall { .. } => pb::Action::ReloadFwRules,
+            NotificationRequest::Command(command) => command.action(),
+            NotificationRequest::ChangeConfig(_) => pb::Action::ChangeConfig,
+        }
+    }
+
+    /// Name of the operation for the audit log, e.g. `delete`.
+    #[must_use]
+    pub fn get_str(&self) -> &str {
+        match self {
+            NotificationRequest::Rule(operation, _) => operation.get_str(),
+            NotificationRequest::ReloadFirewall { rollback: None, .. } => "reload_firewall",
+            NotificationRequest::ReloadFirewall { .. } => "change_firewall",
+            NotificationRequest::Command(command) => command.get_str(),
+            NotificationRequest::ChangeConfig(_) => "change_config",
+        }
+    }
+
+    /// The rule sent along, for rule operations.
+    #[must_use]
+    pub fn rule(&self) -> Option<&pb::Rule> {
+        match self {
+            NotificationRequest::Rule(_, rule) => Some(rule),
+            NotificationRequest::ReloadFirewall { .. }
+            | NotificationRequest::Command(_)
+            | NotificationRequest::ChangeConfig(_) => None,
+        }
+    }
+
+    fn notification(&self, id: u64) -> pb::Notification {
+        let mut notification = pb::Notification {
+            id,
+            client_name: String::default(),
+            server_name: String::default(),
+            r#type: self.action().into(),
+            data: String::default(),
+            rules: Vec::default(),
+            sys_firewall: None,
+        };
+        match self {
+            NotificationRequest::Rule(_, rule) => notification.rules.push(rule.clone()),
+            NotificationRequest::ReloadFirewall { firewall, .. } => {
+                notification.sys_firewall = Some(firewall.clone());
+            }
+            NotificationRequest::Command(_) => {}
+            NotificationRequest::ChangeConfig(json) => notification.data.clone_from(json),
+        }
+        notification
+    }
+}
+
+/// A notification sent to a daemon that hasn't been replied to yet.
+#[derive(Clone, Debug)]
+pub struct PendingNotification {
+    /// Key of the node the notification was sent to.
+    pub node: String,
+    pub request: NotificationRequest,
+    pub sent_ts: SystemTime,
+}
+
+impl PendingNotification {
+    /// Reflect the request in our copy of the node's state, once the daemon confirmed it.
+    pub fn apply(&self, node: &mut Node) {
+        if let NotificationRequest::Command(command) = self.request {
+            match command {
+                DaemonCommand::EnableInterception => node.interception_paused = false,
+                DaemonCommand::DisableInterception => node.interception_paused = true,
+                DaemonCommand::EnableFirewall | DaemonCommand::DisableFirewall => {
+                    if let Some(config) = node.config.as_mut() {
+                        config.is_firewall_running = command == DaemonCommand::EnableFirewall;
+                    }
+                }
+                DaemonCommand::Stop => {}
+            }
+            return;
+        }
+        let Some(config) = node.config.as_mut() else {
+            return;
+        };
+        match &self.request {
+            NotificationRequest::Rule(RuleOperation::Delete, sent) => {
+                config.rules.retain(|rule| rule.name != sent.name);
+            }
+            NotificationRequest::Rule(_, sent) => {
+                match config.rules.iter_mut().find(|rule| rule.name == sent.name) {
+                    Some(rule) => *rule = sent.clone(),
+                    None => config.rules.push(sent.clone()),
+                }
+            }
+            NotificationRequest::ReloadFirewall { firewall, .. } => {
+                config.system_firewall = Some(firewall.clone());
+            }
+            NotificationRequest::ChangeConfig(json) => config.config.clone_from(json),
+            NotificationRequest::Command(_) => {}
+        }
+    }
+
+    /// Short description for alerts and the status line, e.g. `disable rule allow-curl on unix`.
+    #[must_use]
+    pub fn describe(&self) -> String {
+        match &self.request {
+            NotificationRequest::Rule(operation, rule) => {
+                format!(
+                    "{} rule {} on {}",
+                    operation.get_str(),
+                    rule.name,
+                    self.node
+                )
+            }
+            NotificationRequest::ReloadFirewall { rollback: None, .. } => {
+                format!("reload firewall on {}", self.node)
+            }
+            NotificationRequest::ReloadFirewall { .. } => {
+                format!("change firewall on {}", self.node)
+            }
+            NotificationRequest::Command(command) => {
+                format!("{} on {}", command.describe(), self.node)
+            }
+            NotificationRequest::ChangeConfig(_) => format!("change config on {}", self.node),
+        }
+    }
+}
+
+/// Assigns notification IDs and matches daemon replies to them.
+#[derive(Debug, Default)]
+pub struct NotificationTracker {
+    /// Last assigned ID. Notifications start from 1.
+    last_id: u64,
+    pending: HashMap<u64, PendingNotification>,
+}
+
+impl NotificationTracker {
+    /// Send a request to a node and start waiting for its reply.
+    /// # Errors
+    /// Returns an error if the node's notifications stream is closed or backed up.
+    pub fn send(
+        &mut self,
+        node: &str,
+        sender: &NotificationSender,
+        request: NotificationRequest,
+        now: SystemTime,
+    ) -> Result<u64, String> {
+        self.last_id = self.last_id.wrapping_add(1);
+        let id = self.last_id;
+        sender
+            .try_send(Ok(request.notification(id)))
+            .map_err(|err| format!("Unable to notify {node}: {err}"))?;
+        self.pending.insert(
+            id,
+            PendingNotification {
+                node: node.to_string(),
+                request,
+                sent_ts: now,
+            },
+        );
+        Ok(id)
+    }
+
+    /// Stop tracking a notification, returning it if it was still pending.
+    pub fn resolve(&mut self, id: u64) -> Option<PendingNotification> {
+        self.pending.remove(&id)
+    }
+
+    /// Stop tracking the stop commands sent to a node, returning them. Daemons exit without
+    /// replying to them, so their notifications stream closing is the confirmation.
+    pub fn resolve_stops(&mut self, node: &str) -> Vec<PendingNotification> {
+        let stop_ids: Vec<u64> = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| {
+                pending.node == node
+                    && matches!(
+                        pending.request,
+                        NotificationRequest::Command(DaemonCommand::Stop)
+                    )
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        stop_ids
+            .iter()
+            .filter_map(|id| self.pending.remove(id))
+            .collect()
+    }
+
+    /// Whether an operation on the named rule of a node is awaiting a reply.
+    #[must_use]
+    pub fn is_pending(&self, node: &str, rule_name: &str) -> bool {
+        self.pending.values().any(|pending| {
+            pending.node == node
+                && pending
+                    .request
+                    .rule()
+                    .is_some_and(|rule| rule.name == rule_name)
+        })
+    }
+
+    /// Whether a firewall reload or change sent to a node is awaiting a reply.
+    #[must_use]
+    pub fn is_firewall_pending(&self, node: &str) -> bool {
+        self.pending.values().any(|pending| {
+            pending.node == node
+                && matches!(pending.request, NotificationRequest::ReloadFirewall { .. })
+        })
+    }
+
+    /// Whether a config change sent to a node is awaiting a reply.
+    #[must_use]
+    pub fn is_config_pending(&self, node: &str) -> bool {
+        self.pending.values().any(|pending| {
+            pending.node == node && matches!(pending.request, NotificationRequest::ChangeConfig(_))
+        })
+    }
+
+    /// Drop and return notifications that went unanswered for longer than `REPLY_TIMEOUT`.
+    pub fn expire(&mut self, now: SystemTime) -> Vec<PendingNotification> {
+        let expired_ids: Vec<u64> = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| {
+                now.duration_since(pending.sent_ts)
+                    .is_ok_and(|age| age >= REPLY_TIMEOUT)
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        expired_ids
+            .iter()
+            .filter_map(|id| self.pending.remove(id))
+            .collect()
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    fn make_rule(name: &str, enabled: bool) -> pb::Rule {
+        pb::Rule {
+            name: name.to_string(),
+            enabled,
+            ..Default::default()
+        }
+    }
+
+    /// Test notifications are built with unique IDs and resolved by reply ID.
+    #[test]
+    fn test_send_and_resolve() {
+        let (sender, mut receiver) = mpsc::channel(8);
+        let mut tracker = NotificationTracker::default();
+        let now = SystemTime::now();
+
+        let first = tracker
+            .send(
+                "unix",
+                &sender,
+                NotificationRequest::Rule(RuleOperation::Disable, make_rule("a", false)),
+                now,
+            )
+            .expect("send failed");
+        let second = tracker
+            .send(
+                "unix",
+                &sender,
+                NotificationRequest::Rule(RuleOperation::Delete, make_rule("b", true)),
+                now,
+            )
+            .expect("send failed");
+        assert_ne!(first, second);
+        assert_eq!(tracker.len(), 2);
+        assert!(tracker.is_pending("unix", "a"));
+        assert!(!tracker.is_pending("10.0.0.1", "a"));
+
+        let notification = receiver
+            .try_recv()
+            .expect("missing notification")
+            .expect("error notification");
+        assert_eq!(notification.id, first);
+        assert_eq!(notification.r#type(), pb::Action::DisableRule);
+        assert_eq!(notification.rules, vec![make_rule("a", false)]);
+
+        let pending = tracker.resolve(first).expect("not pending");
+        assert_eq!(pending.describe(), "disable rule a on unix");
+        assert!(tracker.resolve(first).is_none());
+
+        // Unanswered notifications expire.
+        assert!(tracker.expire(now).is_empty());
+        let expired = tracker.expire(now + REPLY_TIMEOUT);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].request.get_str(), "delete");
+        assert!(tracker.is_empty());
+
+        // Closed stream is reported and nothing is left pending.
+        drop(receiver);
+        assert!(
+            tracker
+                .send(
+                    "unix",
+                    &sender,
+                    NotificationRequest::Rule(RuleOperation::Enable, make_rule("a", true)),
+                    now,
+                )
+                .is_err()
+        );
+        assert!(tracker.is_empty());
+    }
+
+    /// Test confirmed requests update the local copy of the node's state.
+    #[test]
+    fn test_apply() {
+        let mut node = Node {
+            config: Some(pb::ClientConfig {
+                rules: vec![make_rule("a", true), make_rule("b", true)],
+                is_firewall_running: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let config = |node: &Node| node.config.clone().expect("no config");
+        let pending = |request| PendingNotification {
+            node: String::from("unix"),
+            request,
+            sent_ts: SystemTime::now(),
+        };
+
+        pending(NotificationRequest::Rule(
+            RuleOperation::Disable,
+            make_rule("a", false),
+        ))
+        .apply(&mut node);
+        assert_eq!(
+            config(&node).rules,
+            vec![make_rule("a", false), make_rule("b", true)]
+        );
+
+        pending(NotificationRequest::Rule(
+            RuleOperation::Delete,
+            make_rule("b", true),
+        ))
+        .apply(&mut node);
+        assert_eq!(config(&node).rules, vec![make_rule("a", false)]);
+
+        pending(NotificationRequest::Rule(
+            RuleOperation::Change,
+            make_rule("c", true),
+        ))
+        .apply(&mut node);
+        assert_eq!(
+            config(&node).rules,
+            vec![make_rule("a", false), make_rule("c", true)]
+        );
+
+        let firewall = pb::SysFirewall {
+            enabled: true,
+            version: 1,
+            system_rules: Vec::default(),
+        };
+        let reload = pending(NotificationRequest::ReloadFirewall {
+            firewall: firewall.clone(),
+            rollback: None,
+        });
+        assert_eq!(reload.describe(), "reload firewall on unix");
+        reload.apply(&mut node);
+        assert_eq!(config(&node).system_firewall, Some(firewall.clone()));
+
+        let changed = pb::SysFirewall {
+            enabled: false,
+            ..firewall.clone()
+        };
+        let change = pending(NotificationRequest::ReloadFirewall {
+            firewall: changed.clone(),
+            rollback: Some(firewall),
+        });
+        assert_eq!(change.describe(), "change firewall on unix");
+        assert_eq!(change.request.get_str(), "change_firewall");
+        change.apply(&mut node);
+        assert_eq!(config(&node).system_firewall, Some(changed));
+
+        let command = |command| pending(NotificationRequest::Command(command));
+        let pause = command(DaemonCommand::DisableInterception);
+        assert_eq!(pause.describe(), "pause interception on unix");
+        pause.apply(&mut node);
+        assert!(node.interception_paused);
+        command(DaemonCommand::EnableInterception).apply(&mut node);
+        assert!(!node.interception_paused);
+        command(DaemonCommand::DisableFirewall).apply(&mut node);
+        assert!(!config(&node).is_firewall_running);
+
+        let json = String::from(r#"{"DefaultAction": "deny"}"#);
+        let change = pending(NotificationRequest::ChangeConfig(json.clone()));
+        assert_eq!(change.describe(), "change config on unix");
+        assert_eq!(change.request.notification(1).data, json);
+        change.apply(&mut node);
+        assert_eq!(config(&node).config, json);
+    }
+}
@@ -1,24 +1,219 @@
-use serde;
-
-/// From opensnitch/daemon/ui/config/config.go
-/// Only selective fields implemented to save time.
-#[derive(serde::Serialize)]
-#[allow(non_snake_case)]
-pub struct OpenSnitchDaemonConfig {
-    // LogLevel: int32,
-    // Firewall: string,
-    pub DefaultAction: String,
-    // DefaultDuration: string,
-    // ProcMonitorMethod: string ,
-    // FwOptions: FwOptions,
-    // Audit: audit.Config,
-    // Ebpf: ebpf.Config,
-    // Server: ServerConfig,
-    // Rules: RulesOptions,
-    // Internal: InternalOptions,
-    // Stats: statistics.StatsConfig,
-    // Tasks: TasksOptions,
-    // InterceptUnknown: bool,
-    // LogUTC: bool,
-    // LogMicro: bool,
+//! JSON configuration of the `OpenSnitch` daemon, as shared in `ClientConfig.config` and
+//! sent back with `CHANGE_CONFIG` notifications.
+//! From opensnitch/daemon/ui/config/config.go. The config is kept as the JSON object the
+//! daemon sent and settings are read and written by key path, so that keys, nulls and values
+//! of unexpected types that aren't changed go back as they came.
+
+use serde_json::{Map, Value};
+
+/// A daemon's config.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OpenSnitchDaemonConfig(Map<String, Value>);
+
+/// Settings the TUI sets for every daemon when replying to its subscription.
+/// There's no setting for how long a daemon waits on a prompt, that's capped in
+/// opensnitch/daemon/ui/client.go and the TUI answers before then.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigOverrides {
+    /// Action for connections while the TUI is connected but doesn't answer.
+    pub default_action: String,
+}
+
+impl OpenSnitchDaemonConfig {
+    /// Parse the config a daemon shared. An empty string is an empty config.
+    /// # Errors
+    /// If the config isn't a JSON object.
+    pub fn parse(json: &str) -> Result<OpenSnitchDaemonConfig, String> {
+        if json.trim().is_empty() {
+            return Ok(OpenSnitchDaemonConfig::default());
+        }
+        match serde_json::from_str(json) {
+            Ok(Value::Object(config)) => Ok(OpenSnitchDaemonConfig(config)),
+            Ok(_) => Err(String::from("Invalid daemon config: not a JSON object")),
+            Err(err) => Err(format!("Invalid daemon config: {err}")),
+        }
+    }
+
+    /// Value at a key path like `Stats.Workers`, if the daemon sent it, null or not.
+    #[must_use]
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        let mut keys = path.split('.');
+        let first = self.0.get(keys.next()?)?;
+        keys.try_fold(first, |value, key| value.as_object()?.get(key))
+    }
+
+    /// Set the value at a key path, turning whatever isn't an object along it into one.
+    pub fn set(&mut self, path: &str, value: Value) {
+        let mut object = &mut self.0;
+        let mut keys = path.split('.').peekable();
+        while let Some(key) = keys.next() {
+            if keys.peek().is_none() {
+                object.insert(key.to_string(), value);
+                return;
+            }
+            let entry = object
+                .entry(key)
+                .or_insert_with(|| Value::Object(Map::new()));
+            if !entry.is_object() {
+                *entry = Value::Object(Map::new());
+            }
+            let Value::Object(inner) = entry else {
+                return;
+            };
+            object = inner;
+        }
+    }
+
+    /// Config as JSON for the daemon.
+    /// # Errors
+    /// If serialization fails, which it shouldn't.
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(&self.0).map_err(|err| err.to_string())
+    }
+}
+
+/// Merge `overrides` into the config JSON a daemon shared, keeping every other key and
+/// value as the daemon sent it. An empty string is an empty config.
+/// # Errors
+/// If the daemon's config isn't a JSON object.
+pub fn merge_overrides(json: &str, overrides: &ConfigOverrides) -> Result<String, String> {
+    let mut config = OpenSnitchDaemonConfig::parse(json)?;
+    config.set(
+        "DefaultAction",
+        Value::from(overrides.default_action.as_str()),
+    );
+    serde_json::to_string(&config.0).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Config of a daemon installed from a release package.
+    const DAEMON_CONFIG: &str = include_str!("testdata/daemon-config.json");
+
+    fn keys(value: &Value) -> Vec<&str> {
+        value
+            .as_object()
+            .expect("not an object")
+            .keys()
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Test settings are read and written by key path, and everything else survives a round
+    /// trip, nulls and values of unexpected types included.
+    #[test]
+    fn test_round_trip() {
+        let json = r#"{
+            "Server": {"Address": "unix:///tmp/osui.sock", "Authentication": {"Type": "simple"}},
+            "DefaultAction": "deny",
+            "DefaultDuration": null,
+            "LogLevel": "2",
+            "Rules": {"Path": "/etc/opensnitchd/rules/", "Future": [1, 2]},
+            "Stats": null,
+            "Audit": {"AudispSocketPath": "/var/run/audispd_events"}
+        }"#;
+        let mut config = OpenSnitchDaemonConfig::parse(json).expect("parse failed");
+        assert_eq!(config.get("DefaultAction"), Some(&Value::from("deny")));
+        assert_eq!(config.get("DefaultDuration"), Some(&Value::Null));
+        assert_eq!(config.get("LogLevel"), Some(&Value::from("2")));
+        assert_eq!(
+            config.get("Server.Authentication.Type"),
+            Some(&Value::from("simple"))
+        );
+        assert_eq!(config.get("Server.Address.Scheme"), None);
+        assert_eq!(config.get("Firewall"), None);
+
+        let round_trip: Value =
+            serde_json::from_str(&config.to_json().expect("to_json failed")).expect("bad json");
+        let mut original: Value = serde_json::from_str(json).expect("bad json");
+        assert_eq!(round_trip, original);
+        assert_eq!(
+            keys(&round_trip),
+            vec![
+                "Server",
+                "DefaultAction",
+                "DefaultDuration",
+                "LogLevel",
+                "Rules",
+                "Stats",
+                "Audit"
+            ]
+        );
+
+        config.set("Rules.Path", Value::from(""));
+        config.set("Stats.Workers", Value::from(6));
+        let changed: Value =
+            serde_json::from_str(&config.to_json().expect("to_json failed")).expect("bad json");
+        original["Rules"]["Path"] = Value::from("");
+        original["Stats"] = serde_json::json!({"Workers": 6});
+        assert_eq!(changed, original);
+
+        assert_eq!(
+            OpenSnitchDaemonConfig::parse(""),
+            Ok(OpenSnitchDaemonConfig::default())
+        );
+        assert!(OpenSnitchDaemonConfig::parse("[]").is_err());
+        assert!(OpenSnitchDaemonConfig::parse("not json").is_err());
+    }
+
+    /// Test only the overridden settings of a real daemon config change.
+    #[test]
+    fn test_merge_overrides() {
+        let overrides = ConfigOverrides {
+            default_action: String::from("deny"),
+        };
+        let merged = merge_overrides(DAEMON_CONFIG, &overrides).expect("merge failed");
+        let merged: Value = serde_json::from_str(&merged).expect("bad json");
+        let mut original: Value = serde_json::from_str(DAEMON_CONFIG).expect("bad json");
+        assert_eq!(merged["DefaultAction"], "deny");
+        assert_eq!(merged["Server"]["Loggers"][0]["Tag"], "opensnitchd");
+        // Keys stay in the order the daemon sent them.
+        assert_eq!(
+            keys(&merged),
+            vec![
+                "Server",
+                "DefaultAction",
+                "DefaultDuration",
+                "InterceptUnknown",
+                "ProcMonitorMethod",
+                "LogLevel",
+                "LogUTC",
+                "LogMicro",
+                "Firewall",
+                "FwOptions",
+                "Rules",
+                "Ebpf",
+                "Audit",
+                "Internal",
+                "Stats",
+                "TaskManager"
+            ]
+        );
+
+        original["DefaultAction"] = Value::from("deny");
+        assert_eq!(merged, original);
+
+        assert_eq!(
+            merge_overrides("", &overrides),
+            Ok(String::from(r#"{"DefaultAction":"deny"}"#))
+        );
+        assert!(merge_overrides("not json", &overrides).is_err());
+        assert!(merge_overrides("[]", &overrides).is_err());
+
+        // Keys the TUI models are kept even when their values don't fit the model.
+        let json = r#"{"DefaultAction": null, "LogLevel": null, "Rules": null, "Stats": {"Workers": "6"}}"#;
+        let merged = merge_overrides(json, &overrides).expect("merge failed");
+        let merged: Value = serde_json::from_str(&merged).expect("bad json");
+        assert_eq!(
+            merged,
+            serde_json::json!({
+                "DefaultAction": "deny",
+                "LogLevel": null,
+                "Rules": null,
+                "Stats": {"Workers": "6"}
+            })
+        );
+    }
 }
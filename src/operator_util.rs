@@ -1,10 +1,10 @@
-use crate::opensnitch_proto::pb::Operator;
+use crate::opensnitch_proto::pb::{self, Operator};
 
 use crate::constants;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::str::FromStr;
 
 /// A map representing which presets are enabled for operator chain creation.
-/// TODO: wishlist - process hashes.
 #[derive(Clone, Debug, Default)]
 #[allow(clippy::struct_excessive_bools)]
 pub struct PresetCombination {
@@ -14,6 +14,19 @@ pub struct PresetCombination {
     pub exact_dst_port: bool,
     pub exact_protocol: bool,
     pub match_hostname: MatchHostname,
+    /// Match any executable in the same directory as the process.
+    pub process_path_regexp_dir: bool,
+    /// Prefix length of the IPv4 destination network to match.
+    pub dst_network_v4_prefix: Option<u8>,
+    /// Prefix length of the IPv6 destination network to match.
+    pub dst_network_v6_prefix: Option<u8>,
+    /// Path to a directory of domain blocklists on the daemon's host.
+    pub lists_domains: Option<String>,
+    pub exact_cmdline: bool,
+    pub exact_md5: bool,
+    pub exact_sha1: bool,
+    /// Match the path of the process' parent.
+    pub process_tree: bool,
 }
 
 /// Hostname matching isn't binary. It may be disabled or a degree of enabled: exact/any subdomain.
@@ -41,8 +54,38 @@ impl FromStr for PresetCombination {
                     res.exact_user_id = true;
                 }
                 "exact_ppath" => {
+                    if res.process_path_regexp_dir {
+                        return Err("Conflicting process path matching presets".to_string());
+                    }
                     res.exact_ppath = true;
                 }
+                "process_path_regexp_dir" => {
+                    if res.exact_ppath {
+                        return Err("Conflicting process path matching presets".to_string());
+                    }
+                    res.process_path_regexp_dir = true;
+                }
+                "dst_network_/24" | "dst_network_/16" => {
+                    if res.dst_network_v4_prefix.is_some() {
+                        return Err("Conflicting IPv4 network matching presets".to_string());
+                    }
+                    res.dst_network_v4_prefix = Some(if key.ends_with("24") { 24 } else { 16 });
+                }
+                "exact_cmdline" => {
+                    res.exact_cmdline = true;
+                }
+                "exact_md5" => {
+                    res.exact_md5 = true;
+                }
+                "exact_sha1" => {
+                    res.exact_sha1 = true;
+                }
+                "process_tree" => {
+                    res.process_tree = true;
+                }
+                "dst_network_/64" => {
+                    res.dst_network_v6_prefix = Some(64);
+                }
                 "exact_dst_ip" => {
                     res.exact_dst_ip = true;
                 }
@@ -64,9 +107,14 @@ impl FromStr for PresetCombination {
                     }
                     res.match_hostname = MatchHostname::AnySubdomain;
                 }
-                _ => {
-                    return Err(format!("Unknown preset key: {key}"));
-                }
+                _ => match key.split_once(':') {
+                    Some(("lists_domains", path)) if !path.is_empty() => {
+                        res.lists_domains = Some(path.to_string());
+                    }
+                    _ => {
+                        return Err(format!("Unknown preset key: {key}"));
+                    }
+                },
             }
         }
         Ok(res)
@@ -85,9 +133,40 @@ pub struct GeneratedOperatorsInputs {
     pub dst_port: Option<u32>,
     pub protocol: Option<String>,
     pub hostname: Option<String>,
+    /// Process args joined by spaces, as the daemon matches them.
+    pub cmdline: Option<String>,
+    pub md5: Option<String>,
+    pub sha1: Option<String>,
+    /// Path of the parent process.
+    pub parent_path: Option<String>,
 }
 
 impl GeneratedOperatorsInputs {
+    /// Fill in all the inputs from a trapped connection with best effort.
+    #[must_use]
+    pub fn from_connection(conn: &pb::Connection) -> Self {
+        GeneratedOperatorsInputs {
+            user_id: Some(conn.user_id),
+            ppath: Some(conn.process_path.clone()),
+            dst_ip: Some(conn.dst_ip.clone()),
+            dst_port: Some(conn.dst_port),
+            protocol: Some(conn.protocol.clone()),
+            hostname: if conn.dst_host.is_empty() {
+                None
+            } else {
+                Some(conn.dst_host.clone())
+            },
+            cmdline: if conn.process_args.is_empty() {
+                None
+            } else {
+                Some(conn.process_args.join(" "))
+            },
+            md5: process_checksum(conn, constants::Operand::ProcessHashMd5, "md5"),
+            sha1: process_checksum(conn, constants::Operand::ProcessHashSha1, "sha1"),
+            parent_path: parent_path(conn),
+        }
+    }
+
     /// Generate preset operators for this input struct based on the desired preset combo.
     #[must_use]
     pub fn generate_operators(&self, combo: &PresetCombination) -> Vec<Operator> {
@@ -105,11 +184,51 @@ impl GeneratedOperatorsInputs {
         {
             res.push(match_proc_path(ppath));
         }
+        if combo.process_path_regexp_dir
+            && let Some(ppath) = &self.ppath
+            && let Some(op) = match_proc_dir(ppath)
+        {
+            res.push(op);
+        }
+        if combo.exact_cmdline
+            && let Some(cmdline) = &self.cmdline
+        {
+            res.push(match_simple(constants::Operand::ProcessCmd, cmdline));
+        }
+        if combo.exact_md5
+            && let Some(md5) = &self.md5
+        {
+            res.push(match_simple(constants::Operand::ProcessHashMd5, md5));
+        }
+        if combo.exact_sha1
+            && let Some(sha1) = &self.sha1
+        {
+            res.push(match_simple(constants::Operand::ProcessHashSha1, sha1));
+        }
+        if combo.process_tree
+            && let Some(parent_path) = &self.parent_path
+        {
+            res.push(match_simple(
+                constants::Operand::ProcessParentPath,
+                parent_path,
+            ));
+        }
         if combo.exact_dst_ip
             && let Some(dst_ip) = &self.dst_ip
         {
             res.push(match_dst_ip(dst_ip));
         }
+        if let Some(dst_ip) = &self.dst_ip
+            && let Ok(ip) = dst_ip.parse::<IpAddr>()
+        {
+            let prefix = match ip {
+                IpAddr::V4(_) => combo.dst_network_v4_prefix,
+                IpAddr::V6(_) => combo.dst_network_v6_prefix,
+            };
+            if let Some(prefix) = prefix {
+                res.push(match_dst_network(ip, prefix));
+            }
+        }
         if combo.exact_dst_port
             && let Some(dst_port) = self.dst_port
         {
@@ -133,10 +252,110 @@ impl GeneratedOperatorsInputs {
                 }
             }
         }
+        // Lists don't depend on the connection, only on the lists present on the daemon's host.
+        if let Some(path) = &combo.lists_domains {
+            res.push(match_lists_domains(path));
+        }
         res
     }
 }
 
+/// Checksum of the process executable. The daemon keys checksums by operand, but accept
+/// bare algorithm names too.
+fn process_checksum(
+    conn: &pb::Connection,
+    operand: constants::Operand,
+    algorithm: &str,
+) -> Option<String> {
+    conn.process_checksums
+        .get(operand.get_str())
+        .or_else(|| conn.process_checksums.get(algorithm))
+        .filter(|sum| !sum.is_empty())
+        .cloned()
+}
+
+/// Path of the parent process. The daemon's process tree starts with the process itself,
+/// followed by its ancestors.
+fn parent_path(conn: &pb::Connection) -> Option<String> {
+    let mut tree = conn.process_tree.iter();
+    if tree
+        .clone()
+        .next()
+        .is_some_and(|entry| entry.value == conn.process_id)
+    {
+        tree.next();
+    }
+    tree.next()
+        .map(|entry| entry.key.clone())
+        .filter(|path| !path.is_empty())
+}
+
+/// Name given to rules created via the TUI unless the user picks one.
+#[must_use]
+pub fn default_rule_name(
+    action: constants::Action,
+    duration: constants::Duration,
+    ppath: &str,
+) -> String {
+    // TODO: Leading slash gets turned into double-dash, may be annoying
+    let pretty_proc_path = ppath.replace('/', "-");
+    format!(
+        "{}-{}-simple-via-tui-{pretty_proc_path}",
+        action.get_str(),
+        duration.get_str()
+    )
+}
+
+/// Wrap operators into a rule with a single list operator.
+/// # Panics
+/// If the operators fail to serialize as JSON, which `serde_impl.rs` should rule out.
+#[must_use]
+pub fn build_list_rule(
+    name: String,
+    description: String,
+    action: constants::Action,
+    duration: constants::Duration,
+    operators: Vec<Operator>,
+) -> pb::Rule {
+    let maybe_operator_json = serde_json::to_string(&operators);
+    // Shouldn't really happen due to serde_impl.rs, ideally something caught at build time.
+    assert!(
+        maybe_operator_json.is_ok(),
+        "Operator list JSON serialization failed: {}",
+        maybe_operator_json.unwrap_err()
+    );
+
+    pb::Rule {
+        created: 0,
+        name,
+        description,
+        enabled: true,
+        precedence: false,
+        nolog: false,
+        action: String::from(action.get_str()),
+        duration: String::from(duration.get_str()),
+        operator: Some(Operator {
+            r#type: String::from(constants::RuleType::List.get_str()),
+            operand: String::from(constants::Operand::List.get_str()),
+            data: maybe_operator_json.unwrap(),
+            sensitive: false,
+            list: operators,
+        }),
+    }
+}
+
+/// Match the exact data for an operand.
+#[must_use]
+fn match_simple(operand: constants::Operand, data: &str) -> Operator {
+    Operator {
+        r#type: String::from(constants::RuleType::Simple.get_str()),
+        operand: String::from(operand.get_str()),
+        data: data.to_owned(),
+        sensitive: false,
+        list: Vec::default(),
+    }
+}
+
 #[must_use]
 fn match_user_id(uid: u32) -> Operator {
     Operator {
@@ -159,6 +378,20 @@ fn match_proc_path(ppath: &str) -> Operator {
     }
 }
 
+/// Match any executable in the directory of `ppath`.
+/// Returns `None` for paths without a parent directory.
+#[must_use]
+fn match_proc_dir(ppath: &str) -> Option<Operator> {
+    let (dir, _) = ppath.rsplit_once('/')?;
+    Some(Operator {
+        r#type: String::from(constants::RuleType::Regexp.get_str()),
+        operand: String::from(constants::Operand::ProcessPath.get_str()),
+        data: format!("^{}/[^/]+$", regex::escape(dir)),
+        sensitive: false,
+        list: Vec::default(),
+    })
+}
+
 #[must_use]
 fn match_dst_ip(ip: &str) -> Operator {
     Operator {
@@ -170,6 +403,34 @@ fn match_dst_ip(ip: &str) -> Operator {
     }
 }
 
+/// Network of `ip` with the given prefix length, in CIDR notation.
+#[must_use]
+pub fn network_cidr(ip: IpAddr, prefix: u8) -> String {
+    let network = match ip {
+        IpAddr::V4(ip) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            IpAddr::V4(Ipv4Addr::from(u32::from(ip) & mask))
+        }
+        IpAddr::V6(ip) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            IpAddr::V6(Ipv6Addr::from(u128::from(ip) & mask))
+        }
+    };
+    format!("{network}/{prefix}")
+}
+
+/// Match the network of `ip` with the given prefix length.
+#[must_use]
+fn match_dst_network(ip: IpAddr, prefix: u8) -> Operator {
+    Operator {
+        r#type: String::from(constants::RuleType::Network.get_str()),
+        operand: String::from(constants::Operand::DstNetwork.get_str()),
+        data: network_cidr(ip, prefix),
+        sensitive: false,
+        list: Vec::default(),
+    }
+}
+
 #[must_use]
 fn match_dst_port(port: u32) -> Operator {
     Operator {
@@ -219,6 +480,18 @@ fn match_any_subdomain_hostname(hostname: &str) -> Operator {
     }
 }
 
+/// Match destination domains against the lists in directory `path`.
+#[must_use]
+fn match_lists_domains(path: &str) -> Operator {
+    Operator {
+        r#type: String::from(constants::RuleType::Lists.get_str()),
+        operand: String::from(constants::Operand::ListDomains.get_str()),
+        data: path.to_owned(),
+        sensitive: false,
+        list: Vec::default(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -227,6 +500,7 @@ mod tests {
 
     /// Sanity test regex for hostname subdomains matching.
     #[test]
+    #[allow(clippy::unnecessary_to_owned, clippy::bool_assert_comparison)]
     fn test_match_any_subdomain_hostname() {
         // Map of <Test key> to <whether match expected>
         let mut tests = HashMap::new();
@@ -253,6 +527,7 @@ mod tests {
 
     /// Test operator generator for a single op.
     #[test]
+    #[allow(clippy::field_reassign_with_default)]
     fn test_generator_single() {
         let input = GeneratedOperatorsInputs {
             user_id: Some(123),
@@ -261,6 +536,7 @@ mod tests {
             dst_port: Some(567),
             protocol: Some("tcp".to_string()),
             hostname: None,
+            ..Default::default()
         };
 
         let mut combo = PresetCombination::default();
@@ -281,6 +557,7 @@ mod tests {
 
     /// Test operator generator for many ops.
     #[test]
+    #[allow(clippy::field_reassign_with_default)]
     fn test_generator_many() {
         let input = GeneratedOperatorsInputs {
             user_id: Some(123),
@@ -289,6 +566,7 @@ mod tests {
             dst_port: Some(567),
             protocol: Some("tcp".to_string()),
             hostname: None,
+            ..Default::default()
         };
 
         let mut combo = PresetCombination::default();
@@ -320,6 +598,7 @@ mod tests {
     /// Test operator generator for a single op, but missing data.
     /// TODO: Ideally test this with every input, but I'm lazy.
     #[test]
+    #[allow(clippy::field_reassign_with_default)]
     fn test_generator_single_missing_data() {
         let input = GeneratedOperatorsInputs {
             user_id: Some(123),
@@ -328,6 +607,7 @@ mod tests {
             dst_port: Some(567),
             protocol: Some("tcp".to_string()),
             hostname: None,
+            ..Default::default()
         };
 
         let mut combo = PresetCombination::default();
@@ -339,4 +619,110 @@ mod tests {
 
         assert_eq!(out, expected_out);
     }
+
+    /// Test parsing of the broader presets, including conflicts.
+    #[test]
+    fn test_parse_broad_presets() {
+        let combo = PresetCombination::from_str(
+            "process_path_regexp_dir,dst_network_/16,dst_network_/64,lists_domains:/etc/lists",
+        )
+        .expect("parse failed");
+        assert!(combo.process_path_regexp_dir);
+        assert_eq!(combo.dst_network_v4_prefix, Some(16));
+        assert_eq!(combo.dst_network_v6_prefix, Some(64));
+        assert_eq!(combo.lists_domains.as_deref(), Some("/etc/lists"));
+
+        assert!(PresetCombination::from_str("dst_network_/24,dst_network_/16").is_err());
+        assert!(PresetCombination::from_str("exact_ppath,process_path_regexp_dir").is_err());
+        assert!(PresetCombination::from_str("lists_domains:").is_err());
+        assert!(PresetCombination::from_str("dst_network_/8").is_err());
+    }
+
+    /// Test operator generator for network, regexp and lists presets.
+    #[test]
+    fn test_generator_broad() {
+        let mut input = GeneratedOperatorsInputs {
+            ppath: Some("/opt/my.app/bin/app".to_string()),
+            dst_ip: Some("1.2.3.4".to_string()),
+            ..Default::default()
+        };
+        let combo = PresetCombination::from_str(
+            "process_path_regexp_dir,dst_network_/24,dst_network_/64,lists_domains:/etc/lists",
+        )
+        .expect("parse failed");
+
+        let out = input.generate_operators(&combo);
+        assert_eq!(out.len(), 3);
+        assert_eq!(out[0].r#type, "regexp");
+        assert_eq!(out[0].operand, "process.path");
+        let dir_re = Regex::new(&out[0].data).expect("Bad regex");
+        assert!(dir_re.is_match("/opt/my.app/bin/other"));
+        assert!(!dir_re.is_match("/opt/myxapp/bin/other"));
+        assert!(!dir_re.is_match("/opt/my.app/bin/sub/other"));
+        assert_eq!(out[1].r#type, "network");
+        assert_eq!(out[1].operand, "dest.network");
+        assert_eq!(out[1].data, "1.2.3.0/24");
+        assert_eq!(out[2].r#type, "lists");
+        assert_eq!(out[2].operand, "lists.domains");
+        assert_eq!(out[2].data, "/etc/lists");
+
+        // The IPv6 prefix applies to IPv6 destinations only.
+        input.dst_ip = Some("2001:db8:1:2:3:4:5:6".to_string());
+        let out = input.generate_operators(&combo);
+        assert_eq!(out[1].data, "2001:db8:1:2::/64");
+
+        let combo = PresetCombination::from_str("dst_network_/16").expect("parse failed");
+        assert!(input.generate_operators(&combo).is_empty());
+        input.dst_ip = Some("10.20.30.40".to_string());
+        assert_eq!(input.generate_operators(&combo)[0].data, "10.20.0.0/16");
+    }
+
+    /// Test process identity presets are populated from the connection.
+    #[test]
+    fn test_generator_process_identity() {
+        let conn = pb::Connection {
+            process_id: 42,
+            process_path: String::from("/usr/bin/python3"),
+            process_args: vec![String::from("python3"), String::from("app.py")],
+            process_checksums: [
+                (String::from("process.hash.md5"), String::from("aaa")),
+                (String::from("sha1"), String::from("bbb")),
+            ]
+            .into(),
+            process_tree: vec![
+                pb::StringInt {
+                    key: String::from("/usr/bin/python3"),
+                    value: 42,
+                },
+                pb::StringInt {
+                    key: String::from("/usr/bin/bash"),
+                    value: 7,
+                },
+            ],
+            ..Default::default()
+        };
+        let combo = PresetCombination::from_str("exact_cmdline,exact_md5,exact_sha1,process_tree")
+            .expect("parse failed");
+
+        let out: Vec<(String, String)> = GeneratedOperatorsInputs::from_connection(&conn)
+            .generate_operators(&combo)
+            .into_iter()
+            .map(|op| (op.operand, op.data))
+            .collect();
+        let expected: Vec<(String, String)> = [
+            ("process.command", "python3 app.py"),
+            ("process.hash.md5", "aaa"),
+            ("process.hash.sha1", "bbb"),
+            ("process.parent.path", "/usr/bin/bash"),
+        ]
+        .iter()
+        .map(|(operand, data)| (operand.to_string(), data.to_string()))
+        .collect();
+        assert_eq!(out, expected);
+
+        // Missing data drops the operators.
+        let out = GeneratedOperatorsInputs::from_connection(&pb::Connection::default())
+            .generate_operators(&combo);
+        assert!(out.is_empty());
+    }
 }
@@ -0,0 +1,60 @@
+//! Resolution of user IDs to names from the local passwd file.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Default location of the passwd file.
+pub const PASSWD_PATH: &str = "/etc/passwd";
+
+/// Parse passwd file contents into a map of uid to user name.
+/// Malformed lines are skipped.
+#[must_use]
+pub fn parse_passwd(contents: &str) -> HashMap<u32, String> {
+    contents
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            // name:password:uid:gid:gecos:home:shell
+            let mut fields = line.split(':');
+            let name = fields.next().filter(|name| !name.is_empty())?;
+            let uid = fields.nth(1)?.parse::<u32>().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
+
+/// Load users from a passwd file, or none if it can't be read.
+/// Only meaningful for daemons on the same host as the TUI.
+#[must_use]
+pub fn load_users(path: &Path) -> HashMap<u32, String> {
+    std::fs::read_to_string(path)
+        .map(|contents| parse_passwd(&contents))
+        .unwrap_or_default()
+}
+
+/// Uid of the user running this process, from the owner of its procfs entry.
+#[must_use]
+pub fn current_uid() -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata("/proc/self").ok().map(|meta| meta.uid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test parsing passwd entries, skipping comments and malformed lines.
+    #[test]
+    fn test_parse_passwd() {
+        let users = parse_passwd(
+            "root:x:0:0:root:/root:/bin/bash\n\
+            # comment:x:5:5\n\
+            broken:x:notanumber:0::/:/bin/false\n\
+            :x:7:7::/:/bin/false\n\
+            alice:x:1000:1000:Alice,,,:/home/alice:/bin/zsh\n",
+        );
+        assert_eq!(users.len(), 2);
+        assert_eq!(users.get(&0).map(String::as_str), Some("root"));
+        assert_eq!(users.get(&1000).map(String::as_str), Some("alice"));
+    }
+}
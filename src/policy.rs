@@ -0,0 +1,368 @@
+//! Declarative policy for answering trapped connections without asking the user.
+//!
+//! A policy is a TOML file of `[[rule]]` tables, tried in order. The first rule whose
+//! criteria all match a connection decides the action and duration of the rule sent
+//! back, and which presets it's generated with. Omitted criteria match anything.
+//!
+//! ```toml
+//! [[rule]]
+//! name = "apt"
+//! process_path = "/usr/lib/apt/methods/*"
+//! uids = [0]
+//! dst_host_suffix = "debian.org"
+//! dst_ports = "80,443"
+//! protocol = "tcp"
+//! action = "allow"
+//! duration = "always"
+//! presets = "exact_ppath,exact_dst_port"
+//! ```
+
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::str::FromStr;
+
+use regex::Regex;
+
+use crate::constants;
+use crate::opensnitch_proto::pb;
+use crate::operator_util::{self, PresetCombination};
+
+/// A `[[rule]]` as written in the policy file.
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyRuleDef {
+    name: Option<String>,
+    process_path: Option<String>,
+    #[serde(default)]
+    uids: Vec<u32>,
+    dst_host_suffix: Option<String>,
+    dst_ports: Option<String>,
+    protocol: Option<String>,
+    action: String,
+    #[serde(default = "default_duration")]
+    duration: String,
+    presets: Option<String>,
+}
+
+fn default_duration() -> String {
+    String::from(constants::Duration::Once.get_str())
+}
+
+/// The policy file.
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyDef {
+    #[serde(default, rename = "rule")]
+    rules: Vec<PolicyRuleDef>,
+}
+
+/// A validated policy rule.
+#[derive(Debug)]
+pub struct PolicyRule {
+    /// Name for logs, defaulting to the rule's position in the file.
+    pub name: String,
+    process_path: Option<Regex>,
+    uids: Vec<u32>,
+    /// Lowercased, without a leading dot.
+    dst_host_suffix: Option<String>,
+    dst_ports: Vec<RangeInclusive<u32>>,
+    /// Lowercased.
+    protocol: Option<String>,
+    pub action: constants::Action,
+    pub duration: constants::Duration,
+    /// Presets to generate the rule with, instead of the default ones.
+    presets: Option<PresetCombination>,
+}
+
+impl PolicyRule {
+    fn from_def(idx: usize, def: PolicyRuleDef) -> Result<Self, String> {
+        let name = def.name.unwrap_or_else(|| format!("#{}", idx + 1));
+        let err = |msg: String| format!("Policy rule {name}: {msg}");
+        let Ok(
+            action @ (constants::Action::Allow
+            | constants::Action::Deny
+            | constants::Action::Reject),
+        ) = constants::Action::new(&def.action)
+        else {
+            return Err(err(format!("invalid action: {}", def.action)));
+        };
+        let duration = constants::Duration::new(&def.duration)
+            .map_err(|_| err(format!("invalid duration: {}", def.duration)))?;
+        let process_path = def
+            .process_path
+            .as_deref()
+            .map(glob_to_regex)
+            .transpose()
+            .map_err(err)?;
+        let dst_ports = match def.dst_ports.as_deref() {
+            Some(spec) => parse_port_ranges(spec).map_err(err)?,
+            None => Vec::default(),
+        };
+        let presets = def
+            .presets
+            .as_deref()
+            .map(PresetCombination::from_str)
+            .transpose()
+            .map_err(err)?;
+        Ok(PolicyRule {
+            process_path,
+            uids: def.uids,
+            dst_host_suffix: def
+                .dst_host_suffix
+                .map(|suffix| suffix.trim_start_matches('.').to_lowercase()),
+            dst_ports,
+            protocol: def.protocol.map(|protocol| protocol.to_lowercase()),
+            action,
+            duration,
+            presets,
+            name,
+        })
+    }
+
+    /// Whether every criterion of the rule matches the connection.
+    #[must_use]
+    pub fn matches(&self, conn: &pb::Connection) -> bool {
+        let host = conn.dst_host.to_lowercase();
+        self.process_path
+            .as_ref()
+            .is_none_or(|glob| glob.is_match(&conn.process_path))
+            && (self.uids.is_empty() || self.uids.contains(&conn.user_id))
+            && self.dst_host_suffix.as_ref().is_none_or(|suffix| {
+                host == *suffix
+                    || host
+                        .strip_suffix(suffix.as_str())
+                        .is_some_and(|rest| rest.ends_with('.'))
+            })
+            && (self.dst_ports.is_empty()
+                || self
+                    .dst_ports
+                    .iter()
+                    .any(|range| range.contains(&conn.dst_port)))
+            && self
+                .protocol
+                .as_ref()
+                .is_none_or(|protocol| *protocol == conn.protocol.to_lowercase())
+    }
+
+    /// Rule answering `conn`, generated with this rule's presets or else `default_presets`.
+    /// `None` if the presets yield no operators for the connection.
+    #[must_use]
+    pub fn make_rule(
+        &self,
+        conn: &pb::Connection,
+        default_presets: &PresetCombination,
+    ) -> Option<pb::Rule> {
+        let operators = operator_util::GeneratedOperatorsInputs::from_connection(conn)
+            .generate_operators(self.presets.as_ref().unwrap_or(default_presets));
+        if operators.is_empty() {
+            return None;
+        }
+        Some(operator_util::build_list_rule(
+            operator_util::default_rule_name(self.action, self.duration, &conn.process_path),
+            format!("Matched policy rule {}", self.name),
+            self.action,
+            self.duration,
+            operators,
+        ))
+    }
+}
+
+/// Ordered policy rules.
+#[derive(Debug, Default)]
+pub struct Policy {
+    pub rules: Vec<PolicyRule>,
+}
+
+impl FromStr for Policy {
+    type Err = String;
+
+    /// Parse and validate a policy file's contents.
+    /// # Errors
+    /// If the TOML is malformed or a rule is invalid.
+    fn from_str(s: &str) -> Result<Policy, Self::Err> {
+        let def: PolicyDef = toml::from_str(s).map_err(|err| format!("Invalid policy: {err}"))?;
+        let rules = def
+            .rules
+            .into_iter()
+            .enumerate()
+            .map(|(idx, rule)| PolicyRule::from_def(idx, rule))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Policy { rules })
+    }
+}
+
+impl Policy {
+    /// Load and validate a policy file.
+    /// # Errors
+    /// If the file can't be read or is invalid.
+    pub fn load(path: &Path) -> Result<Policy, String> {
+        std::fs::read_to_string(path)
+            .map_err(|err| format!("Unable to read policy {}: {err}", path.display()))?
+            .parse()
+    }
+
+    /// First rule matching the connection, if any.
+    #[must_use]
+    pub fn find(&self, conn: &pb::Connection) -> Option<&PolicyRule> {
+        self.rules.iter().find(|rule| rule.matches(conn))
+    }
+}
+
+/// Compile a path glob: `*` matches within a path component, `**` across components
+/// and `?` a single character other than `/`.
+fn glob_to_regex(glob: &str) -> Result<Regex, String> {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                pattern.push_str(".*");
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            _ => pattern.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern).map_err(|err| format!("invalid process path glob {glob}: {err}"))
+}
+
+/// Parse comma-separated ports and inclusive ranges, e.g. `53,8000-8999`.
+fn parse_port_ranges(spec: &str) -> Result<Vec<RangeInclusive<u32>>, String> {
+    spec.split(',')
+        .map(|part| {
+            let part = part.trim();
+            let (lo, hi) = part.split_once('-').unwrap_or((part, part));
+            let parse = |port: &str| {
+                port.trim()
+                    .parse::<u16>()
+                    .map(u32::from)
+                    .map_err(|_| format!("invalid port range: {part}"))
+            };
+            let (lo, hi) = (parse(lo)?, parse(hi)?);
+            if lo > hi {
+                return Err(format!("invalid port range: {part}"));
+            }
+            Ok(lo..=hi)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_conn(path: &str, host: &str, port: u32) -> pb::Connection {
+        pb::Connection {
+            protocol: String::from("tcp"),
+            dst_ip: String::from("192.0.2.1"),
+            dst_host: host.to_string(),
+            dst_port: port,
+            user_id: 0,
+            process_path: path.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Test rules are tried in order and each criterion narrows the match.
+    #[test]
+    fn test_find() {
+        let policy: Policy = r#"
+            [[rule]]
+            name = "apt"
+            process_path = "/usr/lib/apt/methods/*"
+            uids = [0]
+            dst_host_suffix = ".debian.org"
+            dst_ports = "80,443"
+            protocol = "TCP"
+            action = "allow"
+            duration = "always"
+            presets = "exact_ppath,exact_dst_port"
+
+            [[rule]]
+            process_path = "/opt/**"
+            dst_ports = "8000-8999"
+            action = "deny"
+        "#
+        .parse()
+        .expect("parse failed");
+        let name = |conn: &pb::Connection| policy.find(conn).map(|rule| rule.name.as_str());
+
+        assert_eq!(
+            name(&make_conn(
+                "/usr/lib/apt/methods/http",
+                "deb.debian.org",
+                443
+            )),
+            Some("apt")
+        );
+        assert_eq!(
+            name(&make_conn("/usr/lib/apt/methods/http", "debian.org", 80)),
+            Some("apt")
+        );
+        // Suffix only matches whole labels.
+        assert_eq!(
+            name(&make_conn(
+                "/usr/lib/apt/methods/http",
+                "evildebian.org",
+                443
+            )),
+            None
+        );
+        // `*` stays within a path component.
+        assert_eq!(
+            name(&make_conn(
+                "/usr/lib/apt/methods/x/http",
+                "deb.debian.org",
+                443
+            )),
+            None
+        );
+        assert_eq!(
+            name(&make_conn(
+                "/usr/lib/apt/methods/http",
+                "deb.debian.org",
+                22
+            )),
+            None
+        );
+        let mut conn = make_conn("/usr/lib/apt/methods/http", "deb.debian.org", 443);
+        conn.user_id = 1000;
+        assert_eq!(name(&conn), None);
+
+        assert_eq!(name(&make_conn("/opt/a/b/c", "", 8080)), Some("#2"));
+        assert_eq!(name(&make_conn("/opt/a/b/c", "", 9000)), None);
+
+        let conn = make_conn("/usr/lib/apt/methods/http", "deb.debian.org", 443);
+        let rule = policy
+            .find(&conn)
+            .and_then(|rule| rule.make_rule(&conn, &PresetCombination::default()))
+            .expect("no rule");
+        assert_eq!(rule.action, "allow");
+        assert_eq!(rule.duration, "always");
+        assert_eq!(rule.description, "Matched policy rule apt");
+        let operators = rule.operator.expect("no operator").data;
+        assert!(operators.contains("process.path"));
+        assert!(operators.contains("dest.port"));
+        assert!(!operators.contains("dest.ip"));
+    }
+
+    /// Test invalid policies are rejected with the offending rule named.
+    #[test]
+    fn test_invalid() {
+        let err = |s: &str| Policy::from_str(s).expect_err("parse succeeded");
+        assert!(err("[[rule]]\naction = \"maybe\"").starts_with("Policy rule #1: invalid action"));
+        assert!(
+            err("[[rule]]\nname = \"x\"\naction = \"allow\"\nduration = \"2d\"")
+                .starts_with("Policy rule x: invalid duration")
+        );
+        assert!(
+            err("[[rule]]\naction = \"allow\"\ndst_ports = \"90-80\"")
+                .contains("invalid port range: 90-80")
+        );
+        assert!(err("[[rule]]\naction = \"allow\"\npresets = \"bogus\"").contains("bogus"));
+        assert!(err("[[rule]]\naction = \"allow\"\nport = 1").starts_with("Invalid policy"));
+        assert!(Policy::from_str("").expect("parse failed").rules.is_empty());
+    }
+}
@@ -0,0 +1,527 @@
+//! Editor to tweak the rule for a pending connection before sending it to the daemon.
+
+use crate::constants;
+use crate::event::ConnectionEvent;
+use crate::opensnitch_proto::pb;
+use crate::operator_util::{self, GeneratedOperatorsInputs, MatchHostname, PresetCombination};
+use crate::rules_view;
+use std::net::IpAddr;
+
+/// Editable fields, in the order they're listed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EditorField {
+    UserId,
+    ProcessPath,
+    ProcessDir,
+    Cmdline,
+    Md5,
+    Sha1,
+    ParentPath,
+    DstIp,
+    DstNetwork,
+    DstPort,
+    Protocol,
+    Hostname,
+    DomainsList,
+    Action,
+    Duration,
+    Name,
+    Description,
+}
+
+impl EditorField {
+    pub const ALL: [EditorField; 17] = [
+        EditorField::UserId,
+        EditorField::ProcessPath,
+        EditorField::ProcessDir,
+        EditorField::Cmdline,
+        EditorField::Md5,
+        EditorField::Sha1,
+        EditorField::ParentPath,
+        EditorField::DstIp,
+        EditorField::DstNetwork,
+        EditorField::DstPort,
+        EditorField::Protocol,
+        EditorField::Hostname,
+        EditorField::DomainsList,
+        EditorField::Action,
+        EditorField::Duration,
+        EditorField::Name,
+        EditorField::Description,
+    ];
+
+    #[must_use]
+    pub fn get_str(&self) -> &str {
+        match self {
+            EditorField::UserId => "User ID",
+            EditorField::ProcessPath => "Process path",
+            EditorField::ProcessDir => "Process dir",
+            EditorField::Cmdline => "Command line",
+            EditorField::Md5 => "MD5",
+            EditorField::Sha1 => "SHA1",
+            EditorField::ParentPath => "Parent",
+            EditorField::DstIp => "Dst IP",
+            EditorField::DstNetwork => "Dst network",
+            EditorField::DstPort => "Dst port",
+            EditorField::Protocol => "Protocol",
+            EditorField::Hostname => "Hostname",
+            EditorField::DomainsList => "Domain lists",
+            EditorField::Action => "Action",
+            EditorField::Duration => "Duration",
+            EditorField::Name => "Name",
+            EditorField::Description => "Description",
+        }
+    }
+}
+
+/// Next duration when cycling, from shortest to longest lived.
+#[must_use]
+pub fn next_duration(duration: constants::Duration) -> constants::Duration {
+    match duration {
+        constants::Duration::Once => constants::Duration::Seconds30,
+        constants::Duration::Seconds30 => constants::Duration::Minutes5,
+        constants::Duration::Minutes5 => constants::Duration::Minutes15,
+        constants::Duration::Minutes15 => constants::Duration::Minutes30,
+        constants::Duration::Minutes30 => constants::Duration::Hours1,
+        constants::Duration::Hours1 => constants::Duration::Hours12,
+        constants::Duration::Hours12 => constants::Duration::UntilRestart,
+        constants::Duration::UntilRestart => constants::Duration::Always,
+        constants::Duration::Always => constants::Duration::Once,
+    }
+}
+
+/// Rule being edited for a pending connection.
+#[derive(Debug)]
+pub struct RuleEditor {
+    /// ID of the `ConnectionEvent` the rule answers.
+    pub connection_id: u64,
+    inputs: GeneratedOperatorsInputs,
+    pub presets: PresetCombination,
+    pub action: constants::Action,
+    pub duration: constants::Duration,
+    /// Name picked by the user. The name is derived from action and duration until set.
+    custom_name: Option<String>,
+    pub description: String,
+    /// Index into `EditorField::ALL`.
+    pub focus: usize,
+    /// Whether keystrokes are currently going into the focused text field.
+    pub editing_text: bool,
+    /// Why the latest attempt to send failed, if it did.
+    pub status: Option<String>,
+}
+
+impl RuleEditor {
+    /// Start editing a rule for `evt`, prefilled with the given presets, action and duration.
+    #[must_use]
+    pub fn new(
+        evt: &ConnectionEvent,
+        presets: &PresetCombination,
+        action: constants::Action,
+        duration: constants::Duration,
+    ) -> Self {
+        RuleEditor {
+            connection_id: evt.id,
+            inputs: GeneratedOperatorsInputs::from_connection(&evt.connection),
+            presets: presets.clone(),
+            action,
+            duration,
+            custom_name: None,
+            description: String::default(),
+            focus: 0,
+            editing_text: false,
+            status: None,
+        }
+    }
+
+    #[must_use]
+    pub fn focused(&self) -> EditorField {
+        EditorField::ALL[self.focus]
+    }
+
+    pub fn focus_prev(&mut self) {
+        self.focus = self.focus.saturating_sub(1);
+    }
+
+    pub fn focus_next(&mut self) {
+        self.focus = std::cmp::min(self.focus + 1, EditorField::ALL.len() - 1);
+    }
+
+    /// Toggle or cycle the focused field, or start typing into a text field.
+    pub fn toggle(&mut self) {
+        let field = self.focused();
+        let dst_ip = self.dst_ip();
+        let presets = &mut self.presets;
+        match field {
+            EditorField::UserId => presets.exact_user_id = !presets.exact_user_id,
+            // Exact and directory matching of the process path are mutually exclusive.
+            EditorField::ProcessPath => {
+                presets.exact_ppath = !presets.exact_ppath;
+                presets.process_path_regexp_dir &= !presets.exact_ppath;
+            }
+            EditorField::ProcessDir => {
+                presets.process_path_regexp_dir = !presets.process_path_regexp_dir;
+                presets.exact_ppath &= !presets.process_path_regexp_dir;
+            }
+            EditorField::Cmdline => presets.exact_cmdline = !presets.exact_cmdline,
+            EditorField::Md5 => presets.exact_md5 = !presets.exact_md5,
+            EditorField::Sha1 => presets.exact_sha1 = !presets.exact_sha1,
+            EditorField::ParentPath => presets.process_tree = !presets.process_tree,
+            EditorField::DstIp => presets.exact_dst_ip = !presets.exact_dst_ip,
+            EditorField::DstNetwork => match dst_ip {
+                Some(IpAddr::V4(_)) => {
+                    presets.dst_network_v4_prefix = match presets.dst_network_v4_prefix {
+                        None => Some(24),
+                        Some(24) => Some(16),
+                        Some(_) => None,
+                    };
+                }
+                Some(IpAddr::V6(_)) => {
+                    presets.dst_network_v6_prefix = match presets.dst_network_v6_prefix {
+                        None => Some(64),
+                        Some(_) => None,
+                    };
+                }
+                None => {}
+            },
+            EditorField::DstPort => presets.exact_dst_port = !presets.exact_dst_port,
+            EditorField::Protocol => presets.exact_protocol = !presets.exact_protocol,
+            EditorField::Hostname => {
+                presets.match_hostname = match presets.match_hostname {
+                    MatchHostname::Disabled => MatchHostname::Exact,
+                    MatchHostname::Exact => MatchHostname::AnySubdomain,
+                    MatchHostname::AnySubdomain => MatchHostname::Disabled,
+                };
+            }
+            EditorField::Action => {
+                self.action = rules_view::next_rule_action(self.action.get_str());
+            }
+            EditorField::Duration => self.duration = next_duration(self.duration),
+            EditorField::DomainsList => {
+                presets.lists_domains.get_or_insert_default();
+                self.editing_text = true;
+            }
+            EditorField::Name => {
+                self.custom_name = Some(self.name());
+                self.editing_text = true;
+            }
+            EditorField::Description => self.editing_text = true,
+        }
+    }
+
+    /// Stop typing into the focused text field.
+    pub fn stop_editing(&mut self) {
+        self.editing_text = false;
+        // No path means no list operator.
+        if self
+            .presets
+            .lists_domains
+            .as_ref()
+            .is_some_and(String::is_empty)
+        {
+            self.presets.lists_domains = None;
+        }
+    }
+
+    /// Text field currently being typed into.
+    fn text_mut(&mut self) -> Option<&mut String> {
+        if !self.editing_text {
+            return None;
+        }
+        match self.focused() {
+            EditorField::DomainsList => self.presets.lists_domains.as_mut(),
+            EditorField::Name => self.custom_name.as_mut(),
+            EditorField::Description => Some(&mut self.description),
+            _ => None,
+        }
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        if let Some(text) = self.text_mut() {
+            text.push(c);
+        }
+    }
+
+    pub fn pop_char(&mut self) {
+        if let Some(text) = self.text_mut() {
+            text.pop();
+        }
+    }
+
+    /// Rule name, either picked by the user or derived.
+    #[must_use]
+    pub fn name(&self) -> String {
+        match &self.custom_name {
+            Some(name) => name.clone(),
+            None => operator_util::default_rule_name(
+                self.action,
+                self.duration,
+                self.inputs.ppath.as_deref().unwrap_or_default(),
+            ),
+        }
+    }
+
+    fn dst_ip(&self) -> Option<IpAddr> {
+        self.inputs.dst_ip.as_ref()?.parse().ok()
+    }
+
+    /// Operators of the rule as currently configured.
+    #[must_use]
+    pub fn operators(&self) -> Vec<pb::Operator> {
+        self.inputs.generate_operators(&self.presets)
+    }
+
+    /// Operator list as it would be sent to the daemon.
+    #[must_use]
+    pub fn preview(&self) -> String {
+        serde_json::to_string_pretty(&self.operators()).unwrap_or_default()
+    }
+
+    /// Build the rule to send.
+    /// # Errors
+    /// If no operator is enabled or the name is empty.
+    pub fn build_rule(&self) -> Result<pb::Rule, String> {
+        let operators = self.operators();
+        if operators.is_empty() {
+            return Err(String::from("Enable at least one operator"));
+        }
+        let name = self.name();
+        if name.trim().is_empty() {
+            return Err(String::from("Rule name cannot be empty"));
+        }
+        Ok(operator_util::build_list_rule(
+            name,
+            self.description.clone(),
+            self.action,
+            self.duration,
+            operators,
+        ))
+    }
+
+    /// Display value of a field, with a checkbox for operator toggles.
+    #[must_use]
+    pub fn field_value(&self, field: EditorField) -> String {
+        let checkbox = |enabled: bool, value: Option<String>| {
+            let mark = if enabled { "x" } else { " " };
+            format!("[{mark}] {}", value.unwrap_or_else(|| String::from("n/a")))
+        };
+        let inputs = &self.inputs;
+        match field {
+            EditorField::UserId => checkbox(
+                self.presets.exact_user_id,
+                inputs.user_id.map(|uid| uid.to_string()),
+            ),
+            EditorField::ProcessPath => checkbox(self.presets.exact_ppath, inputs.ppath.clone()),
+            EditorField::ProcessDir => checkbox(
+                self.presets.process_path_regexp_dir,
+                inputs
+                    .ppath
+                    .as_ref()
+                    .and_then(|ppath| ppath.rsplit_once('/'))
+                    .map(|(dir, _)| format!("{dir}/*")),
+            ),
+            EditorField::Cmdline => checkbox(self.presets.exact_cmdline, inputs.cmdline.clone()),
+            EditorField::Md5 => checkbox(self.presets.exact_md5, inputs.md5.clone()),
+            EditorField::Sha1 => checkbox(self.presets.exact_sha1, inputs.sha1.clone()),
+            EditorField::ParentPath => {
+                checkbox(self.presets.process_tree, inputs.parent_path.clone())
+            }
+            EditorField::DstIp => checkbox(self.presets.exact_dst_ip, inputs.dst_ip.clone()),
+            EditorField::DstNetwork => {
+                let prefix = match self.dst_ip() {
+                    Some(IpAddr::V4(_)) => self.presets.dst_network_v4_prefix,
+                    Some(IpAddr::V6(_)) => self.presets.dst_network_v6_prefix,
+                    None => None,
+                };
+                match (self.dst_ip(), prefix) {
+                    (Some(ip), Some(prefix)) => {
+                        checkbox(true, Some(operator_util::network_cidr(ip, prefix)))
+                    }
+                    (ip, None) => checkbox(false, ip.map(|ip| ip.to_string())),
+                    (None, Some(_)) => checkbox(false, None),
+                }
+            }
+            EditorField::DstPort => checkbox(
+                self.presets.exact_dst_port,
+                inputs.dst_port.map(|port| port.to_string()),
+            ),
+            EditorField::Protocol => checkbox(self.presets.exact_protocol, inputs.protocol.clone()),
+            EditorField::Hostname => {
+                let hostname = inputs.hostname.as_deref().unwrap_or("n/a");
+                match self.presets.match_hostname {
+                    MatchHostname::Disabled => format!("[ ] {hostname}"),
+                    MatchHostname::Exact => format!("[x] {hostname}"),
+                    MatchHostname::AnySubdomain => format!("[x] *.{hostname}"),
+                }
+            }
+            EditorField::DomainsList => checkbox(
+                self.presets.lists_domains.is_some(),
+                self.presets.lists_domains.clone(),
+            ),
+            EditorField::Action => self.action.get_str().to_string(),
+            EditorField::Duration => self.duration.get_str().to_string(),
+            EditorField::Name => self.name(),
+            EditorField::Description => self.description.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn focus(editor: &mut RuleEditor, field: EditorField) {
+        editor.focus = EditorField::ALL
+            .iter()
+            .position(|f| *f == field)
+            .expect("unknown field");
+    }
+
+    fn make_editor() -> RuleEditor {
+        let evt = ConnectionEvent {
+            id: 7,
+            node: String::from("unix"),
+            connection: pb::Connection {
+                protocol: String::from("tcp"),
+                dst_ip: String::from("1.2.3.4"),
+                dst_host: String::from("example.com"),
+                dst_port: 443,
+                user_id: 1000,
+                process_path: String::from("/usr/bin/curl"),
+                ..Default::default()
+            },
+            expiry_ts: SystemTime::now(),
+        };
+        let presets = PresetCombination {
+            exact_dst_ip: true,
+            ..Default::default()
+        };
+        RuleEditor::new(
+            &evt,
+            &presets,
+            constants::Action::Allow,
+            constants::Duration::Once,
+        )
+    }
+
+    /// Test toggling operators and cycling action/duration shape the built rule.
+    #[test]
+    fn test_toggle_fields() {
+        let mut editor = make_editor();
+        assert_eq!(editor.connection_id, 7);
+
+        // Swap dst IP for any subdomain of the host.
+        focus(&mut editor, EditorField::DstIp);
+        editor.toggle();
+        focus(&mut editor, EditorField::Hostname);
+        editor.toggle();
+        editor.toggle();
+        assert_eq!(
+            editor.field_value(EditorField::Hostname),
+            "[x] *.example.com"
+        );
+
+        focus(&mut editor, EditorField::Action);
+        editor.toggle();
+        editor.toggle();
+        editor.focus_next();
+        editor.toggle();
+
+        let rule = editor.build_rule().expect("no rule");
+        assert_eq!(rule.action, "reject");
+        assert_eq!(rule.duration, "30s");
+        assert_eq!(rule.name, "reject-30s-simple-via-tui--usr-bin-curl");
+        let operator = rule.operator.expect("no operator");
+        assert_eq!(operator.list.len(), 1);
+        assert_eq!(operator.list[0].operand, "dest.host");
+        assert_eq!(operator.list[0].r#type, "regexp");
+        assert!(editor.preview().contains("dest.host"));
+
+        // Nothing to match on.
+        focus(&mut editor, EditorField::Hostname);
+        editor.toggle();
+        assert!(editor.build_rule().is_err());
+    }
+
+    /// Test typing a name and description.
+    #[test]
+    fn test_text_fields() {
+        let mut editor = make_editor();
+        // Typing is ignored outside text fields.
+        editor.push_char('x');
+
+        focus(&mut editor, EditorField::Name);
+        editor.toggle();
+        assert!(editor.editing_text);
+        for _ in 0.."-usr-bin-curl".len() {
+            editor.pop_char();
+        }
+        for c in "curl".chars() {
+            editor.push_char(c);
+        }
+        editor.stop_editing();
+
+        // Name no longer follows the action once picked.
+        focus(&mut editor, EditorField::Action);
+        editor.toggle();
+        focus(&mut editor, EditorField::Description);
+        editor.toggle();
+        for c in "web".chars() {
+            editor.push_char(c);
+        }
+
+        let rule = editor.build_rule().expect("no rule");
+        assert_eq!(rule.name, "allow-once-simple-via-tui-curl");
+        assert_eq!(rule.action, "deny");
+        assert_eq!(rule.description, "web");
+    }
+
+    /// Test the broader presets can be picked in the editor.
+    #[test]
+    fn test_broad_fields() {
+        let mut editor = make_editor();
+
+        focus(&mut editor, EditorField::DstNetwork);
+        editor.toggle();
+        assert_eq!(
+            editor.field_value(EditorField::DstNetwork),
+            "[x] 1.2.3.0/24"
+        );
+        editor.toggle();
+        assert_eq!(
+            editor.field_value(EditorField::DstNetwork),
+            "[x] 1.2.0.0/16"
+        );
+
+        focus(&mut editor, EditorField::ProcessPath);
+        editor.toggle();
+        focus(&mut editor, EditorField::ProcessDir);
+        editor.toggle();
+        assert!(!editor.presets.exact_ppath);
+        assert_eq!(
+            editor.field_value(EditorField::ProcessDir),
+            "[x] /usr/bin/*"
+        );
+
+        // An empty list path is dropped.
+        focus(&mut editor, EditorField::DomainsList);
+        editor.toggle();
+        editor.stop_editing();
+        assert!(editor.presets.lists_domains.is_none());
+        editor.toggle();
+        for c in "/etc/lists".chars() {
+            editor.push_char(c);
+        }
+        editor.stop_editing();
+
+        let operands: Vec<String> = editor
+            .operators()
+            .into_iter()
+            .map(|op| op.operand)
+            .collect();
+        assert_eq!(
+            operands,
+            vec!["process.path", "dest.ip", "dest.network", "lists.domains"]
+        );
+    }
+}
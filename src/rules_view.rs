@@ -0,0 +1,258 @@
+//! Sorting, filtering and decoding of daemon rules for the rules screen.
+
+use crate::constants;
+use crate::opensnitch_proto::pb;
+
+/// Column the rule list is sorted by.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RuleSortKey {
+    #[default]
+    Name,
+    Action,
+    Duration,
+    Enabled,
+}
+
+impl RuleSortKey {
+    /// Next sort key, wrapping around.
+    #[must_use]
+    pub fn next(self) -> Self {
+        match self {
+            RuleSortKey::Name => RuleSortKey::Action,
+            RuleSortKey::Action => RuleSortKey::Duration,
+            RuleSortKey::Duration => RuleSortKey::Enabled,
+            RuleSortKey::Enabled => RuleSortKey::Name,
+        }
+    }
+
+    #[must_use]
+    pub fn get_str(&self) -> &str {
+        match self {
+            RuleSortKey::Name => "name",
+            RuleSortKey::Action => "action",
+            RuleSortKey::Duration => "duration",
+            RuleSortKey::Enabled => "enabled",
+        }
+    }
+}
+
+/// User-controlled state of the rules screen.
+#[derive(Debug, Default)]
+pub struct RulesView {
+    /// Index into the visible (filtered and sorted) rules.
+    pub selected: usize,
+    pub sort_key: RuleSortKey,
+    /// Case-insensitive substring to match against rule name, action, duration and operators.
+    pub filter: String,
+    /// Whether keystrokes are currently going into the filter.
+    pub editing_filter: bool,
+    /// Whether the user is being asked to confirm deleting the selected rule.
+    pub confirm_delete: bool,
+    /// Outcome of the latest rule operation.
+    pub status: Option<String>,
+}
+
+impl RulesView {
+    /// Rules that match the filter, in sort order.
+    #[must_use]
+    pub fn visible<'a>(&self, rules: &'a [pb::Rule]) -> Vec<&'a pb::Rule> {
+        let filter = self.filter.to_lowercase();
+        let mut res: Vec<&pb::Rule> = rules
+            .iter()
+            .filter(|rule| filter.is_empty() || rule_search_text(rule).contains(&filter))
+            .collect();
+        match self.sort_key {
+            RuleSortKey::Name => res.sort_by(|a, b| a.name.cmp(&b.name)),
+            RuleSortKey::Action => res.sort_by(|a, b| a.action.cmp(&b.action)),
+            RuleSortKey::Duration => res.sort_by(|a, b| a.duration.cmp(&b.duration)),
+            // Enabled rules first.
+            RuleSortKey::Enabled => res.sort_by_key(|rule| std::cmp::Reverse(rule.enabled)),
+        }
+        res
+    }
+
+    /// The selected rule among `rules`, if any.
+    #[must_use]
+    pub fn selected_rule<'a>(&self, rules: &'a [pb::Rule]) -> Option<&'a pb::Rule> {
+        self.visible(rules).get(self.selected).copied()
+    }
+
+    /// Keep selection within bounds of the visible rules.
+    pub fn clamp_selection(&mut self, rules: &[pb::Rule]) {
+        self.selected = std::cmp::min(self.selected, self.visible(rules).len().saturating_sub(1));
+    }
+}
+
+/// Next action when cycling a rule through allow, deny and reject.
+#[must_use]
+pub fn next_rule_action(action: &str) -> constants::Action {
+    match constants::Action::new(action) {
+        Ok(constants::Action::Allow) => constants::Action::Deny,
+        Ok(constants::Action::Deny) => constants::Action::Reject,
+        _ => constants::Action::Allow,
+    }
+}
+
+/// Lowercased text a filter is matched against.
+fn rule_search_text(rule: &pb::Rule) -> String {
+    let operator = rule
+        .operator
+        .as_ref()
+        .map(operator_summary)
+        .unwrap_or_default();
+    format!(
+        "{} {} {} {}",
+        rule.name, rule.action, rule.duration, operator
+    )
+    .to_lowercase()
+}
+
+/// Children of a list operator. The daemon may send these either in `list` or only
+/// as a JSON blob in `data`.
+#[must_use]
+pub fn sub_operators(operator: &pb::Operator) -> Vec<pb::Operator> {
+    if operator.r#type != constants::RuleType::List.get_str() {
+        return Vec::default();
+    }
+    if !operator.list.is_empty() {
+        return operator.list.clone();
+    }
+    serde_json::from_str(&operator.data).unwrap_or_default()
+}
+
+/// One-line summary of an operator and its children, e.g.
+/// `process.path=/usr/bin/curl & dest.port=443`.
+#[must_use]
+pub fn operator_summary(operator: &pb::Operator) -> String {
+    let children = sub_operators(operator);
+    if children.is_empty() {
+        let comparator = if operator.r#type == constants::RuleType::Simple.get_str() {
+            String::from("=")
+        } else {
+            format!(" {} ", operator.r#type)
+        };
+        format!("{}{comparator}{}", operator.operand, operator.data)
+    } else {
+        children
+            .iter()
+            .map(operator_summary)
+            .collect::<Vec<String>>()
+            .join(" & ")
+    }
+}
+
+/// Indented lines describing an operator tree, one operator per line.
+#[must_use]
+pub fn operator_tree_lines(operator: &pb::Operator) -> Vec<String> {
+    let mut res = Vec::default();
+    push_operator_lines(operator, 0, &mut res);
+    res
+}
+
+fn push_operator_lines(operator: &pb::Operator, depth: usize, res: &mut Vec<String>) {
+    let indent = "  ".repeat(depth);
+    let sensitive = if operator.sensitive {
+        " (case sensitive)"
+    } else {
+        ""
+    };
+    let children = sub_operators(operator);
+    if children.is_empty() {
+        res.push(format!(
+            "{indent}[{}] {} = {}{sensitive}",
+            operator.r#type, operator.operand, operator.data
+        ));
+    } else {
+        res.push(format!("{indent}[{}] all of:{sensitive}", operator.r#type));
+        for child in &children {
+            push_operator_lines(child, depth + 1, res);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Helper to make a rule with a single simple operator.
+    fn make_rule(name: &str, action: &str, enabled: bool, dst_port: &str) -> pb::Rule {
+        pb::Rule {
+            name: name.to_string(),
+            action: action.to_string(),
+            duration: String::from("always"),
+            enabled,
+            operator: Some(pb::Operator {
+                r#type: String::from("simple"),
+                operand: String::from("dest.port"),
+                data: dst_port.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Test sorting and filtering.
+    #[test]
+    fn test_visible_rules() {
+        let rules = vec![
+            make_rule("b-rule", "deny", true, "22"),
+            make_rule("a-rule", "allow", false, "443"),
+            make_rule("c-rule", "allow", true, "53"),
+        ];
+        let mut view = RulesView::default();
+
+        let names = |view: &RulesView| -> Vec<String> {
+            view.visible(&rules)
+                .iter()
+                .map(|rule| rule.name.clone())
+                .collect()
+        };
+        assert_eq!(names(&view), vec!["a-rule", "b-rule", "c-rule"]);
+
+        view.sort_key = RuleSortKey::Action;
+        assert_eq!(names(&view), vec!["a-rule", "c-rule", "b-rule"]);
+
+        view.sort_key = RuleSortKey::Enabled;
+        assert_eq!(names(&view), vec!["b-rule", "c-rule", "a-rule"]);
+
+        // Filter on operator data, case-insensitively on name.
+        view.filter = String::from("dest.port=443");
+        assert_eq!(names(&view), vec!["a-rule"]);
+        view.filter = String::from("B-RULE");
+        assert_eq!(names(&view), vec!["b-rule"]);
+
+        view.selected = 5;
+        view.clamp_selection(&rules);
+        assert_eq!(view.selected, 0);
+        assert_eq!(
+            view.selected_rule(&rules).map(|rule| rule.name.as_str()),
+            Some("b-rule")
+        );
+    }
+
+    /// Test decoding a list operator that only carries its children as JSON.
+    #[test]
+    fn test_operator_tree_from_json() {
+        let operator = pb::Operator {
+            r#type: String::from("list"),
+            operand: String::from("list"),
+            data: String::from(
+                "[{\"type\":\"simple\",\"operand\":\"process.path\",\"data\":\"/usr/bin/curl\"},\
+                {\"type\":\"regexp\",\"operand\":\"dest.host\",\"data\":\"^example\\\\.com$\",\"sensitive\":true}]",
+            ),
+            ..Default::default()
+        };
+        assert_eq!(
+            operator_summary(&operator),
+            "process.path=/usr/bin/curl & dest.host regexp ^example\\.com$"
+        );
+        assert_eq!(
+            operator_tree_lines(&operator),
+            vec![
+                "[list] all of:",
+                "  [simple] process.path = /usr/bin/curl",
+                "  [regexp] dest.host = ^example\\.com$ (case sensitive)",
+            ]
+        );
+    }
+}
@@ -1,4 +1,5 @@
-use crate::opensnitch_proto::pb::Operator;
+use crate::opensnitch_proto::pb::{Connection, Event, Operator, Rule, StringInt};
+use serde::de::{Deserialize, Deserializer};
 use serde::ser::{Serialize, SerializeStruct, Serializer};
 
 impl Serialize for Operator {
@@ -16,6 +17,116 @@ impl Serialize for Operator {
     }
 }
 
+impl Serialize for Rule {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("Rule", 9)?;
+        state.serialize_field("created", &self.created)?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("description", &self.description)?;
+        state.serialize_field("enabled", &self.enabled)?;
+        state.serialize_field("precedence", &self.precedence)?;
+        state.serialize_field("nolog", &self.nolog)?;
+        state.serialize_field("action", &self.action)?;
+        state.serialize_field("duration", &self.duration)?;
+        state.serialize_field("operator", &self.operator)?;
+        state.end()
+    }
+}
+
+impl Serialize for StringInt {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("StringInt", 2)?;
+        state.serialize_field("key", &self.key)?;
+        state.serialize_field("value", &self.value)?;
+        state.end()
+    }
+}
+
+/// The process environment is left out, it routinely holds secrets that have
+/// no business being written to disk.
+impl Serialize for Connection {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("Connection", 13)?;
+        state.serialize_field("protocol", &self.protocol)?;
+        state.serialize_field("src_ip", &self.src_ip)?;
+        state.serialize_field("src_port", &self.src_port)?;
+        state.serialize_field("dst_ip", &self.dst_ip)?;
+        state.serialize_field("dst_host", &self.dst_host)?;
+        state.serialize_field("dst_port", &self.dst_port)?;
+        state.serialize_field("user_id", &self.user_id)?;
+        state.serialize_field("process_id", &self.process_id)?;
+        state.serialize_field("process_path", &self.process_path)?;
+        state.serialize_field("process_cwd", &self.process_cwd)?;
+        state.serialize_field("process_args", &self.process_args)?;
+        state.serialize_field("process_checksums", &self.process_checksums)?;
+        state.serialize_field("process_tree", &self.process_tree)?;
+        state.end()
+    }
+}
+
+impl Serialize for Event {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("Event", 4)?;
+        state.serialize_field("time", &self.time)?;
+        state.serialize_field("connection", &self.connection)?;
+        state.serialize_field("rule", &self.rule)?;
+        state.serialize_field("unixnano", &self.unixnano)?;
+        state.end()
+    }
+}
+
+/// Mirror of `Operator` as found in daemon rule JSON, e.g. the `data` of a list operator.
+/// Fields other than type and operand may be omitted by the daemon.
+#[derive(serde::Deserialize)]
+struct OperatorDef {
+    r#type: String,
+    operand: String,
+    #[serde(default)]
+    data: String,
+    #[serde(default)]
+    sensitive: bool,
+    #[serde(default)]
+    list: Option<Vec<OperatorDef>>,
+}
+
+impl From<OperatorDef> for Operator {
+    fn from(def: OperatorDef) -> Self {
+        Operator {
+            r#type: def.r#type,
+            operand: def.operand,
+            data: def.data,
+            sensitive: def.sensitive,
+            list: def
+                .list
+                .unwrap_or_default()
+                .into_iter()
+                .map(Operator::from)
+                .collect(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Operator {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        OperatorDef::deserialize(deserializer).map(Operator::from)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -37,4 +148,69 @@ mod tests {
         );
         assert_eq!(output, expected_output);
     }
+
+    /// Test Operator JSON deserialization, including daemon-style omitted and null fields.
+    #[test]
+    fn test_operator_deserialize() {
+        let input = "[{\"type\":\"simple\",\"operand\":\"dest.port\",\"data\":\"443\"},\
+            {\"type\":\"list\",\"operand\":\"list\",\"sensitive\":true,\"list\":null}]";
+        let output: Vec<Operator> =
+            serde_json::from_str(input).expect("failed deserialize json to struct");
+        let expected_output = vec![
+            Operator {
+                r#type: String::from("simple"),
+                operand: String::from("dest.port"),
+                data: String::from("443"),
+                sensitive: false,
+                list: Vec::default(),
+            },
+            Operator {
+                r#type: String::from("list"),
+                operand: String::from("list"),
+                data: String::default(),
+                sensitive: true,
+                list: Vec::default(),
+            },
+        ];
+        assert_eq!(output, expected_output);
+
+        // Round trip.
+        let json = serde_json::to_string(&expected_output).expect("failed serialize");
+        let round_trip: Vec<Operator> = serde_json::from_str(&json).expect("failed deserialize");
+        assert_eq!(round_trip, expected_output);
+    }
+
+    /// Test Event JSON serialization, with nested connection and rule but no environment.
+    #[test]
+    fn test_event_serialize() {
+        let input = Event {
+            time: String::from("2025-01-01 00:00:00"),
+            connection: Some(Connection {
+                protocol: String::from("tcp"),
+                dst_port: 443,
+                process_env: [(String::from("TOKEN"), String::from("secret"))].into(),
+                process_tree: vec![StringInt {
+                    key: String::from("/bin/bash"),
+                    value: 7,
+                }],
+                ..Default::default()
+            }),
+            rule: Some(Rule {
+                name: String::from("allow-curl"),
+                action: String::from("allow"),
+                ..Default::default()
+            }),
+            unixnano: 42,
+        };
+        let output = serde_json::to_value(&input).expect("failed serialize struct to json");
+        assert_eq!(output["time"], "2025-01-01 00:00:00");
+        assert_eq!(output["unixnano"], 42);
+        assert_eq!(output["connection"]["protocol"], "tcp");
+        assert_eq!(output["connection"]["dst_port"], 443);
+        assert_eq!(output["connection"]["process_tree"][0]["key"], "/bin/bash");
+        assert!(output["connection"].get("process_env").is_none());
+        assert_eq!(output["rule"]["name"], "allow-curl");
+        assert!(output["rule"]["operator"].is_null());
+        assert!(!serde_json::to_string(&input).unwrap().contains("secret"));
+    }
 }
@@ -1,35 +1,100 @@
+use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, SystemTime};
 
+use futures::TryStreamExt;
+use tokio::net::UnixListener;
 use tokio::time::timeout;
 use tonic::Streaming;
 use tonic::{Request, Response, Status, transport::Server};
 
 use crate::alert;
-use crate::event::{AppEvent, ConnectionEvent, Event, PingEvent};
+use crate::event::{
+    AppEvent, ConnectionEvent, Event, NotificationReplyEvent, NotificationsEvent, PingEvent,
+    RuleReply, SubscribeEvent,
+};
+use crate::node::node_key;
 use crate::opensnitch_proto::pb;
 use crate::opensnitch_proto::pb::ui_server::Ui;
 use crate::opensnitch_proto::pb::ui_server::UiServer;
+use crate::unix_socket::{self, UnixSocketOptions};
 use crate::{constants, opensnitch_json};
 
 use std::sync::Arc;
-use tokio::sync::{Mutex, mpsc};
-use tokio_stream::wrappers::ReceiverStream;
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::wrappers::{ReceiverStream, UnixListenerStream};
+
+/// Pending `AskRule` requests keyed by connection ID, each waiting on a rule from the app.
+type PendingRules = Arc<std::sync::Mutex<HashMap<u64, oneshot::Sender<pb::Rule>>>>;
+type PeerNames = Arc<std::sync::Mutex<HashMap<String, String>>>;
 
 #[derive(Debug)]
 pub struct OpenSnitchUIGrpcServer {
     /// Send events to app.
     server_to_app_event_sender: mpsc::UnboundedSender<Event>,
-    /// Handle for app to send notifications to the daemon connected to this server for notifications streaming.
-    app_to_server_notification_sender: Arc<Mutex<mpsc::Sender<Result<pb::Notification, Status>>>>,
-    /// Receiver for rules generated by app, to be sent to clients.
-    app_to_server_rule_receiver: Mutex<mpsc::Receiver<pb::Rule>>,
-    /// Default action to be passed to clients.
-    default_action: String,
+    /// `AskRule` requests awaiting a rule from the app.
+    pending_rules: PendingRules,
+    /// Source of unique IDs for trapped connections.
+    next_connection_id: AtomicU64,
+    /// Source of unique IDs for notifications streams.
+    next_stream_id: AtomicU64,
+    /// Names daemons reported on subscribing, by the address of their connection, until
+    /// their notifications stream closes.
+    peer_names: PeerNames,
+    /// Host domain socket peers are keyed by, as `unix:<socket path>`.
+    unix_host: String,
+    /// Settings merged into the config of clients when they subscribe.
+    config_overrides: opensnitch_json::ConfigOverrides,
     /// Duration to wait for app to provide a rule for client that's trapped a connection.
     connection_disposition_timeout: Duration,
-    /// Mutex to ensure only one `AskRule` request is active at a time.
-    askrule_lock: Mutex<()>,
+}
+
+/// Removes a pending `AskRule` entry when dropped, so that RPCs cancelled by the daemon
+/// don't leave stale senders behind.
+struct PendingRuleGuard<'a> {
+    pending_rules: &'a PendingRules,
+    id: u64,
+}
+
+impl Drop for PendingRuleGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut pending) = self.pending_rules.lock() {
+            pending.remove(&self.id);
+        }
+    }
+}
+
+/// Address of the connection a request came over, or its ID on a domain socket.
+fn peer_address<T>(request: &Request<T>) -> String {
+    if let Some(addr) = request.remote_addr() {
+        return addr.to_string();
+    }
+    request
+        .extensions()
+        .get::<unix_socket::UnixConnectInfo>()
+        .map_or_else(
+            || String::from("unix"),
+            |info| format!("unix#{}", info.connection),
+        )
+}
+
+impl OpenSnitchUIGrpcServer {
+    /// Key of the node that sent a request: the host it came from, named once the daemon
+    /// subscribed over the same connection.
+    fn node_of<T>(&self, request: &Request<T>) -> String {
+        let name = self
+            .peer_names
+            .lock()
+            .ok()
+            .and_then(|names| names.get(&peer_address(request)).cloned())
+            .unwrap_or_default();
+        let host = request
+            .remote_addr()
+            .map_or_else(|| self.unix_host.clone(), |addr| addr.ip().to_string());
+        node_key(&host, &name)
+    }
 }
 
 #[tonic::async_trait]
@@ -41,6 +106,7 @@ impl Ui for OpenSnitchUIGrpcServer {
         request: Request<pb::PingRequest>,
     ) -> Result<Response<pb::PingReply>, Status> {
         let event = PingEvent {
+            node: self.node_of(&request),
             peer: request.remote_addr(),
             stats: request.get_ref().stats.as_ref().unwrap().clone(),
         };
@@ -77,18 +143,23 @@ impl Ui for OpenSnitchUIGrpcServer {
         &self,
         request: Request<pb::Connection>,
     ) -> Result<Response<pb::Rule>, Status> {
-        // In theory, the current proto spec and OpenSnitch daemon design doesn't seem
-        // to permit opening concurrent `AskRule` requests.
-        // If this was to be supported in the future, we'd want to mix in some UID
-        // for request routing/identification as well.
-        let askrule_permit = self.askrule_lock.try_lock();
-        if askrule_permit.is_err() {
-            return Err(Status::resource_exhausted(
-                "Only one AskRule request permitted at a time",
-            ));
+        // The daemon may trap several connections concurrently (e.g. a browser opening many
+        // sockets at once), so each request gets its own ID and reply channel.
+        let id = self.next_connection_id.fetch_add(1, Ordering::Relaxed);
+        let (rule_tx, rule_rx) = oneshot::channel();
+        if let Ok(mut pending) = self.pending_rules.lock() {
+            pending.insert(id, rule_tx);
+        } else {
+            return Err(Status::internal("pending rules lock poisoned"));
         }
+        let _guard = PendingRuleGuard {
+            pending_rules: &self.pending_rules,
+            id,
+        };
 
         let connection = ConnectionEvent {
+            id,
+            node: self.node_of(&request),
             connection: request.get_ref().clone(),
             expiry_ts: SystemTime::now() + self.connection_disposition_timeout,
         };
@@ -96,12 +167,11 @@ impl Ui for OpenSnitchUIGrpcServer {
             .server_to_app_event_sender
             .send(Event::App(Box::new(AppEvent::AskRule(connection))));
 
-        let mut recv_lock = self.app_to_server_rule_receiver.lock().await;
-        let maybe_rule = timeout(self.connection_disposition_timeout, recv_lock.recv()).await;
+        let maybe_rule = timeout(self.connection_disposition_timeout, rule_rx).await;
         match maybe_rule {
             Ok(possibly_rule) => match possibly_rule {
-                Some(rule) => Ok(Response::new(rule)),
-                None => Err(Status::internal("sender somehow closed")),
+                Ok(rule) => Ok(Response::new(rule)),
+                Err(_) => Err(Status::internal("sender somehow closed")),
             },
             Err(err) => Err(Status::internal(format!("No rule created: {err}"))),
         }
@@ -111,55 +181,71 @@ impl Ui for OpenSnitchUIGrpcServer {
         &self,
         request: Request<pb::ClientConfig>,
     ) -> Result<Response<pb::ClientConfig>, Status> {
-        // Relfect back most of the rx'ed config.
-        // Be a little oversmart here and rewrite the config JSON blob with the only k-v
-        // the daemon really cares about - default action.
+        if let Ok(mut names) = self.peer_names.lock() {
+            names.insert(peer_address(&request), request.get_ref().name.clone());
+        }
+        // Reflect back the rx'ed config, with the settings the TUI overrides merged into the
+        // config JSON blob. A blob that can't be merged is reflected as is.
+        let node = self.node_of(&request);
         let mut reply = request.get_ref().clone();
-        let config = opensnitch_json::OpenSnitchDaemonConfig {
-            DefaultAction: self.default_action.clone(),
-        };
-        let maybe_config_json = serde_json::to_string(&config);
-        match maybe_config_json {
-            Ok(json) => {
-                reply.config = json;
-                Ok(Response::new(reply))
+        match opensnitch_json::merge_overrides(&reply.config, &self.config_overrides) {
+            Ok(json) => reply.config = json,
+            Err(err) => {
+                let _ =
+                    self.server_to_app_event_sender
+                        .send(Event::App(Box::new(AppEvent::Alert(alert::Alert {
+                            timestamp: std::time::SystemTime::now(),
+                            priority: alert::Priority::High,
+                            r#type: alert::Type::Warning,
+                            what: alert::What::Generic,
+                            msg: format!("Daemon {node} keeps its default action: {err}"),
+                        }))));
             }
-            Err(err) => Err(Status::internal(err.to_string())),
         }
+        let _ = self
+            .server_to_app_event_sender
+            .send(Event::App(Box::new(AppEvent::Subscribe(SubscribeEvent {
+                node,
+                peer: request.remote_addr(),
+                config: request.get_ref().clone(),
+            }))));
+        Ok(Response::new(reply))
     }
 
     async fn notifications(
         &self,
         request: Request<Streaming<pb::NotificationReply>>,
     ) -> Result<Response<Self::NotificationsStream>, Status> {
+        let node = self.node_of(&request);
+        let peer = peer_address(&request);
+        let peer_names = Arc::clone(&self.peer_names);
+        let stream = self.next_stream_id.fetch_add(1, Ordering::Relaxed);
         let mut in_stream = request.into_inner();
         let (app_to_server_notification_tx, app_to_server_notification_rx) = mpsc::channel(128);
         let tx = self.server_to_app_event_sender.clone();
 
-        // Grab a lock on the app to server notification sender, then swaparoo the new sender in.
-        // A pre-existing receiver on the old sender should also eventually close since its sender will have closed.
-        let mut sender_chan = self.app_to_server_notification_sender.lock().await;
-        *sender_chan = app_to_server_notification_tx;
+        // Hand the app a sender for this node's stream. A pre-existing stream for the same node
+        // gets replaced in the app, and its receiver closes once the old sender is dropped.
+        let _ = tx.send(Event::App(Box::new(AppEvent::NotificationsOpened(
+            NotificationsEvent {
+                node: node.clone(),
+                stream,
+                sender: app_to_server_notification_tx,
+            },
+        ))));
 
         tokio::spawn(async move {
             loop {
                 let stream_grpc_event = in_stream.message().await;
                 if let Ok(nominal_grpc_event) = stream_grpc_event {
-                    if let Some(notification) = nominal_grpc_event {
-                        match notification.code() {
-                            pb::NotificationReplyCode::Error => {
-                                // Redirect error notifications to the alerts channel
-                                let _ =
-                                    tx.send(Event::App(Box::new(AppEvent::Alert(alert::Alert {
-                                        timestamp: std::time::SystemTime::now(),
-                                        priority: alert::Priority::Medium,
-                                        r#type: alert::Type::Error,
-                                        what: alert::What::Generic,
-                                        msg: notification.data,
-                                    }))));
-                            }
-                            pb::NotificationReplyCode::Ok => {}
-                        }
+                    if let Some(reply) = nominal_grpc_event {
+                        // The app matches replies to the notifications it sent.
+                        let _ = tx.send(Event::App(Box::new(AppEvent::NotificationReply(
+                            NotificationReplyEvent {
+                                node: node.clone(),
+                                reply,
+                            },
+                        ))));
                     } else {
                         // Stream closed by peer
                         let _ = tx.send(Event::App(Box::new(AppEvent::Alert(alert::Alert {
@@ -167,8 +253,12 @@ impl Ui for OpenSnitchUIGrpcServer {
                             priority: alert::Priority::High,
                             r#type: alert::Type::Warning,
                             what: alert::What::Generic,
-                            msg: String::from("gRPC stream closed by daemon"),
+                            msg: format!("gRPC stream closed by daemon {node}"),
                         }))));
+                        let _ = tx.send(Event::App(Box::new(AppEvent::NotificationsClosed(
+                            node.clone(),
+                            stream,
+                        ))));
                         break;
                     }
                 } else {
@@ -178,11 +268,22 @@ impl Ui for OpenSnitchUIGrpcServer {
                         priority: alert::Priority::High,
                         r#type: alert::Type::Warning,
                         what: alert::What::Generic,
-                        msg: format!("gRPC error from daemon: {}", stream_grpc_event.unwrap_err()),
+                        msg: format!(
+                            "gRPC error from daemon {node}: {}",
+                            stream_grpc_event.unwrap_err()
+                        ),
                     }))));
+                    let _ = tx.send(Event::App(Box::new(AppEvent::NotificationsClosed(
+                        node.clone(),
+                        stream,
+                    ))));
                     break;
                 }
             }
+            // The daemon subscribes again once it reconnects, possibly under a new name.
+            if let Ok(mut names) = peer_names.lock() {
+                names.remove(&peer);
+            }
         });
 
         // Return a stream wrapper over the app to server notifications Receiver.
@@ -191,39 +292,410 @@ impl Ui for OpenSnitchUIGrpcServer {
     }
 }
 
+/// Address for the gRPC server to listen on.
+#[derive(Clone, Debug)]
+pub enum BindAddress {
+    Tcp(SocketAddr),
+    /// Socket path and the ownership/permissions to apply once bound.
+    Unix(PathBuf, UnixSocketOptions),
+}
+
+impl BindAddress {
+    /// Validates bind string and socket options.
+    /// * `bind`: `A.B.C.D:port`, `[A:B:C::D]:port` or `unix:///path/to.sock`.
+    /// * `socket_mode`, `socket_owner`: See `UnixSocketOptions::new`, only valid for domain sockets.
+    /// # Errors
+    /// Returns error if the address or socket options are invalid.
+    pub fn new(
+        bind: &str,
+        socket_mode: Option<&str>,
+        socket_owner: Option<&str>,
+    ) -> Result<BindAddress, String> {
+        if let Some(path) = bind.strip_prefix("unix:") {
+            // Accept both unix:///abs/path and unix:/abs/path like the daemon does.
+            let path = path.strip_prefix("//").unwrap_or(path);
+            if path.is_empty() {
+                return Err(format!("Missing socket path in bind address '{bind}'"));
+            }
+            let options = UnixSocketOptions::new(socket_mode, socket_owner)?;
+            return Ok(BindAddress::Unix(PathBuf::from(path), options));
+        }
+        if socket_mode.is_some() || socket_owner.is_some() {
+            return Err(String::from(
+                "Socket mode and owner only apply to Unix domain sockets",
+            ));
+        }
+        bind.parse::<SocketAddr>()
+            .map(BindAddress::Tcp)
+            .map_err(|err| format!("Error parsing bind address '{bind}' : {err}"))
+    }
+}
+
+impl std::fmt::Display for BindAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BindAddress::Tcp(addr) => write!(f, "{addr}"),
+            BindAddress::Unix(path, _) => write!(f, "unix://{}", path.display()),
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct OpenSnitchUIServer {}
 
 impl OpenSnitchUIServer {
-    /// Note for address: Unix domain sockets unsupported due to upstream "authority" handling bug
+    /// Spawn the gRPC server on the given address.
+    /// Domain sockets are bound before returning, replacing a stale socket file if needed.
+    /// # Errors
+    /// Returns an error if a domain socket can't be set up.
     pub fn spawn_and_run(
         &self,
-        address: SocketAddr,
+        address: &BindAddress,
         server_to_app_event_sender: mpsc::UnboundedSender<Event>,
-        app_to_server_notification_sender: &Arc<
-            Mutex<mpsc::Sender<Result<pb::Notification, Status>>>,
-        >,
-        app_to_server_rule_receiver: mpsc::Receiver<pb::Rule>,
+        mut app_to_server_rule_receiver: mpsc::Receiver<RuleReply>,
         default_action: constants::DefaultAction,
         connection_disposition_timeout: Duration,
-    ) {
+    ) -> std::io::Result<()> {
         let server_to_app_event_sender_handle = server_to_app_event_sender;
-        let notification_sender = Arc::clone(app_to_server_notification_sender);
-        let rule_receiver = Mutex::new(app_to_server_rule_receiver);
-        let default_action_str = String::from(default_action.get_str());
+        let pending_rules = PendingRules::default();
+        let config_overrides = opensnitch_json::ConfigOverrides {
+            default_action: String::from(default_action.get_str()),
+        };
+
+        let unix_host = match address {
+            BindAddress::Tcp(_) => String::from("unix"),
+            BindAddress::Unix(path, _) => format!("unix:{}", path.display()),
+        };
+
+        // Domain sockets are bound up front so that setup errors can be reported to the caller.
+        let unix_listener = match address {
+            BindAddress::Tcp(_) => None,
+            BindAddress::Unix(path, options) => {
+                unix_socket::remove_stale_socket(path)?;
+                let listener = UnixListener::bind(path)?;
+                options.apply(path)?;
+                Some(listener)
+            }
+        };
+        let tcp_address = match address {
+            BindAddress::Tcp(addr) => Some(*addr),
+            BindAddress::Unix(..) => None,
+        };
+
+        // Route rules generated by the app back to the `AskRule` request they answer.
+        let router_pending_rules = Arc::clone(&pending_rules);
+        tokio::spawn(async move {
+            while let Some(reply) = app_to_server_rule_receiver.recv().await {
+                let maybe_sender = match router_pending_rules.lock() {
+                    Ok(mut pending) => pending.remove(&reply.id),
+                    Err(_) => None,
+                };
+                // The request may have already timed out or been cancelled by the daemon.
+                if let Some(sender) = maybe_sender {
+                    let _ = sender.send(reply.rule);
+                }
+            }
+        });
+
         tokio::spawn(async move {
             let grpc_server = OpenSnitchUIGrpcServer {
                 server_to_app_event_sender: server_to_app_event_sender_handle,
-                app_to_server_notification_sender: notification_sender,
-                app_to_server_rule_receiver: rule_receiver,
-                default_action: default_action_str,
+                pending_rules,
+                next_connection_id: AtomicU64::new(0),
+                next_stream_id: AtomicU64::new(0),
+                peer_names: PeerNames::default(),
+                unix_host,
+                config_overrides,
                 connection_disposition_timeout,
-                askrule_lock: Mutex::default(),
             };
-            let _ = Server::builder()
-                .add_service(UiServer::new(grpc_server))
-                .serve(address)
-                .await;
+            let router = Server::builder().add_service(UiServer::new(grpc_server));
+            if let Some(listener) = unix_listener {
+                let incoming = UnixListenerStream::new(listener)
+                    .map_ok(unix_socket::AuthorityRewriteStream::new);
+                let _ = router.serve_with_incoming(incoming).await;
+            } else if let Some(addr) = tcp_address {
+                let _ = router.serve(addr).await;
+            }
         });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opensnitch_proto::pb::ui_client::UiClient;
+    use hyper_util::rt::TokioIo;
+    use tokio::net::UnixStream;
+    use tonic::transport::{Endpoint, Uri};
+
+    /// Unique socket path per test in the system temp dir.
+    fn temp_socket_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("opensnitch-tui-{name}-{}.sock", std::process::id()))
+    }
+
+    /// Spawn a server on the given domain socket and return the app's event receiver.
+    fn spawn_unix_server(path: &std::path::Path) -> mpsc::UnboundedReceiver<Event> {
+        let (event_sender, event_receiver) = mpsc::unbounded_channel();
+        let (_, rule_receiver) = mpsc::channel(1);
+        OpenSnitchUIServer::default()
+            .spawn_and_run(
+                &BindAddress::Unix(path.to_path_buf(), UnixSocketOptions::default()),
+                event_sender,
+                rule_receiver,
+                constants::DefaultAction::Deny,
+                Duration::from_secs(1),
+            )
+            .expect("spawn failed");
+        event_receiver
+    }
+
+    /// Connect a client to the server on the given domain socket.
+    async fn connect_unix(path: &std::path::Path) -> UiClient<tonic::transport::Channel> {
+        let connect_path = path.to_path_buf();
+        let channel = Endpoint::from_static("http://localhost")
+            .connect_with_connector(tower::service_fn(move |_: Uri| {
+                let connect_path = connect_path.clone();
+                async move {
+                    Ok::<_, std::io::Error>(TokioIo::new(UnixStream::connect(connect_path).await?))
+                }
+            }))
+            .await
+            .expect("connect failed");
+        UiClient::new(channel)
+    }
+
+    /// Test bind address parsing.
+    #[test]
+    fn test_bind_address() {
+        assert!(matches!(
+            BindAddress::new("127.0.0.1:50051", None, None),
+            Ok(BindAddress::Tcp(_))
+        ));
+        match BindAddress::new("unix:///tmp/osui.sock", Some("660"), None) {
+            Ok(BindAddress::Unix(path, options)) => {
+                assert_eq!(path, PathBuf::from("/tmp/osui.sock"));
+                assert_eq!(options.mode, Some(0o660));
+            }
+            other => panic!("unexpected bind address: {other:?}"),
+        }
+        assert!(matches!(
+            BindAddress::new("unix:/tmp/osui.sock", None, None),
+            Ok(BindAddress::Unix(..))
+        ));
+        assert!(BindAddress::new("unix://", None, None).is_err());
+        assert!(BindAddress::new("127.0.0.1:50051", Some("660"), None).is_err());
+        assert!(BindAddress::new("localhost", None, None).is_err());
+    }
+
+    /// Test that a local client can Ping the server over a domain socket, including when a
+    /// stale socket file was left behind by a previous run.
+    #[tokio::test]
+    async fn test_unix_socket_ping() {
+        let path = temp_socket_path("ping");
+        // Leave a stale socket file behind.
+        drop(std::os::unix::net::UnixListener::bind(&path).expect("bind failed"));
+        assert!(path.exists());
+
+        let mut event_receiver = spawn_unix_server(&path);
+        let mut client = connect_unix(&path).await;
+
+        let reply = client
+            .ping(pb::PingRequest {
+                id: 42,
+                stats: Some(pb::Statistics::default()),
+            })
+            .await
+            .expect("ping failed");
+        assert_eq!(reply.get_ref().id, 42);
+
+        match event_receiver.recv().await {
+            Some(Event::App(app_event)) => {
+                assert!(matches!(*app_event, AppEvent::Update(_)));
+            }
+            other => panic!("unexpected event: {other:?}"),
+        }
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    /// Test that subscribing daemons get their own config back with only the overridden
+    /// settings changed, and the app gets the config as the daemon shared it.
+    #[tokio::test]
+    async fn test_unix_socket_subscribe() {
+        let path = temp_socket_path("subscribe");
+        let mut event_receiver = spawn_unix_server(&path);
+        let mut client = connect_unix(&path).await;
+
+        let daemon_config = include_str!("testdata/daemon-config.json");
+        let config = pb::ClientConfig {
+            name: String::from("box"),
+            config: daemon_config.to_string(),
+            ..Default::default()
+        };
+        let reply = client
+            .subscribe(config.clone())
+            .await
+            .expect("subscribe failed");
+        let mut merged: serde_json::Value =
+            serde_json::from_str(&reply.get_ref().config).expect("bad json");
+        assert_eq!(merged["DefaultAction"], "deny");
+        let original: serde_json::Value = serde_json::from_str(daemon_config).expect("bad json");
+        merged["DefaultAction"] = original["DefaultAction"].clone();
+        assert_eq!(merged, original);
+
+        match event_receiver.recv().await {
+            Some(Event::App(app_event)) => match *app_event {
+                AppEvent::Subscribe(event) => assert_eq!(event.config, config),
+                other => panic!("unexpected event: {other:?}"),
+            },
+            other => panic!("unexpected event: {other:?}"),
+        }
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    /// Next event for the app, skipping alerts.
+    async fn recv_app_event(event_receiver: &mut mpsc::UnboundedReceiver<Event>) -> AppEvent {
+        loop {
+            match event_receiver.recv().await {
+                Some(Event::App(app_event)) if matches!(*app_event, AppEvent::Alert(_)) => {}
+                Some(Event::App(app_event)) => return *app_event,
+                other => panic!("unexpected event: {other:?}"),
+            }
+        }
+    }
+
+    /// Subscribe as `name` and ping, returning the keys of the node the app got each as.
+    async fn subscribe_and_ping(
+        client: &mut UiClient<tonic::transport::Channel>,
+        event_receiver: &mut mpsc::UnboundedReceiver<Event>,
+        name: &str,
+    ) -> (String, String) {
+        let config = pb::ClientConfig {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        client.subscribe(config).await.expect("subscribe failed");
+        let subscribed = match recv_app_event(event_receiver).await {
+            AppEvent::Subscribe(event) => event.node,
+            other => panic!("unexpected event: {other:?}"),
+        };
+        (subscribed, ping(client, event_receiver).await)
+    }
+
+    /// Ping, returning the key of the node the app got the ping as.
+    async fn ping(
+        client: &mut UiClient<tonic::transport::Channel>,
+        event_receiver: &mut mpsc::UnboundedReceiver<Event>,
+    ) -> String {
+        client
+            .ping(pb::PingRequest {
+                id: 1,
+                stats: Some(pb::Statistics::default()),
+            })
+            .await
+            .expect("ping failed");
+        match recv_app_event(event_receiver).await {
+            AppEvent::Update(event) => event.node,
+            other => panic!("unexpected event: {other:?}"),
+        }
+    }
+
+    /// Test that daemons on the same socket are told apart by name, and their pings land in
+    /// the node they subscribed as.
+    #[tokio::test]
+    async fn test_unix_socket_node_keys() {
+        let path = temp_socket_path("node-keys");
+        let mut event_receiver = spawn_unix_server(&path);
+        let host = format!("unix:{}", path.display());
+
+        let mut keys = Vec::new();
+        for name in ["box", "nas", "box"] {
+            let mut client = connect_unix(&path).await;
+            let (subscribed, pinged) =
+                subscribe_and_ping(&mut client, &mut event_receiver, name).await;
+            assert_eq!(pinged, subscribed);
+            keys.push(subscribed);
+        }
+        assert_eq!(
+            keys,
+            vec![
+                format!("box@{host}"),
+                format!("nas@{host}"),
+                format!("box@{host}")
+            ]
+        );
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    /// Test that a daemon reconnecting from a new port keeps its node, and that its name is
+    /// forgotten along with the connection once its notifications stream closes.
+    #[tokio::test]
+    async fn test_tcp_reconnect() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("no free port");
+        let (event_sender, mut event_receiver) = mpsc::unbounded_channel();
+        let (_, rule_receiver) = mpsc::channel(1);
+        OpenSnitchUIServer::default()
+            .spawn_and_run(
+                &BindAddress::Tcp(addr),
+                event_sender,
+                rule_receiver,
+                constants::DefaultAction::Deny,
+                Duration::from_secs(1),
+            )
+            .expect("spawn failed");
+        let connect = || async {
+            for _ in 0..50 {
+                if let Ok(client) = UiClient::connect(format!("http://{addr}")).await {
+                    return client;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            panic!("connect failed");
+        };
+
+        let mut client = connect().await;
+        let (subscribed, pinged) =
+            subscribe_and_ping(&mut client, &mut event_receiver, "box").await;
+        assert_eq!(subscribed, "box@127.0.0.1");
+        assert_eq!(pinged, subscribed);
+        let (reply_sender, reply_receiver) = mpsc::channel(1);
+        client
+            .notifications(ReceiverStream::new(reply_receiver))
+            .await
+            .expect("notifications failed");
+        assert!(matches!(
+            recv_app_event(&mut event_receiver).await,
+            AppEvent::NotificationsOpened(_)
+        ));
+        drop(reply_sender);
+        assert!(matches!(
+            recv_app_event(&mut event_receiver).await,
+            AppEvent::NotificationsClosed(..)
+        ));
+        assert_eq!(ping(&mut client, &mut event_receiver).await, "127.0.0.1");
+
+        let mut reconnected = connect().await;
+        let (subscribed, pinged) =
+            subscribe_and_ping(&mut reconnected, &mut event_receiver, "box").await;
+        assert_eq!(subscribed, "box@127.0.0.1");
+        assert_eq!(pinged, subscribed);
+    }
+
+    /// Test that the server refuses to clobber files that aren't sockets, or live sockets.
+    #[tokio::test]
+    async fn test_unix_socket_no_clobber() {
+        let path = temp_socket_path("clobber");
+        std::fs::write(&path, "not a socket").expect("write failed");
+        assert!(unix_socket::remove_stale_socket(&path).is_err());
+        std::fs::remove_file(&path).expect("remove failed");
+
+        let _live = std::os::unix::net::UnixListener::bind(&path).expect("bind failed");
+        assert!(unix_socket::remove_stale_socket(&path).is_err());
+        std::fs::remove_file(&path).expect("remove failed");
     }
 }
@@ -0,0 +1,245 @@
+//! Rolling history of a daemon's counters, turned into per-interval rates for sparklines.
+
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+
+use crate::opensnitch_proto::pb;
+
+/// How far back history is kept, the longest `HistoryWindow`.
+pub const HISTORY_RETENTION: Duration = Duration::from_hours(1);
+
+/// Cumulative counters of `pb::Statistics` that are charted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Counter {
+    Connections,
+    Accepted,
+    Dropped,
+    DnsResponses,
+    RuleHits,
+    RuleMisses,
+}
+
+impl Counter {
+    pub const ALL: [Counter; 6] = [
+        Counter::Connections,
+        Counter::Accepted,
+        Counter::Dropped,
+        Counter::DnsResponses,
+        Counter::RuleHits,
+        Counter::RuleMisses,
+    ];
+
+    #[must_use]
+    pub fn get_str(&self) -> &str {
+        match self {
+            Counter::Connections => "connections",
+            Counter::Accepted => "accepted",
+            Counter::Dropped => "dropped",
+            Counter::DnsResponses => "dns responses",
+            Counter::RuleHits => "rule hits",
+            Counter::RuleMisses => "rule misses",
+        }
+    }
+
+    /// Value of this counter in `stats`.
+    #[must_use]
+    pub fn value(self, stats: &pb::Statistics) -> u64 {
+        match self {
+            Counter::Connections => stats.connections,
+            Counter::Accepted => stats.accepted,
+            Counter::Dropped => stats.dropped,
+            Counter::DnsResponses => stats.dns_responses,
+            Counter::RuleHits => stats.rule_hits,
+            Counter::RuleMisses => stats.rule_misses,
+        }
+    }
+}
+
+/// Time span charted by the sparklines.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum HistoryWindow {
+    #[default]
+    OneMinute,
+    TenMinutes,
+    OneHour,
+}
+
+impl HistoryWindow {
+    /// Next window, wrapping around.
+    #[must_use]
+    pub fn next(self) -> Self {
+        match self {
+            HistoryWindow::OneMinute => HistoryWindow::TenMinutes,
+            HistoryWindow::TenMinutes => HistoryWindow::OneHour,
+            HistoryWindow::OneHour => HistoryWindow::OneMinute,
+        }
+    }
+
+    #[must_use]
+    pub fn get_str(&self) -> &str {
+        match self {
+            HistoryWindow::OneMinute => "1m",
+            HistoryWindow::TenMinutes => "10m",
+            HistoryWindow::OneHour => "1h",
+        }
+    }
+
+    #[must_use]
+    pub fn duration(self) -> Duration {
+        match self {
+            HistoryWindow::OneMinute => Duration::from_mins(1),
+            HistoryWindow::TenMinutes => Duration::from_mins(10),
+            HistoryWindow::OneHour => HISTORY_RETENTION,
+        }
+    }
+}
+
+/// Counters of a single ping.
+#[derive(Clone, Debug)]
+struct Sample {
+    ts: SystemTime,
+    values: [u64; Counter::ALL.len()],
+}
+
+/// Counter samples of a node over the last `HISTORY_RETENTION`, oldest first.
+#[derive(Debug, Default)]
+pub struct StatsHistory {
+    samples: VecDeque<Sample>,
+}
+
+impl StatsHistory {
+    /// Record the counters of a ping received at `now`, forgetting samples past retention.
+    pub fn push(&mut self, now: SystemTime, stats: &pb::Statistics) {
+        self.samples.push_back(Sample {
+            ts: now,
+            values: Counter::ALL.map(|counter| counter.value(stats)),
+        });
+        // Keep one sample beyond retention so the oldest interval still has a start.
+        while self.samples.len() > 2
+            && now
+                .duration_since(self.samples[1].ts)
+                .is_ok_and(|age| age > HISTORY_RETENTION)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Increase of `counter` over `window` ending at `now`, split into `buckets`
+    /// equal intervals, oldest first. Each increase between consecutive pings is
+    /// counted in the interval of the later ping. A counter going down, as when
+    /// the daemon restarts, counts as no increase.
+    #[must_use]
+    pub fn rates(
+        &self,
+        counter: Counter,
+        window: HistoryWindow,
+        now: SystemTime,
+        buckets: usize,
+    ) -> Vec<u64> {
+        let mut res = vec![0; buckets];
+        if buckets == 0 {
+            return res;
+        }
+        let idx = Counter::ALL
+            .iter()
+            .position(|c| *c == counter)
+            .unwrap_or_default();
+        let window = window.duration();
+        let bucket_nanos = window.as_nanos() / buckets as u128;
+        for (before, after) in self.samples.iter().zip(self.samples.iter().skip(1)) {
+            let Ok(age) = now.duration_since(after.ts) else {
+                continue;
+            };
+            if age >= window {
+                continue;
+            }
+            // Newest interval is the last bucket.
+            let from_end = usize::try_from(age.as_nanos() / bucket_nanos.max(1)).unwrap_or(0);
+            let bucket = buckets - 1 - std::cmp::min(from_end, buckets - 1);
+            res[bucket] += after.values[idx].saturating_sub(before.values[idx]);
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(accepted: u64, dropped: u64) -> pb::Statistics {
+        pb::Statistics {
+            accepted,
+            dropped,
+            ..Default::default()
+        }
+    }
+
+    /// Test rates are bucketed by ping time, ignore counter resets and respect the window.
+    #[test]
+    fn test_rates() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut history = StatsHistory::default();
+        history.push(at(0), &stats(10, 0));
+        history.push(at(10), &stats(15, 1));
+        history.push(at(40), &stats(25, 1));
+        // Daemon restarted.
+        history.push(at(50), &stats(2, 0));
+        history.push(at(59), &stats(5, 4));
+
+        let now = at(60);
+        assert_eq!(
+            history.rates(Counter::Accepted, HistoryWindow::OneMinute, now, 6),
+            vec![5, 0, 0, 10, 0, 3]
+        );
+        assert_eq!(
+            history.rates(Counter::Dropped, HistoryWindow::OneMinute, now, 2),
+            vec![1, 4]
+        );
+        assert_eq!(
+            history.rates(Counter::Accepted, HistoryWindow::OneHour, now, 1),
+            vec![18]
+        );
+        assert!(
+            history
+                .rates(Counter::Accepted, HistoryWindow::OneMinute, at(200), 3)
+                .iter()
+                .all(|rate| *rate == 0)
+        );
+        assert!(
+            history
+                .rates(Counter::Accepted, HistoryWindow::OneMinute, now, 0)
+                .is_empty()
+        );
+    }
+
+    /// Test samples older than retention are dropped, keeping one to start from.
+    #[test]
+    fn test_retention() {
+        let start = SystemTime::UNIX_EPOCH;
+        let mut history = StatsHistory::default();
+        for minute in 0..=90 {
+            history.push(start + Duration::from_mins(minute), &stats(minute, 0));
+        }
+        assert_eq!(history.len(), 62);
+        assert_eq!(
+            history.rates(
+                Counter::Accepted,
+                HistoryWindow::OneHour,
+                start + Duration::from_mins(90),
+                1
+            ),
+            vec![60]
+        );
+    }
+}
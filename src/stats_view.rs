@@ -0,0 +1,226 @@
+//! Top-N breakdowns of a daemon's statistics maps for the stats screen.
+
+use std::collections::HashMap;
+
+use crate::opensnitch_proto::pb;
+use crate::stats_history::HistoryWindow;
+
+/// The breakdown maps of `pb::Statistics`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StatsMap {
+    Proto,
+    Host,
+    Port,
+    Uid,
+    Executable,
+    Address,
+}
+
+impl StatsMap {
+    pub const ALL: [StatsMap; 6] = [
+        StatsMap::Proto,
+        StatsMap::Host,
+        StatsMap::Port,
+        StatsMap::Uid,
+        StatsMap::Executable,
+        StatsMap::Address,
+    ];
+
+    #[must_use]
+    pub fn get_str(&self) -> &str {
+        match self {
+            StatsMap::Proto => "protocol",
+            StatsMap::Host => "host",
+            StatsMap::Port => "port",
+            StatsMap::Uid => "user",
+            StatsMap::Executable => "executable",
+            StatsMap::Address => "address",
+        }
+    }
+
+    /// The map of this breakdown in `stats`.
+    #[must_use]
+    pub fn values(self, stats: &pb::Statistics) -> &HashMap<String, u64> {
+        match self {
+            StatsMap::Proto => &stats.by_proto,
+            StatsMap::Host => &stats.by_host,
+            StatsMap::Port => &stats.by_port,
+            StatsMap::Uid => &stats.by_uid,
+            StatsMap::Executable => &stats.by_executable,
+            StatsMap::Address => &stats.by_address,
+        }
+    }
+}
+
+/// Order of the entries in a breakdown table.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum StatsSortKey {
+    /// Most hits first.
+    #[default]
+    Hits,
+    /// Largest increase since the previous ping first.
+    Delta,
+    /// Alphabetical.
+    Key,
+}
+
+impl StatsSortKey {
+    /// Next sort key, wrapping around.
+    #[must_use]
+    pub fn next(self) -> Self {
+        match self {
+            StatsSortKey::Hits => StatsSortKey::Delta,
+            StatsSortKey::Delta => StatsSortKey::Key,
+            StatsSortKey::Key => StatsSortKey::Hits,
+        }
+    }
+
+    #[must_use]
+    pub fn get_str(&self) -> &str {
+        match self {
+            StatsSortKey::Hits => "hits",
+            StatsSortKey::Delta => "delta",
+            StatsSortKey::Key => "name",
+        }
+    }
+}
+
+/// A row of a breakdown table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatsEntry {
+    pub key: String,
+    pub hits: u64,
+    /// Change since the previous ping, if there was one.
+    pub delta: Option<i64>,
+}
+
+/// User-controlled state of the stats screen.
+#[derive(Debug, Default)]
+pub struct StatsView {
+    /// Index into `StatsMap::ALL` of the table keys apply to.
+    pub focus: usize,
+    /// Sort key per table, indexed like `StatsMap::ALL`.
+    pub sort_keys: [StatsSortKey; 6],
+    /// Time span of the rate sparklines.
+    pub window: HistoryWindow,
+}
+
+impl StatsView {
+    #[must_use]
+    pub fn focused(&self) -> StatsMap {
+        StatsMap::ALL[self.focus]
+    }
+
+    pub fn focus_next(&mut self) {
+        self.focus = (self.focus + 1) % StatsMap::ALL.len();
+    }
+
+    pub fn focus_prev(&mut self) {
+        self.focus = (self.focus + StatsMap::ALL.len() - 1) % StatsMap::ALL.len();
+    }
+
+    /// Cycle the sort key of the focused table.
+    pub fn cycle_sort(&mut self) {
+        self.sort_keys[self.focus] = self.sort_keys[self.focus].next();
+    }
+}
+
+/// The first `limit` entries of a breakdown, sorted, with deltas against `previous`.
+#[must_use]
+pub fn top_entries(
+    map: StatsMap,
+    stats: &pb::Statistics,
+    previous: Option<&pb::Statistics>,
+    sort_key: StatsSortKey,
+    limit: usize,
+) -> Vec<StatsEntry> {
+    let previous_values = previous.map(|previous| map.values(previous));
+    let mut res: Vec<StatsEntry> = map
+        .values(stats)
+        .iter()
+        .map(|(key, hits)| StatsEntry {
+            key: key.clone(),
+            hits: *hits,
+            delta: previous_values.and_then(|values| {
+                let before = values.get(key).copied().unwrap_or_default();
+                i64::try_from(i128::from(*hits) - i128::from(before)).ok()
+            }),
+        })
+        .collect();
+    // Ties are broken by key so the order is stable across pings.
+    match sort_key {
+        StatsSortKey::Hits => {
+            res.sort_by(|a, b| b.hits.cmp(&a.hits).then_with(|| a.key.cmp(&b.key)));
+        }
+        StatsSortKey::Delta => {
+            res.sort_by(|a, b| b.delta.cmp(&a.delta).then_with(|| a.key.cmp(&b.key)));
+        }
+        StatsSortKey::Key => res.sort_by(|a, b| a.key.cmp(&b.key)),
+    }
+    res.truncate(limit);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test sorting, truncation and deltas of a breakdown.
+    #[test]
+    fn test_top_entries() {
+        let previous = pb::Statistics {
+            by_host: [(String::from("a.com"), 5), (String::from("b.com"), 1)].into(),
+            ..Default::default()
+        };
+        let stats = pb::Statistics {
+            by_host: [
+                (String::from("a.com"), 6),
+                (String::from("b.com"), 5),
+                (String::from("c.com"), 2),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        let keys = |entries: Vec<StatsEntry>| -> Vec<String> {
+            entries.into_iter().map(|entry| entry.key).collect()
+        };
+
+        let entries = top_entries(
+            StatsMap::Host,
+            &stats,
+            Some(&previous),
+            StatsSortKey::Hits,
+            2,
+        );
+        assert_eq!(
+            entries,
+            vec![
+                StatsEntry {
+                    key: String::from("a.com"),
+                    hits: 6,
+                    delta: Some(1),
+                },
+                StatsEntry {
+                    key: String::from("b.com"),
+                    hits: 5,
+                    delta: Some(4),
+                },
+            ]
+        );
+
+        let entries = top_entries(
+            StatsMap::Host,
+            &stats,
+            Some(&previous),
+            StatsSortKey::Delta,
+            10,
+        );
+        assert_eq!(keys(entries), vec!["b.com", "c.com", "a.com"]);
+
+        let entries = top_entries(StatsMap::Host, &stats, None, StatsSortKey::Key, 10);
+        assert!(entries.iter().all(|entry| entry.delta.is_none()));
+        assert_eq!(keys(entries), vec!["a.com", "b.com", "c.com"]);
+
+        assert!(top_entries(StatsMap::Port, &stats, None, StatsSortKey::Hits, 10).is_empty());
+    }
+}
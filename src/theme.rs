@@ -0,0 +1,360 @@
+//! Colors of the TUI: built-in themes, with per-element overrides from the `[colors]`
+//! config table.
+
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::str::FromStr;
+
+use ratatui::style::{Color, Modifier, Style};
+
+use crate::alert::Priority;
+
+/// Built-in themes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ThemeName {
+    Dark,
+    Light,
+    HighContrast,
+    /// No colors at all, for terminals or users that don't want them. Default if `NO_COLOR` is set.
+    NoColor,
+}
+
+impl ThemeName {
+    pub const ALL: [ThemeName; 4] = [
+        ThemeName::Dark,
+        ThemeName::Light,
+        ThemeName::HighContrast,
+        ThemeName::NoColor,
+    ];
+
+    #[must_use]
+    pub fn get_str(&self) -> &str {
+        match self {
+            ThemeName::Dark => "dark",
+            ThemeName::Light => "light",
+            ThemeName::HighContrast => "high-contrast",
+            ThemeName::NoColor => "no-color",
+        }
+    }
+
+    /// Theme used unless one is configured: no colors if the `NO_COLOR` environment
+    /// variable is set and not empty, see <https://no-color.org>.
+    #[must_use]
+    pub fn from_env() -> ThemeName {
+        ThemeName::from_no_color(std::env::var_os("NO_COLOR").as_deref())
+    }
+
+    fn from_no_color(no_color: Option<&OsStr>) -> ThemeName {
+        match no_color {
+            Some(value) if !value.is_empty() => ThemeName::NoColor,
+            _ => ThemeName::Dark,
+        }
+    }
+}
+
+impl FromStr for ThemeName {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ThemeName, Self::Err> {
+        ThemeName::ALL
+            .into_iter()
+            .find(|name| name.get_str() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown theme \"{s}\", expected one of: {}",
+                    ThemeName::ALL
+                        .map(|name| name.get_str().to_string())
+                        .join(", ")
+                )
+            })
+    }
+}
+
+/// Styles of each themed element of the TUI.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    /// Text, borders and background of panels.
+    pub base: Style,
+    /// Key hints and other secondary text.
+    pub text: Style,
+    /// Selected row of a list or table.
+    pub highlight: Style,
+    /// Focused panels, popups, status lines and rules with an operation in flight.
+    pub accent: Style,
+    /// Selected pending connection.
+    pub pending: Style,
+    /// Allowed connections and accepted counts.
+    pub allow: Style,
+    /// Denied connections and dropped counts.
+    pub deny: Style,
+    /// Disconnected nodes, errors and confirmations of destructive operations.
+    pub error: Style,
+    /// Key of a footer control.
+    pub footer_key: Style,
+    /// Label of a footer control.
+    pub footer_label: Style,
+    /// Alerts by priority.
+    pub alert_low: Style,
+    pub alert_medium: Style,
+    pub alert_high: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::builtin(ThemeName::Dark)
+    }
+}
+
+impl Theme {
+    #[must_use]
+    pub fn builtin(name: ThemeName) -> Theme {
+        let fg = |color: Color| Style::default().fg(color);
+        let fg_bg = |fg: Color, bg: Color| Style::default().fg(fg).bg(bg);
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let reversed = Style::default().add_modifier(Modifier::REVERSED);
+        match name {
+            ThemeName::Dark => Theme {
+                base: fg_bg(Color::Cyan, Color::Black),
+                text: fg_bg(Color::White, Color::Black),
+                highlight: fg_bg(Color::Black, Color::Cyan),
+                accent: fg(Color::Yellow),
+                pending: fg_bg(Color::Black, Color::Yellow),
+                allow: fg(Color::Green),
+                deny: fg(Color::LightRed),
+                error: fg(Color::Red),
+                footer_key: fg_bg(Color::White, Color::Black),
+                footer_label: fg_bg(Color::Black, Color::Gray),
+                alert_low: fg(Color::Cyan),
+                alert_medium: fg(Color::Yellow),
+                alert_high: fg(Color::LightRed),
+            },
+            ThemeName::Light => Theme {
+                base: fg_bg(Color::Black, Color::White),
+                text: fg_bg(Color::DarkGray, Color::White),
+                highlight: fg_bg(Color::White, Color::Blue),
+                accent: fg(Color::Magenta),
+                pending: fg_bg(Color::Black, Color::LightYellow),
+                allow: fg(Color::Green),
+                deny: fg(Color::Red),
+                error: fg(Color::Red),
+                footer_key: fg_bg(Color::Black, Color::White),
+                footer_label: fg_bg(Color::White, Color::DarkGray),
+                alert_low: fg(Color::Blue),
+                alert_medium: fg(Color::Magenta),
+                alert_high: fg(Color::Red),
+            },
+            ThemeName::HighContrast => Theme {
+                base: fg_bg(Color::White, Color::Black),
+                text: fg_bg(Color::White, Color::Black).add_modifier(Modifier::BOLD),
+                highlight: fg_bg(Color::Black, Color::White),
+                accent: fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+                pending: fg_bg(Color::Black, Color::LightYellow),
+                allow: fg(Color::LightGreen).add_modifier(Modifier::BOLD),
+                deny: fg(Color::LightRed).add_modifier(Modifier::BOLD),
+                error: fg(Color::LightRed).add_modifier(Modifier::BOLD),
+                footer_key: fg_bg(Color::White, Color::Black).add_modifier(Modifier::BOLD),
+                footer_label: fg_bg(Color::Black, Color::White),
+                alert_low: fg(Color::White),
+                alert_medium: fg(Color::LightYellow),
+                alert_high: fg(Color::LightRed).add_modifier(Modifier::BOLD),
+            },
+            ThemeName::NoColor => Theme {
+                base: Style::default(),
+                text: Style::default(),
+                highlight: reversed,
+                accent: bold,
+                pending: reversed.add_modifier(Modifier::BOLD),
+                allow: Style::default(),
+                deny: bold,
+                error: bold,
+                footer_key: Style::default(),
+                footer_label: reversed,
+                alert_low: Style::default(),
+                alert_medium: Style::default(),
+                alert_high: bold,
+            },
+        }
+    }
+
+    /// Built-in theme `name`, or the one from the environment if unset, with `overrides`
+    /// of element name to style applied.
+    /// # Errors
+    /// With one line per unknown element or unparsable style.
+    pub fn new(name: Option<&str>, overrides: &BTreeMap<String, String>) -> Result<Theme, String> {
+        let name = name.map_or(Ok(ThemeName::from_env()), ThemeName::from_str)?;
+        let mut theme = Theme::builtin(name);
+        let errors: Vec<String> = overrides
+            .iter()
+            .filter_map(|(element, spec)| {
+                theme
+                    .set(element, spec)
+                    .err()
+                    .map(|err| format!("{element}: {err}"))
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(theme)
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    /// Background of the base style alone, for widgets drawn over a styled block.
+    #[must_use]
+    pub fn background(&self) -> Style {
+        Style {
+            bg: self.base.bg,
+            ..Style::default()
+        }
+    }
+
+    /// Style of an alert of the given priority.
+    #[must_use]
+    pub fn alert(&self, priority: &Priority) -> Style {
+        match priority {
+            Priority::Low => self.alert_low,
+            Priority::Medium => self.alert_medium,
+            Priority::High => self.alert_high,
+        }
+    }
+
+    /// Replace the style of an element.
+    /// # Errors
+    /// If the element is unknown or the style can't be parsed.
+    pub fn set(&mut self, element: &str, spec: &str) -> Result<(), String> {
+        let style = parse_style(spec)?;
+        let slot = match element {
+            "base" => &mut self.base,
+            "text" => &mut self.text,
+            "highlight" => &mut self.highlight,
+            "accent" => &mut self.accent,
+            "pending" => &mut self.pending,
+            "allow" => &mut self.allow,
+            "deny" => &mut self.deny,
+            "error" => &mut self.error,
+            "footer_key" => &mut self.footer_key,
+            "footer_label" => &mut self.footer_label,
+            "alert_low" => &mut self.alert_low,
+            "alert_medium" => &mut self.alert_medium,
+            "alert_high" => &mut self.alert_high,
+            _ => return Err(String::from("unknown element")),
+        };
+        *slot = style;
+        Ok(())
+    }
+}
+
+/// Parse a style like `green`, `black on yellow`, `bold #ff8800` or `reversed`.
+/// # Errors
+/// If a word isn't a color or modifier, or a color is missing after `on`.
+pub fn parse_style(spec: &str) -> Result<Style, String> {
+    let mut style = Style::default();
+    let mut words = spec.split_whitespace();
+    let mut empty = true;
+    while let Some(word) = words.next() {
+        empty = false;
+        let parse_color =
+            |word: &str| Color::from_str(word).map_err(|_| format!("unknown color \"{word}\""));
+        match word.to_lowercase().as_str() {
+            "bold" => style = style.add_modifier(Modifier::BOLD),
+            "dim" => style = style.add_modifier(Modifier::DIM),
+            "italic" => style = style.add_modifier(Modifier::ITALIC),
+            "underlined" => style = style.add_modifier(Modifier::UNDERLINED),
+            "reversed" => style = style.add_modifier(Modifier::REVERSED),
+            "on" => {
+                let bg = words
+                    .next()
+                    .ok_or_else(|| format!("missing background color in \"{spec}\""))?;
+                style = style.bg(parse_color(bg)?);
+            }
+            _ => style = style.fg(parse_color(word)?),
+        }
+    }
+    if empty {
+        return Err(String::from("empty style"));
+    }
+    Ok(style)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test style parsing.
+    #[test]
+    fn test_parse_style() {
+        assert_eq!(parse_style("green"), Ok(Style::default().fg(Color::Green)));
+        assert_eq!(
+            parse_style("bold black on light-yellow"),
+            Ok(Style::default()
+                .fg(Color::Black)
+                .bg(Color::LightYellow)
+                .add_modifier(Modifier::BOLD))
+        );
+        assert_eq!(
+            parse_style("#ff8800"),
+            Ok(Style::default().fg(Color::Rgb(0xff, 0x88, 0x00)))
+        );
+        assert_eq!(
+            parse_style("reversed"),
+            Ok(Style::default().add_modifier(Modifier::REVERSED))
+        );
+        assert!(parse_style("black on").is_err());
+        assert!(parse_style("chartreuse").is_err());
+        assert!(parse_style(" ").is_err());
+    }
+
+    /// Test `NO_COLOR` turns colors off only when set and not empty.
+    #[test]
+    fn test_no_color() {
+        assert_eq!(ThemeName::from_no_color(None), ThemeName::Dark);
+        assert_eq!(
+            ThemeName::from_no_color(Some(OsStr::new(""))),
+            ThemeName::Dark
+        );
+        assert_eq!(
+            ThemeName::from_no_color(Some(OsStr::new("1"))),
+            ThemeName::NoColor
+        );
+        let theme = Theme::builtin(ThemeName::NoColor);
+        assert_eq!(theme.background(), Style::default());
+        assert_eq!(
+            Theme::default().background(),
+            Style::default().bg(Color::Black)
+        );
+    }
+
+    /// Test themes are picked by name, with overrides applied and bad ones all reported.
+    #[test]
+    fn test_new() {
+        let overrides = |pairs: &[(&str, &str)]| -> BTreeMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+                .collect()
+        };
+        let theme =
+            Theme::new(Some("no-color"), &overrides(&[("allow", "green")])).expect("new failed");
+        assert_eq!(theme.allow, Style::default().fg(Color::Green));
+        assert_eq!(theme.base, Style::default());
+        assert_eq!(
+            theme.highlight,
+            Style::default().add_modifier(Modifier::REVERSED)
+        );
+
+        assert!(
+            Theme::new(Some("solarized"), &BTreeMap::new())
+                .expect_err("new succeeded")
+                .starts_with("unknown theme \"solarized\"")
+        );
+        let err = Theme::new(
+            Some("dark"),
+            &overrides(&[("allow", "greenish"), ("borders", "red")]),
+        )
+        .expect_err("new succeeded");
+        assert_eq!(
+            err,
+            "allow: unknown color \"greenish\"\nborders: unknown element"
+        );
+    }
+}
@@ -1,12 +1,26 @@
 use ratatui::{
     buffer::Buffer,
     layout::{Alignment, Constraint, Layout, Rect},
-    style::{Color, Style, Stylize},
+    style::{Style, Stylize},
     text::{Line, Span},
-    widgets::{Block, BorderType, List, ListItem, Paragraph, StatefulWidget, Widget},
+    widgets::{
+        Block, BorderType, Borders, Clear, List, ListItem, ListState, Padding, Paragraph, Row,
+        Sparkline, StatefulWidget, Table, TableState, Widget, Wrap,
+    },
 };
 
 use crate::app::{TuiMutState, TuiScreen, TuiState};
+use crate::config_editor::{ConfigEditor, ConfigField};
+use crate::constants;
+use crate::firewall_view::{self, FirewallRow};
+use crate::history::{self, RecordKind};
+use crate::keymap::KeyAction;
+use crate::notification::DaemonCommand;
+use crate::opensnitch_proto::pb;
+use crate::rule_editor::{EditorField, RuleEditor};
+use crate::rules_view;
+use crate::stats_history::Counter;
+use crate::stats_view::{self, StatsMap};
 
 impl StatefulWidget for &TuiState {
     type State = TuiMutState;
@@ -16,9 +30,38 @@ impl StatefulWidget for &TuiState {
         match self.current_screen {
             TuiScreen::Main => {
                 self.render_main_screen(area, buf, state);
+                if let Some(editor) = &self.rule_editor {
+                    self.render_rule_editor(editor, area, buf);
+                }
+                if let Some((key, command)) = &self.confirm_command {
+                    self.render_command_confirmation(key, *command, area, buf);
+                }
             }
             TuiScreen::Help => {
-                TuiState::render_help_screen(area, buf, state);
+                self.render_help_screen(area, buf);
+            }
+            TuiScreen::Rules => {
+                self.render_rules_screen(area, buf);
+            }
+            TuiScreen::ConnectionDetails => {
+                self.render_details_screen(area, buf, state);
+            }
+            TuiScreen::Stats => {
+                self.render_stats_screen(area, buf);
+            }
+            TuiScreen::EventLog => {
+                self.render_event_log_screen(area, buf);
+            }
+            TuiScreen::History => {
+                self.render_history_screen(area, buf);
+            }
+            TuiScreen::Firewall => {
+                self.render_firewall_screen(area, buf);
+            }
+            TuiScreen::DaemonConfig => {
+                if let Some(editor) = &self.config_editor {
+                    self.render_config_screen(editor, area, buf);
+                }
             }
         }
     }
@@ -33,45 +76,275 @@ impl TuiState {
         // Fill greediness prioritizes connections > controls > stats > alerts
         let areas = Layout::vertical([
             Constraint::Max(6),      // Stats
-            Constraint::Min(9),      // Connections
+            Constraint::Min(11),     // Connections
             Constraint::Fill(10000), // Alerts - high fill ratio prevents Mins from growing
             Constraint::Min(1),      // Controls
         ])
         .split(area);
-        let stats_title = match self.peer {
-            Some(addr) => format!(" OpenSnitch ({addr}) "),
-            _ => String::from(" OpenSnitch "),
+        self.render_nodes_panel(areas[0], buf, now);
+
+        // Connection controls
+        self.render_connection_panel(areas[1], buf, state, now);
+
+        // Alerts list
+        let alerts_block = Block::bordered()
+            .title(format!(" Alerts ({}) ", self.current_alerts.len()))
+            .title_alignment(Alignment::Center)
+            .border_type(BorderType::Rounded);
+
+        // We want to render the alert list from some stateful head index,
+        // so get an iterator and skip forward to that head.
+        let items_iter = self
+            .current_alerts
+            .iter()
+            .skip(self.alert_list_render_offset);
+
+        let items: Vec<ListItem> = items_iter
+            .map(|alert| {
+                let maybe_age = now.duration_since(alert.timestamp);
+                let age_s: u64 = match maybe_age {
+                    Ok(age) => age.as_secs(),
+                    Err(_) => 0, // Just default to 0s in case time goes backwards
+                };
+                let alert_text = format!(
+                    "{}s ago : {:?} : {:?} : {:?} : {}\n",
+                    age_s, alert.r#type, alert.priority, alert.what, alert.msg,
+                );
+                ListItem::from(alert_text).style(self.theme.alert(&alert.priority))
+            })
+            .collect();
+
+        // Create a List from all list items
+        let list = List::new(items).block(alerts_block).style(self.theme.base);
+        Widget::render(list, areas[2], buf);
+
+        // Controls footer
+        let mut controls_spans = Vec::new();
+        for control in &self.controls {
+            let (key, label) = self.control_strs(*control);
+            controls_spans.push(Span::styled(key, self.theme.footer_key));
+            controls_spans.push(Span::styled(label, self.theme.footer_label));
+        }
+        let controls_text = vec![controls_spans.into()];
+
+        // Important: Left alignment makes it super easy to calculate whether
+        // mouse clicks occurred over a control "button"
+        let controls_paragraph = Paragraph::new(controls_text)
+            .style(self.theme.background())
+            .alignment(Alignment::Left);
+
+        controls_paragraph.render(areas[3], buf);
+        state.controls_area = areas[3];
+    }
+
+    /// Renders the rule editor as a popup over the main screen.
+    fn render_rule_editor(&self, editor: &RuleEditor, area: Rect, buf: &mut Buffer) {
+        let popup_area = area.inner(ratatui::layout::Margin {
+            horizontal: area.width / 10,
+            vertical: area.height / 8,
+        });
+        Clear.render(popup_area, buf);
+        let hints = if editor.editing_text {
+            " Enter/ESC: done typing "
+        } else {
+            " Up/Down: select | Space/Enter: toggle | S: send | ESC: cancel "
+        };
+        let editor_block = Block::bordered()
+            .title(" Edit Rule ")
+            .title_alignment(Alignment::Center)
+            .title_bottom(Line::from(hints).alignment(Alignment::Right))
+            .border_type(BorderType::Rounded)
+            .style(self.theme.background().patch(self.theme.accent));
+        let inner_area = editor_block.inner(popup_area);
+        editor_block.render(popup_area, buf);
+
+        let areas =
+            Layout::horizontal([Constraint::Fill(3), Constraint::Fill(2)]).split(inner_area);
+
+        let mut field_lines: Vec<Line> = EditorField::ALL
+            .iter()
+            .enumerate()
+            .map(|(idx, field)| {
+                let cursor = if editor.editing_text && idx == editor.focus {
+                    "_"
+                } else {
+                    ""
+                };
+                let line = Line::from(format!(
+                    "{:<13}{}{cursor}",
+                    field.get_str(),
+                    editor.field_value(*field)
+                ));
+                if idx == editor.focus {
+                    line.style(self.theme.pending)
+                } else {
+                    line
+                }
+            })
+            .collect();
+        if let Some(status) = &editor.status {
+            field_lines.push(Line::default());
+            field_lines.push(Line::from(status.as_str()).style(self.theme.error));
+        }
+        Paragraph::new(field_lines)
+            .wrap(Wrap { trim: false })
+            .render(areas[0], buf);
+
+        let preview_block = Block::default()
+            .borders(Borders::LEFT)
+            .title(" Operators ")
+            .padding(Padding::horizontal(1));
+        Paragraph::new(editor.preview())
+            .block(preview_block)
+            .style(self.theme.base)
+            .render(areas[1], buf);
+    }
+
+    /// Renders a popup asking to confirm a command for the node keyed `key`.
+    fn render_command_confirmation(
+        &self,
+        key: &str,
+        command: DaemonCommand,
+        area: Rect,
+        buf: &mut Buffer,
+    ) {
+        let node_name = match self.nodes.get(key) {
+            Some(node) => node.display_name(key),
+            None => key.to_string(),
+        };
+        let consequence = match command {
+            DaemonCommand::EnableInterception => "Connections will be intercepted again.",
+            DaemonCommand::DisableInterception => {
+                "Connections will go through unchecked until resumed."
+            }
+            DaemonCommand::EnableFirewall => "The system firewall rules will be loaded again.",
+            DaemonCommand::DisableFirewall => "The system firewall rules will be unloaded.",
+            DaemonCommand::Stop => "The daemon will exit, and has to be restarted on its host.",
+        };
+        let width = std::cmp::min(area.width, 76);
+        let height = std::cmp::min(area.height, 3);
+        let popup_area = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        Clear.render(popup_area, buf);
+        let mut question = command.describe().to_string();
+        if let Some(first) = question.get_mut(..1) {
+            first.make_ascii_uppercase();
+        }
+        let block = Block::bordered()
+            .title(format!(" {question} on {node_name}? (y/N) "))
+            .title_alignment(Alignment::Center)
+            .border_type(BorderType::Rounded)
+            .style(self.theme.background().patch(match command {
+                DaemonCommand::EnableInterception | DaemonCommand::EnableFirewall => {
+                    self.theme.accent
+                }
+                _ => self.theme.error,
+            }));
+        Paragraph::new(consequence)
+            .block(block)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true })
+            .render(popup_area, buf);
+    }
+
+    /// Renders the list of connected nodes and stats of the selected node.
+    fn render_nodes_panel(&self, area: Rect, buf: &mut Buffer, now: std::time::SystemTime) {
+        let nodes_areas =
+            Layout::horizontal([Constraint::Length(30), Constraint::Fill(1)]).split(area);
+
+        let selected_key = self.nodes.selected_key();
+        let node_items: Vec<ListItem> = self
+            .nodes
+            .iter()
+            .map(|(key, node)| {
+                let item = ListItem::from(node.display_name(key));
+                match (Some(key.as_str()) == selected_key, node.disconnected) {
+                    (true, false) => item.style(self.theme.highlight),
+                    (true, true) => item.style(self.theme.highlight.patch(self.theme.error)),
+                    (false, true) => item.style(self.theme.error),
+                    (false, false) => item,
+                }
+            })
+            .collect();
+        let nodes_block = Block::bordered()
+            .title(format!(" Nodes ({}) ", self.nodes.len()))
+            .title_alignment(Alignment::Center)
+            .border_type(BorderType::Rounded);
+        let nodes_list = List::new(node_items)
+            .block(nodes_block)
+            .style(self.theme.base);
+        Widget::render(nodes_list, nodes_areas[0], buf);
+
+        let (stats_title, stats_border) = match self.nodes.selected() {
+            Some((key, node)) if node.disconnected => {
+                let since = node
+                    .last_ping
+                    .and_then(|last_ping| now.duration_since(last_ping).ok())
+                    .map_or(String::default(), |since| {
+                        format!(", last ping {}s ago", since.as_secs())
+                    });
+                (
+                    format!(
+                        " OpenSnitch ({}) - DISCONNECTED{since} ",
+                        node.display_name(key)
+                    ),
+                    self.theme.error,
+                )
+            }
+            Some((key, node)) => (
+                format!(" OpenSnitch ({}) ", node.display_name(key)),
+                self.theme.base,
+            ),
+            _ => (String::from(" OpenSnitch "), self.theme.base),
         };
         let stats_block = Block::bordered()
             .title(stats_title)
             .title_alignment(Alignment::Center)
-            .border_type(BorderType::Rounded);
+            .border_type(BorderType::Rounded)
+            .border_style(stats_border);
 
         let stats_text = self.format_stats_panel();
         let stats_paragraph = Paragraph::new(stats_text)
             .block(stats_block)
-            .fg(Color::Cyan)
-            .bg(Color::Black);
+            .style(self.theme.base);
 
-        stats_paragraph.render(areas[0], buf);
+        stats_paragraph.render(nodes_areas[1], buf);
+    }
 
-        // Connection controls
+    /// Renders the pending connections list and details of the selected connection.
+    fn render_connection_panel(
+        &self,
+        area: Rect,
+        buf: &mut Buffer,
+        state: &mut TuiMutState,
+        now: std::time::SystemTime,
+    ) {
+        let selected_connection = self.pending_connections.get(self.selected_connection);
         let mut connection_block = Block::bordered()
-            .title(" New Connections ")
+            .title(format!(
+                " New Connections ({}) ",
+                self.pending_connections.len()
+            ))
             .title_alignment(Alignment::Center)
             .border_type(BorderType::Rounded)
-            .title_style(match &self.current_connection {
+            .title_style(match selected_connection {
                 None => Style::default(),
                 Some(_) => Style::default().bold(),
             })
-            .style(match &self.current_connection {
-                None => Style::default().fg(Color::Cyan),
-                Some(_) => Style::default().fg(Color::Yellow),
+            .style(match selected_connection {
+                None => self.theme.base,
+                Some(_) => self.theme.base.patch(self.theme.accent),
             });
-        // Also render a "bottom title" with countdown to dispo the connection.
-        if let Some(conn) = &self.current_connection
+        // Also render a "bottom title" with countdown to dispo the selected connection.
+        if let Some(conn) = selected_connection
             && let Ok(remaining_time) = conn.expiry_ts.duration_since(now)
         {
+            connection_block = connection_block
+                .title_bottom(Line::from(" I: inspect | E: edit rule ").alignment(Alignment::Left));
             connection_block = connection_block.title_bottom(
                 Line::from(format!(
                     " {}s to disposition, else {} ",
@@ -81,74 +354,821 @@ impl TuiState {
                 .alignment(Alignment::Right),
             );
         }
+        let connection_inner_area = connection_block.inner(area);
+        connection_block.render(area, buf);
+
+        // Pending connection list on the left, details of the selected one on the right.
+        let connection_areas = Layout::horizontal([Constraint::Length(34), Constraint::Fill(1)])
+            .split(connection_inner_area);
+
+        // Scroll the list just enough to keep the selected connection visible.
+        let list_offset = self
+            .selected_connection
+            .saturating_sub(usize::from(connection_areas[0].height).saturating_sub(1));
+        let pending_items: Vec<ListItem> = self
+            .pending_connections
+            .iter()
+            .enumerate()
+            .skip(list_offset)
+            .map(|(idx, conn)| {
+                let remaining_s = conn
+                    .expiry_ts
+                    .duration_since(now)
+                    .map_or(0, |remaining| remaining.as_secs());
+                let item = ListItem::from(format!(
+                    "{remaining_s:>3}s {}",
+                    format_connection_summary(&conn.connection)
+                ));
+                if idx == self.selected_connection {
+                    item.style(self.theme.pending)
+                } else {
+                    item
+                }
+            })
+            .collect();
+        let pending_list = List::new(pending_items).style(self.theme.background());
+        Widget::render(pending_list, connection_areas[0], buf);
+        state.connection_area = connection_areas[0];
+        state.connection_list_offset = list_offset;
 
         let connection_text = self.format_connection_panel();
         let connection_paragraph = Paragraph::new(connection_text)
-            .block(connection_block)
-            .bg(Color::Black);
+            .block(
+                Block::new()
+                    .borders(Borders::LEFT)
+                    .padding(Padding::left(1)),
+            )
+            .style(self.theme.background());
 
-        connection_paragraph.render(areas[1], buf);
+        connection_paragraph.render(connection_areas[1], buf);
+    }
 
-        // Alerts list
-        let alerts_block = Block::bordered()
-            .title(format!(" Alerts ({}) ", self.current_alerts.len()))
+    /// Renders the full process context of the selected connection.
+    fn render_details_screen(&self, area: Rect, buf: &mut Buffer, state: &mut TuiMutState) {
+        let lines = match self.pending_connections.get(self.selected_connection) {
+            Some(info) => {
+                let node_string = match self.nodes.get(&info.node) {
+                    Some(node) => node.display_name(&info.node),
+                    None => info.node.clone(),
+                };
+                format_connection_details(&info.connection, &node_string)
+            }
+            None => vec![String::from("Connection is no longer pending.")],
+        };
+        let details_block = Block::bordered()
+            .title(format!(
+                " Connection {}/{} ",
+                std::cmp::min(self.selected_connection + 1, self.pending_connections.len()),
+                self.pending_connections.len()
+            ))
             .title_alignment(Alignment::Center)
+            .title_bottom(
+                Line::from(" ESC: back | Up/Down/PgUp/PgDn: scroll | Lt/Rt: other connection ")
+                    .alignment(Alignment::Right),
+            )
             .border_type(BorderType::Rounded);
+        let inner_width = details_block.inner(area).width;
+        let details_paragraph =
+            Paragraph::new(lines.into_iter().map(Line::from).collect::<Vec<_>>())
+                .wrap(Wrap { trim: false })
+                .style(self.theme.base);
+        // Long lines wrap, so scrolling is bounded by the lines as rendered.
+        let line_count = details_paragraph.line_count(inner_width);
+        state.details_scroll = std::cmp::min(state.details_scroll, line_count.saturating_sub(1));
+        details_paragraph
+            .block(details_block)
+            .scroll((u16::try_from(state.details_scroll).unwrap_or(u16::MAX), 0))
+            .render(area, buf);
+    }
 
-        // We want to render the alert list from some stateful head index,
-        // so get an iterator and skip forward to that head.
-        let items_iter = self
-            .current_alerts
+    /// Renders top-N tables for each of the selected node's stats breakdowns.
+    fn render_stats_screen(&self, area: Rect, buf: &mut Buffer) {
+        let areas = Layout::vertical([
+            Constraint::Fill(1),   // First row of tables
+            Constraint::Fill(1),   // Second row of tables
+            Constraint::Length(8), // Rate sparklines
+            Constraint::Length(1), // Hints
+        ])
+        .split(area);
+        let table_areas: Vec<Rect> = areas[..2]
             .iter()
-            .skip(self.alert_list_render_offset);
+            .flat_map(|row| {
+                Layout::horizontal([Constraint::Fill(1); 3])
+                    .split(*row)
+                    .to_vec()
+            })
+            .collect();
 
-        let items: Vec<ListItem> = items_iter
-            .map(|alert| {
-                let maybe_age = now.duration_since(alert.timestamp);
-                let age_s: u64 = match maybe_age {
-                    Ok(age) => age.as_secs(),
-                    Err(_) => 0, // Just default to 0s in case time goes backwards
+        let selected = self.nodes.selected();
+        for (idx, (map, table_area)) in StatsMap::ALL.iter().zip(table_areas).enumerate() {
+            self.render_stats_table(*map, idx, table_area, buf);
+        }
+        self.render_rate_sparklines(areas[2], buf);
+
+        let node_name = selected.map_or(String::from("no node"), |(key, node)| {
+            node.display_name(key)
+        });
+        Paragraph::new(format!(
+            " {node_name} | ESC: back | Tab/Lt/Rt: select table | S: sort table | \
+            W: rate window ({}) | N: next node ",
+            self.stats_view.window.get_str()
+        ))
+        .style(self.theme.text)
+        .render(areas[3], buf);
+    }
+
+    /// Renders a sparkline per counter of the selected node's increase over the rate window.
+    fn render_rate_sparklines(&self, area: Rect, buf: &mut Buffer) {
+        let now = std::time::SystemTime::now();
+        let window = self.stats_view.window;
+        let sparkline_areas: Vec<Rect> = Layout::vertical([Constraint::Fill(1); 2])
+            .split(area)
+            .iter()
+            .flat_map(|row| {
+                Layout::horizontal([Constraint::Fill(1); 3])
+                    .split(*row)
+                    .to_vec()
+            })
+            .collect();
+        for (counter, sparkline_area) in Counter::ALL.iter().zip(sparkline_areas) {
+            // One bar per column inside the borders.
+            let buckets = usize::from(sparkline_area.width.saturating_sub(2));
+            let rates = match self.nodes.selected() {
+                Some((_, node)) => node.history.rates(*counter, window, now, buckets),
+                None => vec![0; buckets],
+            };
+            let block = Block::bordered()
+                .title(format!(
+                    " {} +{} in {} ",
+                    counter.get_str(),
+                    rates.iter().sum::<u64>(),
+                    window.get_str()
+                ))
+                .border_type(BorderType::Rounded);
+            let style = match counter {
+                Counter::Accepted => self.theme.allow,
+                Counter::Dropped => self.theme.deny,
+                _ => self.theme.base,
+            };
+            Sparkline::default()
+                .block(block)
+                .data(&rates)
+                .style(self.theme.background().patch(style))
+                .render(sparkline_area, buf);
+        }
+    }
+
+    /// Renders a single breakdown table, as many rows as fit.
+    fn render_stats_table(&self, map: StatsMap, idx: usize, area: Rect, buf: &mut Buffer) {
+        let view = &self.stats_view;
+        let sort_key = view.sort_keys[idx];
+        let focused = view.focus == idx;
+        let block = Block::bordered()
+            .title(format!(" By {} ", map.get_str()))
+            .title_alignment(Alignment::Center)
+            .title_bottom(
+                Line::from(format!(" sort: {} ", sort_key.get_str())).alignment(Alignment::Right),
+            )
+            .border_type(BorderType::Rounded)
+            .border_style(if focused {
+                self.theme.accent
+            } else {
+                Style::default()
+            });
+        // Leave room for borders and header.
+        let limit = usize::from(area.height.saturating_sub(3));
+        let entries = match self.nodes.selected() {
+            Some((_, node)) => match &node.stats {
+                Some(stats) => {
+                    stats_view::top_entries(map, stats, node.prev_stats.as_ref(), sort_key, limit)
+                }
+                None => Vec::default(),
+            },
+            None => Vec::default(),
+        };
+        let rows: Vec<Row> = entries
+            .into_iter()
+            .map(|entry| {
+                let key = match (map, entry.key.parse::<u32>()) {
+                    (StatsMap::Uid, Ok(uid)) => match self.users.get(&uid) {
+                        Some(name) => format!("{uid} ({name})"),
+                        None => entry.key,
+                    },
+                    _ => entry.key,
                 };
-                let alert_text = format!(
-                    "{}s ago : {:?} : {:?} : {:?} : {}\n",
-                    age_s, alert.r#type, alert.priority, alert.what, alert.msg,
-                );
-                ListItem::from(alert_text)
+                let delta = match entry.delta {
+                    Some(delta) if delta > 0 => format!("+{delta}"),
+                    Some(delta) => delta.to_string(),
+                    None => String::from("-"),
+                };
+                Row::new(vec![key, entry.hits.to_string(), delta])
             })
             .collect();
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(1),
+                Constraint::Length(8),
+                Constraint::Length(7),
+            ],
+        )
+        .header(Row::new(vec!["Key", "Hits", "Delta"]).style(Style::default().bold()))
+        .block(block)
+        .style(self.theme.base);
+        Widget::render(table, area, buf);
+    }
 
-        // Create a List from all list items
+    /// Renders the connection events reported by all nodes, newest first.
+    fn render_event_log_screen(&self, area: Rect, buf: &mut Buffer) {
+        let areas = Layout::vertical([
+            Constraint::Fill(1),   // Event table
+            Constraint::Length(1), // Hints
+        ])
+        .split(area);
+
+        let log = &self.event_log;
+        let visible = log.visible();
+        let filter_str = if log.editing_filter {
+            format!(" filter: {}_ ", log.filter)
+        } else if log.filter.is_empty() {
+            String::default()
+        } else {
+            format!(" filter: {} ", log.filter)
+        };
+        let pause_str = if log.is_paused() {
+            format!(" PAUSED, {} new ", log.held())
+        } else {
+            String::default()
+        };
+        let table_block = Block::bordered()
+            .title(format!(" Event log ({}/{}) ", visible.len(), log.len()))
+            .title_alignment(Alignment::Center)
+            .title_bottom(
+                Line::from(format!("{pause_str}{filter_str}")).alignment(Alignment::Right),
+            )
+            .border_type(BorderType::Rounded);
+
+        let header = Row::new(vec!["Time", "Node", "Action", "Rule", "Connection"])
+            .style(Style::default().bold());
+        let rows: Vec<Row> = visible
+            .iter()
+            .map(|entry| {
+                let node = match self.nodes.get(&entry.node) {
+                    Some(node) => node.display_name(&entry.node),
+                    None => entry.node.clone(),
+                };
+                let connection = entry
+                    .event
+                    .connection
+                    .as_ref()
+                    .map(format_connection_summary)
+                    .unwrap_or_default();
+                let row = Row::new(vec![
+                    entry.event.time.clone(),
+                    node,
+                    entry.action().to_string(),
+                    entry.rule_name().to_string(),
+                    connection,
+                ]);
+                match constants::Action::new(entry.action()) {
+                    Ok(constants::Action::Allow | constants::Action::Accept) => {
+                        row.style(self.theme.allow)
+                    }
+                    Ok(
+                        constants::Action::Deny
+                        | constants::Action::Reject
+                        | constants::Action::Drop,
+                    ) => row.style(self.theme.deny),
+                    _ => row,
+                }
+            })
+            .collect();
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(19),
+                Constraint::Fill(1),
+                Constraint::Length(7),
+                Constraint::Fill(2),
+                Constraint::Fill(3),
+            ],
+        )
+        .header(header)
+        .block(table_block)
+        .row_highlight_style(self.theme.highlight)
+        .style(self.theme.base);
+        let mut table_state = TableState::default().with_selected(Some(log.selected));
+        StatefulWidget::render(table, areas[0], buf, &mut table_state);
+
+        Paragraph::new(
+            " ESC: back | Up/Down/PgUp/PgDn: scroll | Home: newest | /: filter | P: pause ",
+        )
+        .style(self.theme.text)
+        .render(areas[1], buf);
+    }
+
+    /// Renders journal records loaded from disk, newest first.
+    fn render_history_screen(&self, area: Rect, buf: &mut Buffer) {
+        let areas = Layout::vertical([
+            Constraint::Fill(1),   // Record table
+            Constraint::Length(2), // Status and hints
+        ])
+        .split(area);
+
+        let view = &self.history_view;
+        let visible = view.visible();
+        let filter_str = if view.editing_filter {
+            format!(" filter: {}_ ", view.filter)
+        } else if view.filter.is_empty() {
+            String::default()
+        } else {
+            format!(" filter: {} ", view.filter)
+        };
+        let kind_str = view.kind.as_ref().map_or("all", RecordKind::get_str);
+        let table_block = Block::bordered()
+            .title(format!(
+                " History ({}/{}) ",
+                visible.len(),
+                view.records.len()
+            ))
+            .title_alignment(Alignment::Center)
+            .title_bottom(
+                Line::from(format!("{filter_str} kind: {kind_str} ")).alignment(Alignment::Right),
+            )
+            .border_type(BorderType::Rounded);
+
+        let header =
+            Row::new(vec!["Time (UTC)", "Kind", "Node", "Summary"]).style(Style::default().bold());
+        let rows: Vec<Row> = visible
+            .iter()
+            .map(|record| {
+                let node = match self.nodes.get(&record.node) {
+                    Some(node) => node.display_name(&record.node),
+                    None => record.node.clone(),
+                };
+                Row::new(vec![
+                    history::format_utc(record.ts),
+                    record.kind.get_str().to_string(),
+                    node,
+                    record.summary.clone(),
+                ])
+            })
+            .collect();
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(19),
+                Constraint::Length(10),
+                Constraint::Fill(1),
+                Constraint::Fill(4),
+            ],
+        )
+        .header(header)
+        .block(table_block)
+        .row_highlight_style(self.theme.highlight)
+        .style(self.theme.base);
+        let mut table_state = TableState::default().with_selected(Some(view.selected));
+        StatefulWidget::render(table, areas[0], buf, &mut table_state);
+
+        let status = Line::from(format!(" {}", view.status.as_deref().unwrap_or_default()))
+            .style(self.theme.accent);
+        let hints = Line::from(
+            " ESC: back | Up/Down/PgUp/PgDn: select | Home: newest | /: filter | K: kind | R: reload ",
+        );
+        Paragraph::new(vec![status, hints])
+            .style(self.theme.text)
+            .render(areas[1], buf);
+    }
+
+    /// Renders the rule browser for the selected node.
+    fn render_rules_screen(&self, area: Rect, buf: &mut Buffer) {
+        let areas = Layout::vertical([
+            Constraint::Fill(1),    // Rule table
+            Constraint::Length(10), // Operator tree of selected rule
+            Constraint::Length(2),  // Status and hints
+        ])
+        .split(area);
+
+        let rules = self.nodes.selected_rules();
+        let node_key = self.nodes.selected_key().unwrap_or_default();
+        let view = &self.rules_view;
+        let visible = view.visible(rules);
+
+        let node_name = match self.nodes.selected() {
+            Some((key, node)) => node.display_name(key),
+            None => String::from("no node"),
+        };
+        let filter_str = if view.editing_filter {
+            format!(" filter: {}_ ", view.filter)
+        } else if view.filter.is_empty() {
+            String::default()
+        } else {
+            format!(" filter: {} ", view.filter)
+        };
+        let table_block = Block::bordered()
+            .title(format!(
+                " Rules on {node_name} ({}/{}) ",
+                visible.len(),
+                rules.len()
+            ))
+            .title_alignment(Alignment::Center)
+            .title_bottom(
+                Line::from(format!("{filter_str} sort: {} ", view.sort_key.get_str()))
+                    .alignment(Alignment::Right),
+            )
+            .border_type(BorderType::Rounded);
+
+        let header = Row::new(vec!["Name", "Action", "Duration", "Enabled", "Operator"])
+            .style(Style::default().bold());
+        let rows: Vec<Row> = visible
+            .iter()
+            .map(|rule| {
+                let operator = rule
+                    .operator
+                    .as_ref()
+                    .map(rules_view::operator_summary)
+                    .unwrap_or_default();
+                let row = Row::new(vec![
+                    rule.name.clone(),
+                    rule.action.clone(),
+                    rule.duration.clone(),
+                    if rule.enabled { "yes" } else { "no" }.to_string(),
+                    operator,
+                ]);
+                // Rules with an operation in flight until the daemon replies.
+                if self.notifications.is_pending(node_key, &rule.name) {
+                    row.style(self.theme.accent)
+                } else {
+                    row
+                }
+            })
+            .collect();
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(3),
+                Constraint::Length(8),
+                Constraint::Length(13),
+                Constraint::Length(7),
+                Constraint::Fill(4),
+            ],
+        )
+        .header(header)
+        .block(table_block)
+        .row_highlight_style(self.theme.highlight)
+        .style(self.theme.base);
+        let mut table_state = TableState::default().with_selected(Some(view.selected));
+        StatefulWidget::render(table, areas[0], buf, &mut table_state);
+
+        self.render_rule_detail(view.selected_rule(rules), areas[1], buf);
+        self.render_rules_footer(areas[2], buf);
+    }
+
+    /// Renders the rules screen's operation status and key hints.
+    fn render_rules_footer(&self, area: Rect, buf: &mut Buffer) {
+        let view = &self.rules_view;
+        let status = if view.confirm_delete {
+            let name = view
+                .selected_rule(self.nodes.selected_rules())
+                .map(|rule| rule.name.as_str())
+                .unwrap_or_default();
+            Line::from(format!(" Delete rule {name}? (y/N) ")).style(self.theme.error)
+        } else {
+            let pending = if self.notifications.is_empty() {
+                String::default()
+            } else {
+                format!("[{} pending] ", self.notifications.len())
+            };
+            Line::from(format!(
+                " {pending}{}",
+                view.status.as_deref().unwrap_or_default()
+            ))
+            .style(self.theme.accent)
+        };
+        let hints = Line::from(
+            " ESC: back | Up/Down: select | /: filter | S: sort | N: next node | \
+            E/D: enable/disable | A: cycle action | P: toggle precedence | X: delete ",
+        );
+        Paragraph::new(vec![status, hints])
+            .style(self.theme.text)
+            .render(area, buf);
+    }
+
+    /// Renders description and operator tree of the selected rule.
+    fn render_rule_detail(&self, rule: Option<&pb::Rule>, area: Rect, buf: &mut Buffer) {
+        let detail_lines: Vec<Line> = match rule {
+            Some(rule) => {
+                let mut lines = vec![Line::from(format!(
+                    "{} | precedence: {} | nolog: {}",
+                    if rule.description.is_empty() {
+                        "no description"
+                    } else {
+                        &rule.description
+                    },
+                    rule.precedence,
+                    rule.nolog
+                ))];
+                if let Some(operator) = &rule.operator {
+                    lines.extend(
+                        rules_view::operator_tree_lines(operator)
+                            .into_iter()
+                            .map(Line::from),
+                    );
+                }
+                lines
+            }
+            None => Vec::default(),
+        };
+        let detail_block = Block::bordered()
+            .title(" Operators ")
+            .title_alignment(Alignment::Center)
+            .border_type(BorderType::Rounded);
+        let detail_paragraph = Paragraph::new(detail_lines)
+            .block(detail_block)
+            .wrap(Wrap { trim: false })
+            .style(self.theme.base);
+        detail_paragraph.render(area, buf);
+    }
+
+    /// Renders the system firewall chains and rules of the selected node.
+    fn render_firewall_screen(&self, area: Rect, buf: &mut Buffer) {
+        let areas = Layout::vertical([
+            Constraint::Fill(1),   // Chains and rules
+            Constraint::Length(8), // Detail of selected row
+            Constraint::Length(2), // Status and hints
+        ])
+        .split(area);
+
+        let view = &self.firewall_view;
+        let node_key = self.nodes.selected_key().unwrap_or_default();
+        let node_name = match self.nodes.selected() {
+            Some((key, node)) => node.display_name(key),
+            None => String::from("no node"),
+        };
+        let firewall = view.shown(&self.nodes);
+        let rows = firewall.map(firewall_view::rows).unwrap_or_default();
+        let modified = if view.draft_for(node_key).is_some() {
+            " (modified)"
+        } else {
+            ""
+        };
+        let state_str = match firewall {
+            Some(firewall) => format!(
+                " {}, version {} ",
+                if firewall.enabled {
+                    "enabled"
+                } else {
+                    "disabled"
+                },
+                firewall.version
+            ),
+            None => String::from(" not reported "),
+        };
+        let list_block = Block::bordered()
+            .title(format!(" System firewall on {node_name}{modified} "))
+            .title_alignment(Alignment::Center)
+            .title_bottom(Line::from(state_str).alignment(Alignment::Right))
+            .border_type(BorderType::Rounded);
+        let items: Vec<ListItem> = match firewall {
+            Some(firewall) => rows
+                .iter()
+                .map(|row| {
+                    let item = ListItem::from(firewall_view::row_summary(firewall, *row));
+                    match row {
+                        FirewallRow::Chain { .. } => item.style(Style::default().bold()),
+                        FirewallRow::Rule { group, chain, rule } => {
+                            match firewall_view::chain(firewall, *group, *chain)
+                                .and_then(|chain| chain.rules.get(*rule))
+                            {
+                                Some(rule) if !rule.enabled => item.dim(),
+                                _ => item,
+                            }
+                        }
+                        FirewallRow::Legacy { .. } => item,
+                    }
+                })
+                .collect(),
+            None => Vec::default(),
+        };
+        // Chains and rules being reloaded until the daemon replies.
+        let list_style = if self.notifications.is_firewall_pending(node_key) {
+            self.theme.base.patch(self.theme.accent)
+        } else {
+            self.theme.base
+        };
         let list = List::new(items)
-            .block(alerts_block)
-            .fg(Color::Cyan)
-            .bg(Color::Black);
-        Widget::render(list, areas[2], buf);
+            .block(list_block)
+            .highlight_style(self.theme.highlight)
+            .style(list_style);
+        let mut list_state = ListState::default().with_selected(Some(view.selected));
+        StatefulWidget::render(list, areas[0], buf, &mut list_state);
 
-        // Controls footer
-        let mut controls_spans = Vec::new();
-        for control in &self.controls {
-            controls_spans.push(Span::styled(
-                control.get_keybind_str(),
-                Style::default().bg(Color::Black).fg(Color::White),
-            ));
-            controls_spans.push(Span::styled(
-                control.get_control_str(),
-                Style::default().bg(Color::Gray).fg(Color::Black),
-            ));
+        let detail_lines: Vec<Line> = firewall
+            .and_then(|firewall| {
+                view.selected_row(firewall)
+                    .map(|row| firewall_view::row_detail_lines(firewall, row))
+            })
+            .unwrap_or_default()
+            .into_iter()
+            .map(Line::from)
+            .collect();
+        Paragraph::new(detail_lines)
+            .block(
+                Block::bordered()
+                    .title(" Detail ")
+                    .title_alignment(Alignment::Center)
+                    .border_type(BorderType::Rounded),
+            )
+            .wrap(Wrap { trim: false })
+            .style(self.theme.base)
+            .render(areas[1], buf);
+
+        self.render_firewall_footer(areas[2], buf);
+        if view.confirm_send {
+            self.render_firewall_diff(area, buf);
         }
-        let controls_text = vec![controls_spans.into()];
+    }
 
-        // Important: Left alignment makes it super easy to calculate whether
-        // mouse clicks occurred over a control "button"
-        let controls_paragraph = Paragraph::new(controls_text)
-            .bg(Color::Black)
-            .alignment(Alignment::Left);
+    /// Renders the firewall screen's status and key hints, or the prompt for a new rule.
+    fn render_firewall_footer(&self, area: Rect, buf: &mut Buffer) {
+        let view = &self.firewall_view;
+        let status = Line::from(format!(" {}", view.status.as_deref().unwrap_or_default()))
+            .style(self.theme.accent);
+        let lines = if let Some(key) = &view.confirm_reload {
+            let node_name = self
+                .nodes
+                .get(key)
+                .map_or_else(|| key.clone(), |node| node.display_name(key));
+            vec![
+                Line::from(format!(
+                    " Reapply the firewall rules {node_name} reported? (y/N) "
+                ))
+                .style(self.theme.error),
+            ]
+        } else if let Some(spec) = &view.new_rule {
+            let prompt = Line::from(format!(" New rule: {spec}_")).style(self.theme.accent);
+            let hints = if view.status.is_some() {
+                status.style(self.theme.error)
+            } else {
+                Line::from(format!(
+                    " Enter: add | Tab: next template ({}) | ESC: cancel ",
+                    firewall_view::RULE_TEMPLATES[view.template].0
+                ))
+            };
+            vec![prompt, hints]
+        } else {
+            let hints = Line::from(
+                " ESC: back | Space: toggle | X: remove | Shift+Up/Down: move | A: add | \
+                    S: send | U: undo | N: next node | R: reapply ",
+            );
+            vec![status, hints]
+        };
+        Paragraph::new(lines)
+            .style(self.theme.text)
+            .render(area, buf);
+    }
 
-        controls_paragraph.render(areas[3], buf);
-        state.controls_area = areas[3];
+    /// Renders the changes of the firewall draft as a popup, asking to send them.
+    fn render_firewall_diff(&self, area: Rect, buf: &mut Buffer) {
+        let node_key = self.nodes.selected_key().unwrap_or_default();
+        let lines: Vec<Line> = match (
+            self.nodes.selected_firewall(),
+            self.firewall_view.draft_for(node_key),
+        ) {
+            (Some(firewall), Some(draft)) => firewall_view::diff_lines(firewall, draft)
+                .into_iter()
+                .map(|line| {
+                    let style = match line.chars().next() {
+                        Some('+') => self.theme.allow,
+                        Some('-') => self.theme.deny,
+                        _ => Style::default().bold(),
+                    };
+                    Line::styled(line, style)
+                })
+                .collect(),
+            _ => Vec::default(),
+        };
+        let popup_area = area.inner(ratatui::layout::Margin {
+            horizontal: area.width / 10,
+            vertical: area.height / 8,
+        });
+        Clear.render(popup_area, buf);
+        let block = Block::bordered()
+            .title(format!(" Send changes to {node_key}? (y/N) "))
+            .title_alignment(Alignment::Center)
+            .title_bottom(
+                Line::from(" Rolled back if the node fails to apply them ")
+                    .alignment(Alignment::Right),
+            )
+            .border_type(BorderType::Rounded)
+            .style(self.theme.background().patch(self.theme.accent));
+        Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false })
+            .style(self.theme.background())
+            .render(popup_area, buf);
     }
 
-    fn render_help_screen(area: Rect, buf: &mut Buffer, _state: &mut TuiMutState) {
+    /// Renders the form editing a node's daemon config, next to the JSON it sends.
+    fn render_config_screen(&self, editor: &ConfigEditor, area: Rect, buf: &mut Buffer) {
+        let areas = Layout::vertical([
+            Constraint::Fill(1),   // Settings and JSON preview
+            Constraint::Length(2), // Status and hints
+        ])
+        .split(area);
+        let panes = Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).split(areas[0]);
+
+        let node_name = self.nodes.get(&editor.node).map_or_else(
+            || editor.node.clone(),
+            |node| node.display_name(&editor.node),
+        );
+        let modified = if editor.is_modified() {
+            " (modified)"
+        } else {
+            ""
+        };
+        let field_lines: Vec<Line> = ConfigField::ALL
+            .iter()
+            .enumerate()
+            .map(|(idx, field)| {
+                let marker = if editor.is_field_modified(*field) {
+                    "*"
+                } else {
+                    " "
+                };
+                let line = Line::from(format!(
+                    "{marker}{:<26}{}",
+                    field.get_str(),
+                    editor.field_value(*field)
+                ));
+                if idx == editor.focus {
+                    line.style(self.theme.pending)
+                } else {
+                    line
+                }
+            })
+            .collect();
+        // Changes being applied until the daemon replies.
+        let style = if self.notifications.is_config_pending(&editor.node) {
+            self.theme.base.patch(self.theme.accent)
+        } else {
+            self.theme.base
+        };
+        Paragraph::new(field_lines)
+            .block(
+                Block::bordered()
+                    .title(format!(" Daemon config of {node_name}{modified} "))
+                    .title_alignment(Alignment::Center)
+                    .border_type(BorderType::Rounded),
+            )
+            .style(style)
+            .render(panes[0], buf);
+        Paragraph::new(editor.preview())
+            .block(
+                Block::bordered()
+                    .title(" JSON ")
+                    .title_alignment(Alignment::Center)
+                    .border_type(BorderType::Rounded),
+            )
+            .wrap(Wrap { trim: false })
+            .style(self.theme.base)
+            .render(panes[1], buf);
+
+        let status = Line::from(format!(" {}", editor.status.as_deref().unwrap_or_default()))
+            .style(self.theme.accent);
+        let lines = if editor.confirm_send {
+            let modified = editor.modified_fields();
+            let fields: Vec<&str> = modified.iter().map(ConfigField::get_str).collect();
+            vec![
+                Line::from(format!(" Changed: {}", fields.join(", "))).style(self.theme.accent),
+                Line::from(format!(" Send these changes to {node_name}? (y/N) "))
+                    .style(self.theme.error),
+            ]
+        } else if editor.confirm_discard {
+            vec![
+                status,
+                Line::from(format!(
+                    " Discard the changes to {node_name}'s config and edit the next node? (y/N) "
+                ))
+                .style(self.theme.error),
+            ]
+        } else if editor.text.is_some() {
+            vec![status, Line::from(" Enter: done typing | ESC: cancel ")]
+        } else {
+            vec![
+                status,
+                Line::from(
+                    " ESC: back | Up/Down: select | Space/Enter: change | S: send | U: undo | \
+                        N: next node ",
+                ),
+            ]
+        };
+        Paragraph::new(lines)
+            .style(self.theme.text)
+            .render(areas[1], buf);
+    }
+
+    fn render_help_screen(&self, area: Rect, buf: &mut Buffer) {
         let help_title = String::from(" OpenSnitch TUI Help ");
         let help_block = Block::bordered()
             .title(help_title)
@@ -160,61 +1180,65 @@ impl TuiState {
         let author = env!("CARGO_PKG_AUTHORS");
         help_lines.push(Line::styled(
             format!("opensnitch-tui {version} by {author}. Released under the GNU GPLv3."),
-            Style::default().fg(Color::Cyan),
+            self.theme.base,
         ));
         help_lines.push(Line::default()); // blank
 
         help_lines.push(Line::styled(
             "Run the binary with --help to see details on CLI arguments.",
-            Style::default().fg(Color::Cyan),
+            self.theme.base,
         ));
         help_lines.push(Line::default()); // blank
 
-        help_lines.push(Line::styled(
-            "Keybindings",
-            Style::default().fg(Color::Cyan).bold(),
-        ));
-        // Very scrappy vec of per-line k-vs in help screen
-        // 1st => some keybinding
-        // 2nd => description
-        // Technically slightly repetitive of main screen's footer.
-        let kv_raw_lines = vec![
-            ("Ctrl+C", "Quit"),
-            ("ESC", "Return to main screen"),
-            ("H", "Display this help screen"),
-            ("A", "Allow connection temporarily"),
-            ("D", "Deny connection temporarily"),
-            ("J", "Allow connection forever"),
-            ("L", "Deny connection forever"),
-            ("Arrows", "Scroll alert list"),
-        ];
-        for (raw_k, raw_v) in kv_raw_lines {
+        help_lines.push(Line::styled("Keybindings", self.theme.base.bold()));
+        // Per-line keybinding and description, from the same keymap as the footer.
+        let mut kv_lines = vec![(String::from("ESC"), "Return to main screen")];
+        kv_lines.extend(
+            KeyAction::ALL
+                .iter()
+                .map(|action| (self.keymap.key(*action).to_string(), action.description())),
+        );
+        for (raw_k, raw_v) in kv_lines {
             help_lines.push(Line::from(vec![
-                Span::styled(format!("{raw_k:>7} "), Style::default().fg(Color::White)),
-                Span::styled(raw_v.to_string(), Style::default().fg(Color::Cyan)),
+                Span::styled(format!("{raw_k:>7} "), self.theme.text),
+                Span::styled(raw_v.to_string(), self.theme.base),
             ]));
         }
         help_lines.push(Line::default()); // blank
 
         help_lines.push(Line::styled(
             "The main screen's footer with keybinding hints is clickable.",
-            Style::default().fg(Color::Cyan),
+            self.theme.base,
         ));
 
         let help_paragraph = Paragraph::new(help_lines)
             .block(help_block)
-            .fg(Color::Cyan)
-            .bg(Color::Black);
+            .style(self.theme.base);
 
         help_paragraph.render(area, buf);
     }
 
     fn format_stats_panel(&self) -> String {
-        match &self.current_stats {
-            Some(stats) => {
+        match self
+            .nodes
+            .selected()
+            .and_then(|(_, node)| node.stats.as_ref().map(|stats| (node, stats)))
+        {
+            Some((node, stats)) => {
+                let firewall = match &node.config {
+                    Some(config) if config.is_firewall_running => "running",
+                    Some(_) => "stopped",
+                    None => "-",
+                };
+                let interception = if node.interception_paused {
+                    "paused"
+                } else {
+                    "on"
+                };
                 format!(
                     "\
-                        daemon version: {} | uptime: {}\n\
+                        daemon version: {} | uptime: {} | firewall: {firewall} | \
+                        interception: {interception}\n\
                         rules: {} | dns responses: {} | connections: {}\n\
                         ignored: {} | accepted: {} | dropped: {}\n\
                         rule hits: {} | rule misses: {}",
@@ -235,7 +1259,7 @@ impl TuiState {
     }
 
     fn format_connection_panel(&self) -> String {
-        match &self.current_connection {
+        match self.pending_connections.get(self.selected_connection) {
             None => String::default(),
             Some(info) => {
                 // Don't just leave field blank if not populated.
@@ -248,15 +1272,23 @@ impl TuiState {
                 let src_ip = format_ip_address_string(&info.connection.src_ip);
                 let dst_ip = format_ip_address_string(&info.connection.dst_ip);
 
+                let node_string = match self.nodes.get(&info.node) {
+                    Some(node) => node.display_name(&info.node),
+                    None => info.node.clone(),
+                };
+
                 format!(
                     "\
+                node      {}\n\
                 src       {}:{}\n\
                 dst       {}:{}\n\
                 proto     {}\n\
                 dst host  {}\n\
                 uid       {}\n\
                 pid       {}\n\
-                ppath     {}",
+                ppath     {}\n\
+                cmd       {}",
+                    node_string,
                     src_ip,
                     info.connection.src_port,
                     dst_ip,
@@ -266,12 +1298,115 @@ impl TuiState {
                     info.connection.user_id,
                     info.connection.process_id,
                     info.connection.process_path,
+                    format_command_line(&info.connection.process_args),
                 )
             }
         }
     }
 }
 
+/// Join process args into a command line, quoting args that would otherwise be ambiguous.
+fn format_command_line(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| {
+            if arg.is_empty() || arg.contains(char::is_whitespace) {
+                format!("'{arg}'")
+            } else {
+                arg.clone()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Lines of the connection details screen, grouped by section.
+fn format_connection_details(conn: &pb::Connection, node: &str) -> Vec<String> {
+    let mut lines = vec![
+        String::from("Process"),
+        format!("  pid       {}", conn.process_id),
+        format!("  uid       {}", conn.user_id),
+        format!("  path      {}", conn.process_path),
+        format!("  cwd       {}", conn.process_cwd),
+        format!("  cmdline   {}", format_command_line(&conn.process_args)),
+        String::default(),
+        String::from("Connection"),
+        format!("  node      {node}"),
+        format!("  proto     {}", conn.protocol),
+        format!(
+            "  src       {}:{}",
+            format_ip_address_string(&conn.src_ip),
+            conn.src_port
+        ),
+        format!(
+            "  dst       {}:{}",
+            format_ip_address_string(&conn.dst_ip),
+            conn.dst_port
+        ),
+        format!(
+            "  dst host  {}",
+            if conn.dst_host.is_empty() {
+                "-"
+            } else {
+                &conn.dst_host
+            }
+        ),
+        String::default(),
+        String::from("Checksums"),
+    ];
+    let mut checksums: Vec<_> = conn.process_checksums.iter().collect();
+    checksums.sort();
+    if checksums.is_empty() {
+        lines.push(String::from("  -"));
+    }
+    lines.extend(
+        checksums
+            .into_iter()
+            .map(|(algo, sum)| format!("  {algo:<9} {sum}")),
+    );
+
+    // The daemon lists the process itself first, then its ancestors up to init.
+    lines.push(String::default());
+    lines.push(String::from("Process tree"));
+    if conn.process_tree.is_empty() {
+        lines.push(String::from("  -"));
+    }
+    lines.extend(conn.process_tree.iter().enumerate().map(|(depth, entry)| {
+        let branch = if depth == 0 { "" } else { "└─ " };
+        format!(
+            "  {}{branch}{} ({})",
+            "   ".repeat(depth.saturating_sub(1)),
+            entry.key,
+            entry.value
+        )
+    }));
+
+    lines.push(String::default());
+    lines.push(format!("Environment ({})", conn.process_env.len()));
+    let mut env: Vec<_> = conn.process_env.iter().collect();
+    env.sort();
+    lines.extend(
+        env.into_iter()
+            .map(|(key, value)| format!("  {key}={value}")),
+    );
+    lines
+}
+
+/// One-line summary of a connection for list views: process name and destination.
+#[must_use]
+pub fn format_connection_summary(conn: &pb::Connection) -> String {
+    let process = conn
+        .process_path
+        .rsplit('/')
+        .next()
+        .unwrap_or(&conn.process_path);
+    let dst = if conn.dst_host.is_empty() {
+        format_ip_address_string(&conn.dst_ip)
+    } else {
+        conn.dst_host.clone()
+    };
+    format!("{process} -> {dst}:{}", conn.dst_port)
+}
+
 /// Format IPv6 addresses (that are already strings) with square brackets. Noop if IPv4.
 fn format_ip_address_string(ip: &String) -> String {
     if ip.contains(':') {
@@ -280,3 +1415,49 @@ fn format_ip_address_string(ip: &String) -> String {
         ip.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test the details screen lists process context, with the process tree indented.
+    #[test]
+    fn test_connection_details() {
+        let conn = pb::Connection {
+            process_path: String::from("/usr/bin/python3"),
+            process_args: vec![
+                String::from("python3"),
+                String::from("my script.py"),
+                String::from("-v"),
+            ],
+            process_checksums: [(String::from("md5"), String::from("abc"))].into(),
+            process_env: [(String::from("HOME"), String::from("/root"))].into(),
+            process_tree: vec![
+                pb::StringInt {
+                    key: String::from("/usr/bin/python3"),
+                    value: 42,
+                },
+                pb::StringInt {
+                    key: String::from("/bin/bash"),
+                    value: 7,
+                },
+                pb::StringInt {
+                    key: String::from("/sbin/init"),
+                    value: 1,
+                },
+            ],
+            ..Default::default()
+        };
+        let lines = format_connection_details(&conn, "unix");
+        let has_line = |expected: &str| lines.iter().any(|line| line == expected);
+
+        assert!(has_line("  cmdline   python3 'my script.py' -v"));
+        assert!(has_line("  node      unix"));
+        assert!(has_line("  md5       abc"));
+        assert!(has_line("  /usr/bin/python3 (42)"));
+        assert!(has_line("  └─ /bin/bash (7)"));
+        assert!(has_line("     └─ /sbin/init (1)"));
+        assert!(has_line("Environment (1)"));
+        assert!(has_line("  HOME=/root"));
+    }
+}
@@ -0,0 +1,488 @@
+//! Unix domain socket transport helpers for the gRPC server.
+//!
+//! The `OpenSnitch` daemon dials domain sockets with grpc-go's passthrough resolver, which
+//! sets the HTTP/2 `:authority` pseudo-header to the socket path (e.g. `/tmp/osui.sock`).
+//! That isn't a valid URI authority, so the `h2` crate underneath tonic rejects every request
+//! as malformed (see <https://github.com/hyperium/tonic/issues/742>).
+//! `AuthorityRewriteStream` works around this by re-encoding each header block sent by the
+//! client, replacing an invalid authority with `localhost` before `h2` gets to parse it.
+
+use std::io;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+
+use loona_hpack::{Decoder, Encoder};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::UnixStream;
+use tonic::codegen::http::uri::Authority;
+use tonic::transport::server::{Connected, UdsConnectInfo};
+
+/// HTTP/2 client connection preface.
+const PREFACE_LEN: usize = 24;
+/// HTTP/2 frame header length.
+const FRAME_HEADER_LEN: usize = 9;
+/// Default `SETTINGS_MAX_FRAME_SIZE`, which tonic doesn't override.
+const MAX_FRAME_SIZE: usize = 16_384;
+/// Authority substituted for invalid ones.
+const FALLBACK_AUTHORITY: &[u8] = b"localhost";
+
+const FRAME_TYPE_HEADERS: u8 = 0x1;
+const FRAME_TYPE_CONTINUATION: u8 = 0x9;
+const FLAG_END_STREAM: u8 = 0x1;
+const FLAG_END_HEADERS: u8 = 0x4;
+const FLAG_PADDED: u8 = 0x8;
+const FLAG_PRIORITY: u8 = 0x20;
+
+/// Ownership and permissions to apply to a freshly bound socket file.
+#[derive(Clone, Debug, Default)]
+pub struct UnixSocketOptions {
+    /// File mode bits, e.g. `0o660`.
+    pub mode: Option<u32>,
+    /// Owning user ID.
+    pub uid: Option<u32>,
+    /// Owning group ID.
+    pub gid: Option<u32>,
+}
+
+impl UnixSocketOptions {
+    /// Validates raw CLI inputs and builds socket options.
+    /// * `mode`: Octal file mode, e.g. "660" or "0660".
+    /// * `owner`: Numeric "uid:gid", "uid" or ":gid".
+    /// # Errors
+    /// Returns an error if either input can't be parsed.
+    pub fn new(mode: Option<&str>, owner: Option<&str>) -> Result<Self, String> {
+        let mut res = Self::default();
+        if let Some(mode) = mode {
+            let parsed = u32::from_str_radix(mode, 8)
+                .map_err(|err| format!("Invalid socket mode '{mode}': {err}"))?;
+            if parsed > 0o7777 {
+                return Err(format!("Invalid socket mode '{mode}': out of range"));
+            }
+            res.mode = Some(parsed);
+        }
+        if let Some(owner) = owner {
+            let (uid, gid) = owner.split_once(':').unwrap_or((owner, ""));
+            let parse_id = |id: &str| -> Result<Option<u32>, String> {
+                if id.is_empty() {
+                    Ok(None)
+                } else {
+                    id.parse::<u32>()
+                        .map(Some)
+                        .map_err(|err| format!("Invalid socket owner '{owner}': {err}"))
+                }
+            };
+            res.uid = parse_id(uid)?;
+            res.gid = parse_id(gid)?;
+        }
+        Ok(res)
+    }
+
+    /// Apply these options to the socket file at `path`.
+    /// # Errors
+    /// Returns an error if permissions or ownership can't be changed.
+    pub fn apply(&self, path: &Path) -> io::Result<()> {
+        if let Some(mode) = self.mode {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+        }
+        if self.uid.is_some() || self.gid.is_some() {
+            std::os::unix::fs::chown(path, self.uid, self.gid)?;
+        }
+        Ok(())
+    }
+}
+
+/// Remove a socket file left behind by a previous run.
+/// # Errors
+/// Returns an error if the path exists but isn't a socket, or if another server is
+/// still accepting connections on it.
+pub fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    if !metadata.file_type().is_socket() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} exists and is not a socket", path.display()),
+        ));
+    }
+    if std::os::unix::net::UnixStream::connect(path).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("{} is in use by another server", path.display()),
+        ));
+    }
+    std::fs::remove_file(path)
+}
+
+/// Source of unique IDs for domain socket connections.
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Connection info of a domain socket peer. Peers on the same socket have no address to
+/// tell them apart by, so each connection gets an ID.
+#[derive(Clone, Debug)]
+pub struct UnixConnectInfo {
+    pub connection: u64,
+    pub uds: UdsConnectInfo,
+}
+
+/// Header block being accumulated across HEADERS and CONTINUATION frames.
+#[derive(Debug)]
+struct PendingHeaderBlock {
+    stream_id: [u8; 4],
+    flags: u8,
+    priority: Option<[u8; 5]>,
+    fragment: Vec<u8>,
+}
+
+/// Stream wrapper that rewrites invalid `:authority` pseudo-headers sent by the client.
+/// Writes (server to client) pass through untouched.
+pub struct AuthorityRewriteStream<S> {
+    inner: S,
+    /// ID of the connection, from `NEXT_CONNECTION_ID`.
+    connection: u64,
+    /// Bytes read from the client that don't yet form a complete frame.
+    raw: Vec<u8>,
+    /// Bytes ready to be handed to the server.
+    ready: Vec<u8>,
+    preface_done: bool,
+    header_block: Option<PendingHeaderBlock>,
+    decoder: Decoder<'static>,
+    encoder: Encoder<'static>,
+}
+
+impl<S> std::fmt::Debug for AuthorityRewriteStream<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthorityRewriteStream")
+            .field("raw", &self.raw.len())
+            .field("ready", &self.ready.len())
+            .field("preface_done", &self.preface_done)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> AuthorityRewriteStream<S> {
+    #[must_use]
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            connection: NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
+            raw: Vec::new(),
+            ready: Vec::new(),
+            preface_done: false,
+            header_block: None,
+            decoder: Decoder::new(),
+            encoder: Encoder::new(),
+        }
+    }
+
+    /// Move every complete unit in `raw` over to `ready`, rewriting header blocks on the way.
+    fn process(&mut self) -> io::Result<()> {
+        if !self.preface_done {
+            if self.raw.len() < PREFACE_LEN {
+                return Ok(());
+            }
+            self.ready.extend(self.raw.drain(..PREFACE_LEN));
+            self.preface_done = true;
+        }
+
+        while self.raw.len() >= FRAME_HEADER_LEN {
+            let len = usize::from(self.raw[0]) << 16
+                | usize::from(self.raw[1]) << 8
+                | usize::from(self.raw[2]);
+            if self.raw.len() < FRAME_HEADER_LEN + len {
+                break;
+            }
+            let frame: Vec<u8> = self.raw.drain(..FRAME_HEADER_LEN + len).collect();
+            let frame_type = frame[3];
+            let flags = frame[4];
+            let stream_id = [frame[5], frame[6], frame[7], frame[8]];
+            let payload = &frame[FRAME_HEADER_LEN..];
+
+            match frame_type {
+                FRAME_TYPE_HEADERS => {
+                    let mut start = 0;
+                    let mut end = payload.len();
+                    if flags & FLAG_PADDED != 0 {
+                        let pad_len = usize::from(*payload.first().ok_or_else(malformed)?);
+                        start += 1;
+                        end = end.checked_sub(pad_len).ok_or_else(malformed)?;
+                    }
+                    let priority = if flags & FLAG_PRIORITY == 0 {
+                        None
+                    } else {
+                        let bytes = payload.get(start..start + 5).ok_or_else(malformed)?;
+                        start += 5;
+                        Some([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4]])
+                    };
+                    self.header_block = Some(PendingHeaderBlock {
+                        stream_id,
+                        flags,
+                        priority,
+                        fragment: payload.get(start..end).ok_or_else(malformed)?.to_vec(),
+                    });
+                }
+                FRAME_TYPE_CONTINUATION if self.header_block.is_some() => {
+                    if let Some(block) = &mut self.header_block {
+                        block.fragment.extend_from_slice(payload);
+                    }
+                }
+                _ => {
+                    self.ready.extend_from_slice(&frame);
+                    continue;
+                }
+            }
+
+            if flags & FLAG_END_HEADERS != 0
+                && let Some(block) = self.header_block.take()
+            {
+                self.rewrite_header_block(&block)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Decode a complete header block, fix up its authority and re-emit it as frames.
+    /// Every block is re-encoded, since the server's HPACK state must track our encoder
+    /// rather than the client's.
+    fn rewrite_header_block(&mut self, block: &PendingHeaderBlock) -> io::Result<()> {
+        let mut headers = self
+            .decoder
+            .decode(&block.fragment)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{err:?}")))?;
+        for (name, value) in &mut headers {
+            if name.as_slice() == b":authority" && Authority::try_from(value.as_slice()).is_err() {
+                *value = FALLBACK_AUTHORITY.to_vec();
+            }
+        }
+        let encoded = self
+            .encoder
+            .encode(headers.iter().map(|(name, value)| (&name[..], &value[..])));
+
+        let mut first_payload = Vec::new();
+        if let Some(priority) = block.priority {
+            first_payload.extend_from_slice(&priority);
+        }
+        let first_len = std::cmp::min(encoded.len(), MAX_FRAME_SIZE - first_payload.len());
+        first_payload.extend_from_slice(&encoded[..first_len]);
+        let mut rest = encoded[first_len..].chunks(MAX_FRAME_SIZE).peekable();
+
+        let mut flags = block.flags & (FLAG_END_STREAM | FLAG_PRIORITY);
+        if rest.peek().is_none() {
+            flags |= FLAG_END_HEADERS;
+        }
+        self.push_frame(FRAME_TYPE_HEADERS, flags, block.stream_id, &first_payload);
+        while let Some(chunk) = rest.next() {
+            let flags = if rest.peek().is_none() {
+                FLAG_END_HEADERS
+            } else {
+                0
+            };
+            self.push_frame(FRAME_TYPE_CONTINUATION, flags, block.stream_id, chunk);
+        }
+        Ok(())
+    }
+
+    fn push_frame(&mut self, frame_type: u8, flags: u8, stream_id: [u8; 4], payload: &[u8]) {
+        let len = payload.len().to_be_bytes();
+        self.ready.extend_from_slice(&len[len.len() - 3..len.len()]);
+        self.ready.push(frame_type);
+        self.ready.push(flags);
+        self.ready.extend_from_slice(&stream_id);
+        self.ready.extend_from_slice(payload);
+    }
+}
+
+fn malformed() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "malformed HTTP/2 HEADERS frame")
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for AuthorityRewriteStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if !this.ready.is_empty() {
+                let n = std::cmp::min(buf.remaining(), this.ready.len());
+                buf.put_slice(&this.ready[..n]);
+                this.ready.drain(..n);
+                return Poll::Ready(Ok(()));
+            }
+
+            let mut chunk = [0u8; 8192];
+            let mut chunk_buf = ReadBuf::new(&mut chunk);
+            match Pin::new(&mut this.inner).poll_read(cx, &mut chunk_buf) {
+                Poll::Ready(Ok(())) => {
+                    if chunk_buf.filled().is_empty() {
+                        // EOF. Anything left in `raw` is a truncated frame that h2 couldn't use anyway.
+                        return Poll::Ready(Ok(()));
+                    }
+                    this.raw.extend_from_slice(chunk_buf.filled());
+                    this.process()?;
+                }
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for AuthorityRewriteStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+impl Connected for AuthorityRewriteStream<UnixStream> {
+    type ConnectInfo = UnixConnectInfo;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        UnixConnectInfo {
+            connection: self.connection,
+            uds: self.inner.connect_info(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    /// Build a raw HTTP/2 frame.
+    fn frame(frame_type: u8, flags: u8, stream_id: u8, payload: &[u8]) -> Vec<u8> {
+        let len = payload.len().to_be_bytes();
+        let mut res = len[len.len() - 3..].to_vec();
+        res.extend_from_slice(&[frame_type, flags, 0, 0, 0, stream_id]);
+        res.extend_from_slice(payload);
+        res
+    }
+
+    /// Read everything the wrapper produces for `input` and decode the header blocks.
+    async fn rewrite(input: Vec<u8>) -> (Vec<u8>, Vec<Vec<(Vec<u8>, Vec<u8>)>>) {
+        let mut stream = AuthorityRewriteStream::new(std::io::Cursor::new(input));
+        let mut output = Vec::new();
+        stream.read_to_end(&mut output).await.expect("read failed");
+
+        let mut decoder = Decoder::new();
+        let mut blocks = Vec::new();
+        let mut pos = PREFACE_LEN;
+        let mut fragment = Vec::new();
+        while pos < output.len() {
+            let len = usize::from(output[pos]) << 16
+                | usize::from(output[pos + 1]) << 8
+                | usize::from(output[pos + 2]);
+            let (frame_type, flags) = (output[pos + 3], output[pos + 4]);
+            let payload = &output[pos + FRAME_HEADER_LEN..pos + FRAME_HEADER_LEN + len];
+            if frame_type == FRAME_TYPE_HEADERS || frame_type == FRAME_TYPE_CONTINUATION {
+                fragment.extend_from_slice(payload);
+                if flags & FLAG_END_HEADERS != 0 {
+                    blocks.push(decoder.decode(&fragment).expect("bad block"));
+                    fragment.clear();
+                }
+            }
+            pos += FRAME_HEADER_LEN + len;
+        }
+        (output, blocks)
+    }
+
+    /// Test that a socket path authority is replaced and other headers survive,
+    /// including across CONTINUATION frames and dynamic table references.
+    #[tokio::test]
+    async fn test_rewrite_authority() {
+        let mut encoder = Encoder::new();
+        let headers: Vec<(&[u8], &[u8])> = vec![
+            (b":method", b"POST"),
+            (b":scheme", b"http"),
+            (b":path", b"/protocol.UI/Ping"),
+            (b":authority", b"/tmp/osui.sock"),
+            (b"content-type", b"application/grpc"),
+        ];
+        let first = encoder.encode(headers.clone());
+        // Second block mostly references the dynamic table populated by the first.
+        let second = encoder.encode(headers.clone());
+
+        let mut input = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n".to_vec();
+        input.extend(frame(0x4, 0, 0, &[])); // SETTINGS
+        input.extend(frame(FRAME_TYPE_HEADERS, FLAG_END_HEADERS, 1, &first));
+        input.extend(frame(FRAME_TYPE_HEADERS, 0, 3, &second[..2]));
+        input.extend(frame(
+            FRAME_TYPE_CONTINUATION,
+            FLAG_END_HEADERS,
+            3,
+            &second[2..],
+        ));
+
+        let (output, blocks) = rewrite(input).await;
+        assert!(output.starts_with(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n"));
+        assert_eq!(blocks.len(), 2);
+        for block in blocks {
+            let expected: Vec<(Vec<u8>, Vec<u8>)> = headers
+                .iter()
+                .map(|(name, value)| {
+                    let value = if *name == b":authority" {
+                        FALLBACK_AUTHORITY
+                    } else {
+                        value
+                    };
+                    (name.to_vec(), value.to_vec())
+                })
+                .collect();
+            assert_eq!(block, expected);
+        }
+    }
+
+    /// Test that valid authorities are left alone.
+    #[tokio::test]
+    async fn test_keep_valid_authority() {
+        let mut encoder = Encoder::new();
+        let headers: Vec<(&[u8], &[u8])> =
+            vec![(b":method", b"POST"), (b":authority", b"127.0.0.1:50051")];
+        let block = encoder.encode(headers.clone());
+
+        let mut input = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n".to_vec();
+        input.extend(frame(FRAME_TYPE_HEADERS, FLAG_END_HEADERS, 1, &block));
+
+        let (_, blocks) = rewrite(input).await;
+        let expected: Vec<(Vec<u8>, Vec<u8>)> = headers
+            .iter()
+            .map(|(name, value)| (name.to_vec(), value.to_vec()))
+            .collect();
+        assert_eq!(blocks, vec![expected]);
+    }
+
+    /// Test socket option parsing.
+    #[test]
+    fn test_socket_options() {
+        let opts = UnixSocketOptions::new(Some("0660"), Some("0:1000")).expect("valid");
+        assert_eq!(opts.mode, Some(0o660));
+        assert_eq!(opts.uid, Some(0));
+        assert_eq!(opts.gid, Some(1000));
+
+        let opts = UnixSocketOptions::new(None, Some(":1000")).expect("valid");
+        assert_eq!(opts.uid, None);
+        assert_eq!(opts.gid, Some(1000));
+
+        assert!(UnixSocketOptions::new(Some("999"), None).is_err());
+        assert!(UnixSocketOptions::new(None, Some("root:root")).is_err());
+    }
+}